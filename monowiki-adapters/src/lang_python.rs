@@ -7,7 +7,7 @@
 
 use crate::{
     build_body, build_frontmatter, build_output_path, is_test_path, walk_source_files,
-    AdapterError, AdapterOptions, AdapterOutput, DocAdapter, DocItem, DocKind, SourceLocation,
+    AdapterError, AdapterOptions, AdapterOutput, DocAdapter, DocItem, DocKind, SourceFile,
 };
 use std::path::Path;
 use tracing::{debug, warn};
@@ -47,29 +47,22 @@ impl PythonAdapter {
     ) -> Result<Vec<DocItem>, AdapterError> {
         let source = std::fs::read_to_string(path)?;
         let tree = self.parse(&source)?;
-        let source_bytes = source.as_bytes();
 
-        let rel_path = path
-            .strip_prefix(source_root)
-            .unwrap_or(path)
-            .to_path_buf();
+        let rel_path = path.strip_prefix(source_root).unwrap_or(path).to_path_buf();
 
         let module_path = derive_module_path(&rel_path);
         let include_private = options.get_bool("include_private", false);
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: &rel_path,
+            repo_url,
+            include_private,
+        };
 
         let mut items = Vec::new();
         let root = tree.root_node();
 
-        self.walk_module(
-            root,
-            source_bytes,
-            &rel_path,
-            repo_url,
-            &module_path,
-            include_private,
-            None,
-            &mut items,
-        );
+        self.walk_module(root, &file, &module_path, None, &mut items);
 
         debug!(
             "Extracted {} items from {}",
@@ -83,11 +76,8 @@ impl PythonAdapter {
     fn walk_module(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
         class_name: Option<&str>,
         items: &mut Vec<DocItem>,
     ) {
@@ -96,41 +86,20 @@ impl PythonAdapter {
         for child in node.children(&mut cursor) {
             match child.kind() {
                 "function_definition" => {
-                    if let Some(item) = self.extract_function(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        class_name,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_function(child, file, module_path, class_name)
+                    {
                         items.push(item);
                     }
                 }
                 "class_definition" => {
-                    if let Some((class_item, class_name_str)) = self.extract_class(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some((class_item, class_name_str)) =
+                        self.extract_class(child, file, module_path)
+                    {
                         items.push(class_item);
 
                         // Extract methods from the class body
                         if let Some(body) = child.child_by_field_name("body") {
-                            self.walk_module(
-                                body,
-                                source,
-                                rel_path,
-                                repo_url,
-                                module_path,
-                                include_private,
-                                Some(&class_name_str),
-                                items,
-                            );
+                            self.walk_module(body, file, module_path, Some(&class_name_str), items);
                         }
                     }
                 }
@@ -146,18 +115,15 @@ impl PythonAdapter {
     fn extract_function(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
         class_name: Option<&str>,
-        include_private: bool,
     ) -> Option<DocItem> {
         let name_node = node.child_by_field_name("name")?;
-        let func_name = node_text(name_node, source)?;
+        let func_name = node_text(name_node, file.source)?;
 
         // Skip private functions unless requested
-        if func_name.starts_with('_') && !func_name.starts_with("__") && !include_private {
+        if func_name.starts_with('_') && !func_name.starts_with("__") && !file.include_private {
             return None;
         }
 
@@ -171,10 +137,10 @@ impl PythonAdapter {
             return None;
         }
 
-        let docs = extract_docstring(node, source);
+        let docs = extract_docstring(node, file.source);
         let full_name = build_full_name(module_path, class_name, &func_name);
-        let signature = extract_function_signature(node, source)?;
-        let item_source = node_text(node, source)?;
+        let signature = extract_function_signature(node, file.source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -186,12 +152,7 @@ impl PythonAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: class_name.map(String::from),
         })
@@ -200,24 +161,21 @@ impl PythonAdapter {
     fn extract_class(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<(DocItem, String)> {
         let name_node = node.child_by_field_name("name")?;
-        let class_name = node_text(name_node, source)?;
+        let class_name = node_text(name_node, file.source)?;
 
         // Skip private classes unless requested
-        if class_name.starts_with('_') && !include_private {
+        if class_name.starts_with('_') && !file.include_private {
             return None;
         }
 
-        let docs = extract_docstring(node, source);
+        let docs = extract_docstring(node, file.source);
         let full_name = build_full_name(module_path, None, &class_name);
-        let signature = extract_class_signature(node, source)?;
-        let item_source = node_text(node, source)?;
+        let signature = extract_class_signature(node, file.source)?;
+        let item_source = node_text(node, file.source)?;
 
         let item = DocItem {
             name: full_name,
@@ -225,12 +183,7 @@ impl PythonAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         };
@@ -345,31 +298,29 @@ fn extract_docstring(node: Node, source: &[u8]) -> Option<String> {
     let body = node.child_by_field_name("body")?;
     let mut cursor = body.walk();
 
-    for child in body.children(&mut cursor) {
-        if child.kind() == "expression_statement" {
-            let mut inner_cursor = child.walk();
-            for inner in child.children(&mut inner_cursor) {
-                if inner.kind() == "string" {
-                    let text = node_text(inner, source)?;
-                    // Remove quotes and clean up
-                    let cleaned = text
-                        .trim_start_matches("\"\"\"")
-                        .trim_start_matches("'''")
-                        .trim_start_matches('"')
-                        .trim_start_matches('\'')
-                        .trim_end_matches("\"\"\"")
-                        .trim_end_matches("'''")
-                        .trim_end_matches('"')
-                        .trim_end_matches('\'')
-                        .trim();
-                    if !cleaned.is_empty() {
-                        return Some(cleaned.to_string());
-                    }
+    // Only check the first statement
+    let child = body.children(&mut cursor).next()?;
+    if child.kind() == "expression_statement" {
+        let mut inner_cursor = child.walk();
+        for inner in child.children(&mut inner_cursor) {
+            if inner.kind() == "string" {
+                let text = node_text(inner, source)?;
+                // Remove quotes and clean up
+                let cleaned = text
+                    .trim_start_matches("\"\"\"")
+                    .trim_start_matches("'''")
+                    .trim_start_matches('"')
+                    .trim_start_matches('\'')
+                    .trim_end_matches("\"\"\"")
+                    .trim_end_matches("'''")
+                    .trim_end_matches('"')
+                    .trim_end_matches('\'')
+                    .trim();
+                if !cleaned.is_empty() {
+                    return Some(cleaned.to_string());
                 }
             }
         }
-        // Only check the first statement
-        break;
     }
 
     None
@@ -411,7 +362,6 @@ fn extract_class_signature(node: Node, source: &[u8]) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
 
     fn make_adapter() -> PythonAdapter {
         PythonAdapter::new()
@@ -439,17 +389,18 @@ def add(a: int, b: int) -> int:
     return a + b
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("math.py");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("math.py"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
         adapter.walk_module(
             tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
+            &file,
             &["math".to_string()],
-            false,
             None,
             &mut items,
         );
@@ -478,17 +429,18 @@ class Person:
         return f"Hello, I'm {self.name}"
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("person.py");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("person.py"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
         adapter.walk_module(
             tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
+            &file,
             &["person".to_string()],
-            false,
             None,
             &mut items,
         );
@@ -512,20 +464,15 @@ def _private_helper():
     pass
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.py");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.py"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_module(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            false,
-            None,
-            &mut items,
-        );
+        adapter.walk_module(tree.root_node(), &file, &[], None, &mut items);
 
         assert_eq!(items.len(), 0);
     }
@@ -8,7 +8,7 @@
 
 use crate::{
     build_body, build_frontmatter, build_output_path, is_test_path, walk_source_files,
-    AdapterError, AdapterOptions, AdapterOutput, DocAdapter, DocItem, DocKind, SourceLocation,
+    AdapterError, AdapterOptions, AdapterOutput, DocAdapter, DocItem, DocKind, SourceFile,
 };
 use std::path::Path;
 use tracing::{debug, warn};
@@ -50,30 +50,24 @@ impl RustAdapter {
     ) -> Result<Vec<DocItem>, AdapterError> {
         let source = std::fs::read_to_string(path)?;
         let tree = self.parse(&source)?;
-        let source_bytes = source.as_bytes();
 
-        let rel_path = path
-            .strip_prefix(source_root)
-            .unwrap_or(path)
-            .to_path_buf();
+        let rel_path = path.strip_prefix(source_root).unwrap_or(path).to_path_buf();
 
         // Derive module path from file path
         let module_path = derive_module_path(&rel_path);
         let include_private = options.get_bool("include_private", false);
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: &rel_path,
+            repo_url,
+            include_private,
+        };
 
         let mut items = Vec::new();
         let root = tree.root_node();
 
         // Walk top-level declarations
-        self.walk_top_level(
-            root,
-            source_bytes,
-            &rel_path,
-            repo_url,
-            &module_path,
-            include_private,
-            &mut items,
-        );
+        self.walk_top_level(root, &file, &module_path, &mut items);
 
         debug!(
             "Extracted {} items from {}",
@@ -88,11 +82,8 @@ impl RustAdapter {
     fn walk_top_level(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
         items: &mut Vec<DocItem>,
     ) {
         let mut cursor = node.walk();
@@ -100,100 +91,41 @@ impl RustAdapter {
         for child in node.children(&mut cursor) {
             match child.kind() {
                 "function_item" => {
-                    if let Some(item) = self.extract_function(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        None,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_function(child, file, module_path, None) {
                         items.push(item);
                     }
                 }
                 "struct_item" => {
-                    if let Some(item) = self.extract_struct(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_struct(child, file, module_path) {
                         items.push(item);
                     }
                 }
                 "enum_item" => {
-                    if let Some(item) = self.extract_enum(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_enum(child, file, module_path) {
                         items.push(item);
                     }
                 }
                 "trait_item" => {
-                    if let Some(item) = self.extract_trait(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_trait(child, file, module_path) {
                         items.push(item);
                     }
                 }
                 "impl_item" => {
-                    self.extract_impl_methods(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                        items,
-                    );
+                    self.extract_impl_methods(child, file, module_path, items);
                 }
                 "type_item" => {
-                    if let Some(item) = self.extract_type_alias(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_type_alias(child, file, module_path) {
                         items.push(item);
                     }
                 }
                 "const_item" | "static_item" => {
-                    if let Some(item) = self.extract_constant(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_constant(child, file, module_path) {
                         items.push(item);
                     }
                 }
                 "mod_item" => {
                     // Handle inline modules
-                    self.extract_mod_item(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                        items,
-                    );
+                    self.extract_mod_item(child, file, module_path, items);
                 }
                 _ => {}
             }
@@ -204,39 +136,36 @@ impl RustAdapter {
     fn extract_function(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
         container: Option<&str>,
-        include_private: bool,
     ) -> Option<DocItem> {
         // Get visibility
-        let is_pub = has_visibility(node, source);
-        if !is_pub && !include_private {
+        let is_pub = has_visibility(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         // Get function name
         let name_node = node.child_by_field_name("name")?;
-        let func_name = node_text(name_node, source)?;
+        let func_name = node_text(name_node, file.source)?;
 
         // Skip test functions
-        if func_name.starts_with("test_") || has_test_attribute(node, source) {
+        if func_name.starts_with("test_") || has_test_attribute(node, file.source) {
             return None;
         }
 
         // Get doc comments
-        let docs = extract_doc_comments(node, source);
+        let docs = extract_doc_comments(node, file.source);
 
         // Build full name
         let full_name = build_full_name(module_path, container, &func_name);
 
         // Get signature (function header without body)
-        let signature = extract_function_signature(node, source)?;
+        let signature = extract_function_signature(node, file.source)?;
 
         // Get source code
-        let item_source = node_text(node, source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -248,12 +177,7 @@ impl RustAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: container.map(String::from),
         })
@@ -263,23 +187,20 @@ impl RustAdapter {
     fn extract_struct(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = has_visibility(node, source);
-        if !is_pub && !include_private {
+        let is_pub = has_visibility(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         let name_node = node.child_by_field_name("name")?;
-        let struct_name = node_text(name_node, source)?;
-        let docs = extract_doc_comments(node, source);
+        let struct_name = node_text(name_node, file.source)?;
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, None, &struct_name);
-        let signature = extract_struct_signature(node, source)?;
-        let item_source = node_text(node, source)?;
+        let signature = extract_struct_signature(node, file.source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -287,12 +208,7 @@ impl RustAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         })
@@ -302,23 +218,20 @@ impl RustAdapter {
     fn extract_enum(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = has_visibility(node, source);
-        if !is_pub && !include_private {
+        let is_pub = has_visibility(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         let name_node = node.child_by_field_name("name")?;
-        let enum_name = node_text(name_node, source)?;
-        let docs = extract_doc_comments(node, source);
+        let enum_name = node_text(name_node, file.source)?;
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, None, &enum_name);
-        let signature = extract_enum_signature(node, source)?;
-        let item_source = node_text(node, source)?;
+        let signature = extract_enum_signature(node, file.source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -326,12 +239,7 @@ impl RustAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         })
@@ -341,22 +249,19 @@ impl RustAdapter {
     fn extract_trait(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = has_visibility(node, source);
-        if !is_pub && !include_private {
+        let is_pub = has_visibility(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         let name_node = node.child_by_field_name("name")?;
-        let trait_name = node_text(name_node, source)?;
-        let docs = extract_doc_comments(node, source);
+        let trait_name = node_text(name_node, file.source)?;
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, None, &trait_name);
-        let item_source = node_text(node, source)?;
+        let item_source = node_text(node, file.source)?;
 
         // For traits, use the whole definition as signature
         let signature = item_source.clone();
@@ -367,12 +272,7 @@ impl RustAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         })
@@ -382,22 +282,19 @@ impl RustAdapter {
     fn extract_type_alias(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = has_visibility(node, source);
-        if !is_pub && !include_private {
+        let is_pub = has_visibility(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         let name_node = node.child_by_field_name("name")?;
-        let type_name = node_text(name_node, source)?;
-        let docs = extract_doc_comments(node, source);
+        let type_name = node_text(name_node, file.source)?;
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, None, &type_name);
-        let item_source = node_text(node, source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -405,12 +302,7 @@ impl RustAdapter {
             docs,
             signature: item_source.clone(),
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         })
@@ -420,22 +312,19 @@ impl RustAdapter {
     fn extract_constant(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = has_visibility(node, source);
-        if !is_pub && !include_private {
+        let is_pub = has_visibility(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         let name_node = node.child_by_field_name("name")?;
-        let const_name = node_text(name_node, source)?;
-        let docs = extract_doc_comments(node, source);
+        let const_name = node_text(name_node, file.source)?;
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, None, &const_name);
-        let item_source = node_text(node, source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -443,12 +332,7 @@ impl RustAdapter {
             docs,
             signature: item_source.clone(),
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         })
@@ -458,15 +342,12 @@ impl RustAdapter {
     fn extract_impl_methods(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
         items: &mut Vec<DocItem>,
     ) {
         // Get the type being implemented
-        let type_name = get_impl_type_name(node, source);
+        let type_name = get_impl_type_name(node, file.source);
 
         // Find the declaration_list (impl body)
         let body = match node.child_by_field_name("body") {
@@ -477,15 +358,9 @@ impl RustAdapter {
         let mut cursor = body.walk();
         for child in body.children(&mut cursor) {
             if child.kind() == "function_item" {
-                if let Some(item) = self.extract_function(
-                    child,
-                    source,
-                    rel_path,
-                    repo_url,
-                    module_path,
-                    type_name.as_deref(),
-                    include_private,
-                ) {
+                if let Some(item) =
+                    self.extract_function(child, file, module_path, type_name.as_deref())
+                {
                     items.push(item);
                 }
             }
@@ -496,11 +371,8 @@ impl RustAdapter {
     fn extract_mod_item(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         parent_module_path: &[String],
-        include_private: bool,
         items: &mut Vec<DocItem>,
     ) {
         // Get module name
@@ -508,7 +380,7 @@ impl RustAdapter {
             Some(n) => n,
             None => return,
         };
-        let mod_name = match node_text(name_node, source) {
+        let mod_name = match node_text(name_node, file.source) {
             Some(n) => n,
             None => return,
         };
@@ -524,15 +396,7 @@ impl RustAdapter {
         };
 
         // Walk the module body
-        self.walk_top_level(
-            body,
-            source,
-            rel_path,
-            repo_url,
-            &new_module_path,
-            include_private,
-            items,
-        );
+        self.walk_top_level(body, file, &new_module_path, items);
     }
 }
 
@@ -895,19 +759,15 @@ pub fn add(a: i32, b: i32) -> i32 {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("math.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("math.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["math".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["math".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         let item = &items[0];
@@ -925,19 +785,15 @@ pub fn add(a: i32, b: i32) -> i32 {
 fn private_helper() {}
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            false, // include_private = false
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         assert_eq!(items.len(), 0);
     }
@@ -950,19 +806,15 @@ fn private_helper() {}
 fn private_helper() {}
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: true,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            true, // include_private = true
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].name, "private_helper");
@@ -981,19 +833,15 @@ pub struct Config {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("config.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("config.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["config".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["config".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         let item = &items[0];
@@ -1016,19 +864,15 @@ pub enum NoteType {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("note.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("note.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["note".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["note".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         let item = &items[0];
@@ -1047,17 +891,18 @@ pub trait MarkdownProcessor {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("markdown.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("markdown.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
         adapter.walk_top_level(
             tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
+            &file,
             &["markdown".to_string()],
-            false,
             &mut items,
         );
 
@@ -1088,19 +933,15 @@ impl Config {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("config.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("config.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["config".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["config".to_string()], &mut items);
 
         // Should have: Config struct + 2 methods
         assert_eq!(items.len(), 3);
@@ -1125,19 +966,15 @@ impl Config {
 pub type Result<T> = std::result::Result<T, Error>;
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("error.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("error.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["error".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["error".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         let item = &items[0];
@@ -1153,19 +990,15 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub const DEFAULT_PORT: u16 = 8080;
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("server.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("server.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["server".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["server".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         let item = &items[0];
@@ -1185,19 +1018,15 @@ fn test_something() {}
 fn another_test() {}
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: true,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            true, // include private to check test filtering
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         // Only real_function should be included (tests filtered)
         assert_eq!(items.len(), 1);
@@ -1214,19 +1043,15 @@ fn another_test() {}
 pub fn documented_function() {}
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         assert_eq!(items.len(), 1);
         let docs = items[0].docs.as_ref().unwrap();
@@ -1244,19 +1069,15 @@ pub fn my_func() {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("test.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("test.rs"),
+            repo_url: Some("https://github.com/user/repo"),
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            Some("https://github.com/user/repo"),
-            &["test".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["test".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         let loc = &items[0].location;
@@ -1280,19 +1101,15 @@ pub mod inner {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("outer.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("outer.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &["outer".to_string()],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &["outer".to_string()], &mut items);
 
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].name, "outer::inner::inner_func");
@@ -1316,17 +1133,18 @@ impl<T> Container<T> {
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("container.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("container.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
         adapter.walk_top_level(
             tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
+            &file,
             &["container".to_string()],
-            false,
             &mut items,
         );
 
@@ -1360,19 +1178,15 @@ where
 }
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         assert_eq!(items.len(), 1);
         let sig = &items[0].signature;
@@ -1392,19 +1206,15 @@ where
 pub struct Wrapper(pub String);
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].kind, DocKind::Struct);
@@ -1419,19 +1229,15 @@ pub struct Wrapper(pub String);
 pub struct Marker;
 "#;
         let tree = adapter.parse(source).unwrap();
-        let source_bytes = source.as_bytes();
-        let rel_path = PathBuf::from("lib.rs");
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: Path::new("lib.rs"),
+            repo_url: None,
+            include_private: false,
+        };
 
         let mut items = Vec::new();
-        adapter.walk_top_level(
-            tree.root_node(),
-            source_bytes,
-            &rel_path,
-            None,
-            &[],
-            false,
-            &mut items,
-        );
+        adapter.walk_top_level(tree.root_node(), &file, &[], &mut items);
 
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].kind, DocKind::Struct);
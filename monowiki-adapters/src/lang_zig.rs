@@ -8,7 +8,7 @@
 
 use crate::{
     build_body, build_frontmatter, build_output_path, is_test_path, walk_source_files,
-    AdapterError, AdapterOptions, AdapterOutput, DocAdapter, DocItem, DocKind, SourceLocation,
+    AdapterError, AdapterOptions, AdapterOutput, DocAdapter, DocItem, DocKind, SourceFile,
 };
 use std::path::Path;
 use tracing::{debug, warn};
@@ -48,29 +48,22 @@ impl ZigAdapter {
     ) -> Result<Vec<DocItem>, AdapterError> {
         let source = std::fs::read_to_string(path)?;
         let tree = self.parse(&source)?;
-        let source_bytes = source.as_bytes();
 
-        let rel_path = path
-            .strip_prefix(source_root)
-            .unwrap_or(path)
-            .to_path_buf();
+        let rel_path = path.strip_prefix(source_root).unwrap_or(path).to_path_buf();
 
         let module_path = derive_module_path(&rel_path);
         let include_private = options.get_bool("include_private", false);
+        let file = SourceFile {
+            source: source.as_bytes(),
+            rel_path: &rel_path,
+            repo_url,
+            include_private,
+        };
 
         let mut items = Vec::new();
         let root = tree.root_node();
 
-        self.walk_declarations(
-            root,
-            source_bytes,
-            &rel_path,
-            repo_url,
-            &module_path,
-            include_private,
-            None,
-            &mut items,
-        );
+        self.walk_declarations(root, &file, &module_path, None, &mut items);
 
         debug!(
             "Extracted {} items from {}",
@@ -84,11 +77,8 @@ impl ZigAdapter {
     fn walk_declarations(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
         container: Option<&str>,
         items: &mut Vec<DocItem>,
     ) {
@@ -97,93 +87,37 @@ impl ZigAdapter {
         for child in node.children(&mut cursor) {
             match child.kind() {
                 "FnDecl" => {
-                    if let Some(item) = self.extract_function(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        container,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_function(child, file, module_path, container) {
                         items.push(item);
                     }
                 }
                 "VarDecl" => {
-                    if let Some(item) = self.extract_var_decl(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some(item) = self.extract_var_decl(child, file, module_path) {
                         items.push(item);
                     }
                 }
                 "TopLevelDecl" => {
                     // Recurse into top-level declarations
-                    self.walk_declarations(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                        container,
-                        items,
-                    );
+                    self.walk_declarations(child, file, module_path, container, items);
                 }
                 "Decl" => {
                     // Generic declaration wrapper - recurse
-                    self.walk_declarations(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                        container,
-                        items,
-                    );
+                    self.walk_declarations(child, file, module_path, container, items);
                 }
                 "ContainerDecl" | "ContainerDeclAuto" => {
                     // This could be a struct, enum, or union
-                    if let Some((type_item, type_name)) = self.extract_container_type(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                    ) {
+                    if let Some((type_item, type_name)) =
+                        self.extract_container_type(child, file, module_path)
+                    {
                         items.push(type_item);
 
                         // Walk the container's members
-                        self.walk_declarations(
-                            child,
-                            source,
-                            rel_path,
-                            repo_url,
-                            module_path,
-                            include_private,
-                            Some(&type_name),
-                            items,
-                        );
+                        self.walk_declarations(child, file, module_path, Some(&type_name), items);
                     }
                 }
                 _ => {
                     // Recurse to find nested declarations
-                    self.walk_declarations(
-                        child,
-                        source,
-                        rel_path,
-                        repo_url,
-                        module_path,
-                        include_private,
-                        container,
-                        items,
-                    );
+                    self.walk_declarations(child, file, module_path, container, items);
                 }
             }
         }
@@ -192,30 +126,27 @@ impl ZigAdapter {
     fn extract_function(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
         container: Option<&str>,
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = is_public(node, source);
-        if !is_pub && !include_private {
+        let is_pub = is_public(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         // Find the function name
-        let func_name = find_fn_name(node, source)?;
+        let func_name = find_fn_name(node, file.source)?;
 
         // Skip test functions
         if func_name.starts_with("test") {
             return None;
         }
 
-        let docs = extract_doc_comments(node, source);
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, container, &func_name);
-        let signature = extract_fn_signature(node, source)?;
-        let item_source = node_text(node, source)?;
+        let signature = extract_fn_signature(node, file.source)?;
+        let item_source = node_text(node, file.source)?;
 
         Some(DocItem {
             name: full_name,
@@ -227,12 +158,7 @@ impl ZigAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: container.map(String::from),
         })
@@ -241,26 +167,23 @@ impl ZigAdapter {
     fn extract_var_decl(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        include_private: bool,
     ) -> Option<DocItem> {
-        let is_pub = is_public(node, source);
-        if !is_pub && !include_private {
+        let is_pub = is_public(node, file.source);
+        if !is_pub && !file.include_private {
             return None;
         }
 
         // Find variable/const name
-        let var_name = find_var_name(node, source)?;
-        let docs = extract_doc_comments(node, source);
+        let var_name = find_var_name(node, file.source)?;
+        let docs = extract_doc_comments(node, file.source);
         let full_name = build_full_name(module_path, None, &var_name);
-        let signature = node_text(node, source)?.lines().next()?.to_string();
-        let item_source = node_text(node, source)?;
+        let signature = node_text(node, file.source)?.lines().next()?.to_string();
+        let item_source = node_text(node, file.source)?;
 
         // Check if this is a type definition (struct, enum, etc.)
-        let kind = if is_type_definition(node, source) {
+        let kind = if is_type_definition(node, file.source) {
             DocKind::Type
         } else {
             DocKind::Constant
@@ -272,12 +195,7 @@ impl ZigAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(node.start_position().row as u32 + 1),
-                end_line: Some(node.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(node.start_position().row, node.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         })
@@ -286,11 +204,8 @@ impl ZigAdapter {
     fn extract_container_type(
         &self,
         node: Node,
-        source: &[u8],
-        rel_path: &Path,
-        repo_url: Option<&str>,
+        file: &SourceFile,
         module_path: &[String],
-        _include_private: bool,
     ) -> Option<(DocItem, String)> {
         // Container types in Zig are typically assigned to const variables
         // The parent VarDecl has the name
@@ -299,14 +214,14 @@ impl ZigAdapter {
             return None;
         }
 
-        let type_name = find_var_name(parent, source)?;
-        let docs = extract_doc_comments(parent, source);
+        let type_name = find_var_name(parent, file.source)?;
+        let docs = extract_doc_comments(parent, file.source);
         let full_name = build_full_name(module_path, None, &type_name);
 
         // Determine if it's struct, enum, or union
-        let kind = determine_container_kind(node, source);
-        let signature = extract_container_signature(node, source, &type_name)?;
-        let item_source = node_text(parent, source)?;
+        let kind = determine_container_kind(node, file.source);
+        let signature = extract_container_signature(node, file.source, &type_name)?;
+        let item_source = node_text(parent, file.source)?;
 
         let item = DocItem {
             name: full_name,
@@ -314,12 +229,7 @@ impl ZigAdapter {
             docs,
             signature,
             source: Some(item_source),
-            location: SourceLocation {
-                file: rel_path.to_path_buf(),
-                start_line: Some(parent.start_position().row as u32 + 1),
-                end_line: Some(parent.end_position().row as u32 + 1),
-                repo_url: repo_url.map(String::from),
-            },
+            location: file.location(parent.start_position().row, parent.end_position().row),
             module_path: module_path.to_vec(),
             container: None,
         };
@@ -560,7 +470,6 @@ fn extract_container_signature(node: Node, source: &[u8], name: &str) -> Option<
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
 
     fn make_adapter() -> ZigAdapter {
         ZigAdapter::new()
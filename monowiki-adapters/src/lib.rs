@@ -292,6 +292,7 @@ pub fn adapter_by_name(name: &str) -> Option<Box<dyn DocAdapter>> {
 }
 
 /// List names of all available adapters (based on enabled features)
+#[allow(clippy::vec_init_then_push)]
 pub fn available_adapters() -> Vec<&'static str> {
     let mut adapters = Vec::new();
 
@@ -426,6 +427,31 @@ pub fn build_output_path(item: &DocItem) -> PathBuf {
     path
 }
 
+/// A parsed source file whose documented items are being extracted
+#[derive(Debug, Clone, Copy)]
+pub struct SourceFile<'a> {
+    /// Source text
+    pub source: &'a [u8],
+    /// Path relative to the source root
+    pub rel_path: &'a Path,
+    /// Repository URL for source links
+    pub repo_url: Option<&'a str>,
+    /// Whether private items are documented too
+    pub include_private: bool,
+}
+
+impl SourceFile<'_> {
+    /// Location of the lines spanned by `start_row..=end_row` (0-indexed)
+    pub fn location(&self, start_row: usize, end_row: usize) -> SourceLocation {
+        SourceLocation {
+            file: self.rel_path.to_path_buf(),
+            start_line: Some(start_row as u32 + 1),
+            end_line: Some(end_row as u32 + 1),
+            repo_url: self.repo_url.map(String::from),
+        }
+    }
+}
+
 /// Walk source files matching given extensions
 pub fn walk_source_files<'a>(
     root: &'a Path,
@@ -1,7 +1,7 @@
 //! Shared JSON schema helpers for agent-facing commands and APIs.
 
 use chrono::NaiveDate;
-use monowiki_core::{Frontmatter, Note, SearchEntry};
+use monowiki_core::{Frontmatter, Mention, Note, SearchEntry, SiteIndex};
 use serde::Serialize;
 
 pub const SCHEMA_VERSION: &str = "2024-11-llm-v1";
@@ -97,6 +97,72 @@ pub struct GraphPathData {
     pub path: Option<Vec<String>>,
 }
 
+#[derive(Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+    pub url: String,
+}
+
+#[derive(Serialize)]
+pub struct TagsData {
+    pub total: usize,
+    pub tags: Vec<TagCount>,
+}
+
+#[derive(Serialize)]
+pub struct MentionEntry {
+    pub slug: String,
+    pub title: String,
+    /// Page URL, pointing at the section containing the mention
+    pub url: String,
+    pub section: String,
+    pub text: String,
+    pub snippet: String,
+}
+
+#[derive(Serialize)]
+pub struct MentionsData {
+    pub slug: String,
+    pub title: String,
+    pub total: usize,
+    pub mentions: Vec<MentionEntry>,
+}
+
+pub fn mentions_payload(
+    target: &Note,
+    mentions: Vec<Mention>,
+    site_index: &SiteIndex,
+    base_url: &str,
+) -> MentionsData {
+    let mentions: Vec<MentionEntry> = mentions
+        .into_iter()
+        .map(|mention| {
+            let page = site_index
+                .find_by_slug(&mention.slug)
+                .map(|note| note.url_with_base(base_url))
+                .unwrap_or_default();
+            MentionEntry {
+                url: match &mention.anchor {
+                    Some(anchor) => format!("{page}#{anchor}"),
+                    None => page,
+                },
+                slug: mention.slug,
+                title: mention.title,
+                section: mention.section,
+                text: mention.text,
+                snippet: mention.snippet,
+            }
+        })
+        .collect();
+    MentionsData {
+        slug: target.slug.clone(),
+        title: target.title.clone(),
+        total: mentions.len(),
+        mentions,
+    }
+}
+
 pub fn note_to_payload(note: &Note, base_url: &str, backlinks: Vec<String>) -> NoteData {
     NoteData {
         slug: note.slug.clone(),
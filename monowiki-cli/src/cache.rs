@@ -2,13 +2,14 @@
 
 use anyhow::{Context, Result};
 use chrono::Utc;
-use monowiki_core::{Config, SiteBuilder, SiteIndex};
+use monowiki_core::{BuildManifest, Config, SiteBuilder, SiteIndex};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 const CACHE_VERSION: &str = "1";
 const CACHE_FILENAME: &str = ".site_index.json";
+const MANIFEST_FILENAME: &str = ".build_manifest.json";
 
 #[derive(Serialize, Deserialize)]
 struct CachedSiteIndex {
@@ -58,6 +59,35 @@ pub fn load_cached_site_index(config: &Config) -> Result<Option<SiteIndex>> {
     }
 }
 
+fn manifest_path(config: &Config) -> PathBuf {
+    config.output_dir().join(MANIFEST_FILENAME)
+}
+
+/// Persist the build manifest used for incremental rebuilds.
+pub fn write_build_manifest(config: &Config, manifest: &BuildManifest) -> Result<()> {
+    let path = manifest_path(config);
+    let json = serde_json::to_vec(manifest).context("Failed to serialize build manifest")?;
+    fs::write(&path, json).with_context(|| format!("Failed to write manifest {:?}", path))?;
+    Ok(())
+}
+
+/// Load the build manifest if present and readable.
+pub fn load_build_manifest(config: &Config) -> Result<Option<BuildManifest>> {
+    let path = manifest_path(config);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let data = fs::read(&path).with_context(|| format!("Failed to read manifest {:?}", path))?;
+    match serde_json::from_slice::<BuildManifest>(&data) {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(err) => {
+            tracing::warn!("Failed to parse build manifest: {}", err);
+            Ok(None)
+        }
+    }
+}
+
 /// Load config and site index, preferring the cache but falling back to a rebuild.
 pub fn load_or_build_site_index(config_path: &Path) -> Result<(Config, SiteIndex)> {
     let config = Config::from_file(config_path).context("Failed to load configuration")?;
@@ -1,18 +1,24 @@
 //! Build command implementation.
 
 use crate::cache;
+use crate::images::ImageSet;
 use anyhow::{Context, Result};
 use askama::Template;
 use chrono::{Datelike, NaiveDate};
 use include_dir::{include_dir, Dir};
-use monowiki_core::{Config, SiteBuilder};
+use monowiki_core::config::{MathRender, PageTemplate};
+use monowiki_core::dates::ParsedDate;
+use monowiki_core::markdown::{mathjax_macros, needs_mathjax};
+use monowiki_core::{AssetIndex, Config, MentionIndex, PreviousBuild, SiteBuilder};
 use monowiki_render::{
-    ApiTemplate, BacklinkEntry, CommentRender, DirectoryNode, FileNode, NotFoundTemplate,
-    PostTemplate,
+    ApiTemplate, BacklinkEntry, CommentRender, DirectoryNode, FileNode, MentionEntry,
+    NotFoundTemplate, NoteEntry, PostTemplate, RedirectTemplate, TagEntry, TagIndexTemplate,
+    TagTemplate,
 };
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 // Embed the theme bundle at compile time so it's available after cargo install
@@ -21,20 +27,37 @@ static THEME_BUNDLE: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/../theme/dist")
 // Embed static assets (CSS, fonts) at compile time
 static STATIC_ASSETS: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/../static");
 
+/// Options controlling a site build
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Ignore the build manifest and re-render every note
+    pub force: bool,
+    /// Moment scheduled / expiring notes are checked against (defaults to now)
+    pub as_of: Option<ParsedDate>,
+}
+
 /// Build the static site (writes output) and discard the in-memory index
-pub fn build_site(config_path: &Path) -> Result<()> {
-    build_site_with_index(config_path).map(|_| ())
+pub fn build_site(config_path: &Path, opts: BuildOptions) -> Result<()> {
+    tracing::info!("Loading config from {:?}", config_path);
+    let config = Config::from_file(config_path).context("Failed to load configuration")?;
+    build_site_with_config(config, &opts).map(|_| ())
 }
 
 /// Build the static site and return the in-memory index alongside the loaded config
 pub fn build_site_with_index(config_path: &Path) -> Result<(Config, monowiki_core::SiteIndex)> {
     tracing::info!("Loading config from {:?}", config_path);
     let config = Config::from_file(config_path).context("Failed to load configuration")?;
-    build_site_with_config(config)
+    build_site_with_config(config, &BuildOptions::default())
 }
 
 /// Build the site from an already loaded config, writing output and returning the index.
-pub fn build_site_with_config(config: Config) -> Result<(Config, monowiki_core::SiteIndex)> {
+///
+/// Unless `opts.force` is set, the previous site index and build manifest are
+/// used to skip re-rendering unchanged notes and rewriting unchanged pages.
+pub fn build_site_with_config(
+    config: Config,
+    opts: &BuildOptions,
+) -> Result<(Config, monowiki_core::SiteIndex)> {
     let base_url = config.normalized_base_url();
 
     tracing::info!("Building site: {}", config.site.title);
@@ -45,9 +68,30 @@ pub fn build_site_with_config(config: Config) -> Result<(Config, monowiki_core::
             .context("Failed to run documentation adapters")?;
     }
 
+    // Load previous build state for incremental rendering
+    let previous_state = if opts.force {
+        None
+    } else {
+        load_previous_build(&config)
+    };
+    let previous = previous_state
+        .as_ref()
+        .map(|(index, manifest)| PreviousBuild { index, manifest });
+
     // Build the site
-    let builder = SiteBuilder::new(config.clone());
-    let site_index = builder.build().context("Failed to build site")?;
+    let mut builder = SiteBuilder::new(config.clone());
+    if let Some(as_of) = opts.as_of {
+        builder = builder.as_of(as_of);
+    }
+    let build = builder
+        .build_incremental(previous)
+        .context("Failed to build site")?;
+    let mut site_index = build.index;
+    let mut manifest = build.manifest;
+
+    if config.git_dates {
+        apply_git_dates(&config, &mut site_index);
+    }
 
     tracing::info!("Parsed {} notes", site_index.notes.len());
 
@@ -55,22 +99,69 @@ pub fn build_site_with_config(config: Config) -> Result<(Config, monowiki_core::
     let output_dir = config.output_dir();
     fs::create_dir_all(&output_dir).context("Failed to create output directory")?;
 
-    // Render individual note pages
-    for note in &site_index.notes {
-        // Skip drafts
-        if note.is_draft() {
-            tracing::debug!("Skipping draft: {}", note.title);
-            continue;
-        }
-
-        // Render comment pages with thread context
-        if note.note_type == monowiki_core::NoteType::Comment {
-            render_comment_page(&config, note, &site_index, &base_url)?;
-            continue;
-        }
+    // Render individual note pages in parallel, only rewriting files whose
+    // content changed. Results are recorded in note order.
+    let mut writer = OutputWriter::new(
+        &output_dir,
+        previous_state
+            .as_ref()
+            .map(|(_, manifest)| &manifest.outputs),
+    );
+    let tag_counts = site_index.tag_counts();
+    let mention_index = config
+        .enable_mentions
+        .then(|| MentionIndex::new(&site_index));
+    let assets = AssetIndex::scan(&config);
+    let images = ImageSet::build(&config, &assets, &base_url)
+        .context("Failed to generate responsive images")?;
+    for variant in images.variants() {
+        let page = writer.copy(&variant.path, &variant.cached, &variant.hash)?;
+        writer.record(variant.path.clone(), page);
+    }
+    let pages: Vec<(String, PageWrite)> = site_index
+        .notes
+        .par_iter()
+        .filter(|note| {
+            // Skip drafts
+            if note.is_draft() {
+                tracing::debug!("Skipping draft: {}", note.title);
+            }
+            !note.is_draft()
+        })
+        .map(|note| {
+            // Render comment pages with thread context
+            let html = if note.note_type == monowiki_core::NoteType::Comment {
+                render_comment_page(&config, note, &site_index, &tag_counts, &base_url)?
+            } else {
+                render_note_page(
+                    &config,
+                    note,
+                    &site_index,
+                    &tag_counts,
+                    mention_index.as_ref(),
+                    &base_url,
+                )?
+            };
+            let rel_path = note.output_rel_path();
+            let html = images.rewrite_html(&html, &rel_path);
 
-        render_note_page(&config, note, &site_index, &base_url)?;
+            let page = writer.write(&rel_path, &html)?;
+            tracing::debug!("Rendered: {}", note.slug);
+            Ok((rel_path, page))
+        })
+        .collect::<Result<_>>()?;
+    for (rel_path, page) in pages {
+        writer.record(rel_path, page);
     }
+    render_tag_pages(&config, &site_index, &base_url, &mut writer)?;
+    render_redirects(&config, &site_index, &base_url, &mut writer)?;
+    writer.remove_stale()?;
+    tracing::info!(
+        "Wrote {} pages ({} unchanged)",
+        writer.written,
+        writer.unchanged
+    );
+    manifest.outputs = writer.into_hashes();
 
     // Render 404 page
     render_404_page(&config, &base_url)?;
@@ -102,7 +193,7 @@ pub fn build_site_with_config(config: Config) -> Result<(Config, monowiki_core::
     copy_assets(&config)?;
 
     // Copy vault assets (images, PDFs, etc.) preserving directory structure
-    copy_vault_assets(&config)?;
+    copy_vault_assets(&config, &assets)?;
 
     let non_draft_count = site_index.notes.iter().filter(|n| !n.is_draft()).count();
 
@@ -112,17 +203,164 @@ pub fn build_site_with_config(config: Config) -> Result<(Config, monowiki_core::
     if let Err(err) = cache::write_site_index_cache(&config, &site_index) {
         tracing::warn!("Failed to write site index cache: {}", err);
     }
+    if let Err(err) = cache::write_build_manifest(&config, &manifest) {
+        tracing::warn!("Failed to write build manifest: {}", err);
+    }
 
     Ok((config, site_index))
 }
 
+/// Fill in missing `date` / `updated` from the first and last git commit
+/// touching each note.
+fn apply_git_dates(config: &Config, site_index: &mut monowiki_core::SiteIndex) {
+    let dates = match super::changes::git_file_dates(&config.vault_dir()) {
+        Ok(dates) => dates,
+        Err(err) => {
+            tracing::warn!("git_dates enabled but git history is unavailable: {}", err);
+            return;
+        }
+    };
+
+    for note in &mut site_index.notes {
+        let Some((first, last)) = note
+            .source_path
+            .as_ref()
+            .and_then(|path| dates.get(Path::new(path)))
+        else {
+            continue;
+        };
+        if note.date.is_none() {
+            note.date = Some(first.date_naive());
+            note.date_time = Some(*first);
+        }
+        if note.updated.is_none() && last != first {
+            note.updated = Some(last.date_naive());
+            note.updated_time = Some(*last);
+        }
+    }
+}
+
+/// Load the previous site index and manifest, if both are available.
+fn load_previous_build(
+    config: &Config,
+) -> Option<(monowiki_core::SiteIndex, monowiki_core::BuildManifest)> {
+    let manifest = cache::load_build_manifest(config).ok().flatten()?;
+    let index = cache::load_cached_site_index(config).ok().flatten()?;
+    Some((index, manifest))
+}
+
+/// Writes output pages, skipping files whose content hash matches the
+/// previous build, and tracks hashes for the next build manifest.
+struct OutputWriter<'a> {
+    output_dir: PathBuf,
+    previous: Option<&'a BTreeMap<String, String>>,
+    hashes: BTreeMap<String, String>,
+    written: usize,
+    unchanged: usize,
+}
+
+/// Outcome of writing a single page.
+struct PageWrite {
+    hash: String,
+    written: bool,
+}
+
+impl<'a> OutputWriter<'a> {
+    fn new(output_dir: &Path, previous: Option<&'a BTreeMap<String, String>>) -> Self {
+        Self {
+            output_dir: output_dir.to_path_buf(),
+            previous,
+            hashes: BTreeMap::new(),
+            written: 0,
+            unchanged: 0,
+        }
+    }
+
+    /// Write a page unless it is unchanged. Safe to call from multiple
+    /// threads; pass the result to `record` afterwards.
+    fn write(&self, rel_path: &str, contents: &str) -> Result<PageWrite> {
+        let hash = monowiki_core::manifest::content_hash(contents.as_bytes());
+        let output_path = self.output_dir.join(rel_path);
+        let previous_hash = self.previous.and_then(|p| p.get(rel_path));
+
+        if previous_hash == Some(&hash) && output_path.exists() {
+            return Ok(PageWrite {
+                hash,
+                written: false,
+            });
+        }
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&output_path, contents)
+            .with_context(|| format!("Failed to write {:?}", output_path))?;
+        Ok(PageWrite {
+            hash,
+            written: true,
+        })
+    }
+
+    /// Copy a generated file into the output unless the previous build
+    /// already wrote the same `hash` there.
+    fn copy(&self, rel_path: &str, source: &Path, hash: &str) -> Result<PageWrite> {
+        let output_path = self.output_dir.join(rel_path);
+        let previous_hash = self.previous.and_then(|p| p.get(rel_path));
+        let page = PageWrite {
+            hash: hash.to_string(),
+            written: previous_hash.map(String::as_str) != Some(hash) || !output_path.exists(),
+        };
+        if page.written {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(source, &output_path)
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, output_path))?;
+        }
+        Ok(page)
+    }
+
+    fn record(&mut self, rel_path: String, page: PageWrite) {
+        if page.written {
+            self.written += 1;
+        } else {
+            self.unchanged += 1;
+        }
+        self.hashes.insert(rel_path, page.hash);
+    }
+
+    /// Remove pages written by the previous build that no longer exist.
+    fn remove_stale(&self) -> Result<()> {
+        let Some(previous) = self.previous else {
+            return Ok(());
+        };
+        for rel_path in previous.keys() {
+            if self.hashes.contains_key(rel_path) {
+                continue;
+            }
+            let stale = self.output_dir.join(rel_path);
+            if stale.exists() {
+                fs::remove_file(&stale)
+                    .with_context(|| format!("Failed to remove stale page {:?}", stale))?;
+                tracing::debug!("Removed stale page {}", rel_path);
+            }
+        }
+        Ok(())
+    }
+
+    fn into_hashes(self) -> BTreeMap<String, String> {
+        self.hashes
+    }
+}
+
 /// Render a comment page with thread context
 fn render_comment_page(
     config: &Config,
     note: &monowiki_core::Note,
     site_index: &monowiki_core::SiteIndex,
+    tag_counts: &BTreeMap<String, usize>,
     base_url: &str,
-) -> Result<()> {
+) -> Result<String> {
     // Find the comment data
     let comment = site_index
         .comments
@@ -198,12 +436,13 @@ fn render_comment_page(
         content.push_str("</div>\n");
     }
 
+    let mathjax = uses_mathjax(config, &content);
     let template = PostTemplate {
         title: note.title.clone(),
         description: format!("Comment: {}", note.title),
         date: None,
         updated: None,
-        tags: note.tags.clone(),
+        tags: note_tag_entries(&note.tags, tag_counts, base_url),
         content,
         toc_html: None,
         site_title: config.site.title.clone(),
@@ -216,23 +455,19 @@ fn render_comment_page(
         has_github: true,
         css_path: base_url.to_string(),
         backlinks: vec![],
+        mentions: vec![],
         base_url: base_url.to_string(),
         slug: note.slug.clone(),
         source: None,
         comments: vec![], // Comment pages don't show the modal UI
         has_unanchored_comments: false,
+        mathjax,
+        mathjax_macros: mathjax_macros(&config.math.macros_for(None)),
     };
 
-    let html = template
+    template
         .render()
-        .context("Failed to render comment template")?;
-
-    let output_path = config.output_dir().join(note.output_rel_path());
-    fs::write(&output_path, html).with_context(|| format!("Failed to write {:?}", output_path))?;
-
-    tracing::debug!("Rendered comment: {}", note.slug);
-
-    Ok(())
+        .context("Failed to render comment template")
 }
 
 /// Render a single note page
@@ -240,8 +475,10 @@ fn render_note_page(
     config: &Config,
     note: &monowiki_core::Note,
     site_index: &monowiki_core::SiteIndex,
+    tag_counts: &BTreeMap<String, usize>,
+    mention_index: Option<&MentionIndex>,
     base_url: &str,
-) -> Result<()> {
+) -> Result<String> {
     // Get backlinks
     let backlinks: Vec<BacklinkEntry> = if config.enable_backlinks {
         let backlink_slugs = site_index.graph.backlinks(&note.slug);
@@ -257,37 +494,56 @@ fn render_note_page(
         Vec::new()
     };
 
-    // Expand {{directory_tree}} macro if present
-    let content = expand_macros(&note.content_html, site_index, base_url);
-
-    // Dispatch to appropriate template based on note type
-    // Use API template only for actual API docs (have doc_kind), not generic "doc" type pages
-    let is_api_doc = note.note_type == monowiki_core::NoteType::Doc
-        && note.frontmatter.doc_kind.is_some();
+    // Plain-text mentions of this note elsewhere, when enabled
+    let mentions: Vec<MentionEntry> = mention_index
+        .map(|index| index.mentions_of(note))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|mention| {
+            let source = site_index.find_by_slug(&mention.slug)?;
+            let url = source.url_with_base(base_url);
+            Some(MentionEntry {
+                url: match &mention.anchor {
+                    Some(anchor) => format!("{url}#{anchor}"),
+                    None => url,
+                },
+                title: mention.title,
+                section: mention.section,
+                snippet: mention.snippet,
+            })
+        })
+        .collect();
 
-    let html = if is_api_doc {
-        render_api_doc(config, note, &content, &backlinks, site_index, base_url)?
-    } else {
-        render_regular_note(config, note, &content, &backlinks, site_index, base_url)?
+    // Dispatch to the template configured for the note type. By default use
+    // the API template only for actual API docs (have doc_kind), not generic
+    // "doc" type pages
+    let is_api_doc = match config.note_type_settings(&note.note_type).template {
+        Some(template) => template == PageTemplate::Api,
+        None => {
+            note.note_type == monowiki_core::NoteType::Doc && note.frontmatter.doc_kind.is_some()
+        }
     };
 
-    let output_path = config.output_dir().join(note.output_rel_path());
-    fs::write(&output_path, html).with_context(|| format!("Failed to write {:?}", output_path))?;
-
-    tracing::debug!("Rendered: {}", note.slug);
-
-    Ok(())
+    if is_api_doc {
+        render_api_doc(config, note, &backlinks, site_index, base_url)
+    } else {
+        render_regular_note(
+            config, note, &backlinks, mentions, site_index, tag_counts, base_url,
+        )
+    }
 }
 
 /// Render an API documentation page using ApiTemplate
 fn render_api_doc(
     config: &Config,
     note: &monowiki_core::Note,
-    content: &str,
     backlinks: &[BacklinkEntry],
     site_index: &monowiki_core::SiteIndex,
     base_url: &str,
 ) -> Result<String> {
+    // Expand {{directory_tree}} macro if present
+    let content = expand_macros(config, &note.content_html, site_index, base_url);
+
     // Build parent item URL if we have a parent_item
     let parent_item_url = note.frontmatter.parent_item.as_ref().and_then(|parent| {
         site_index
@@ -307,7 +563,7 @@ fn render_api_doc(
             .clone()
             .or_else(|| note.frontmatter.description.clone())
             .unwrap_or_else(|| note.title.clone()),
-        content: content.to_string(),
+        content,
         site_title: config.site.title.clone(),
         site_author: config.site.author.clone(),
         year: chrono::Utc::now().year(),
@@ -339,11 +595,15 @@ fn render_api_doc(
 fn render_regular_note(
     config: &Config,
     note: &monowiki_core::Note,
-    content: &str,
     backlinks: &[BacklinkEntry],
+    mentions: Vec<MentionEntry>,
     site_index: &monowiki_core::SiteIndex,
+    tag_counts: &BTreeMap<String, usize>,
     base_url: &str,
 ) -> Result<String> {
+    // Expand {{directory_tree}} macro if present
+    let content = expand_macros(config, &note.content_html, site_index, base_url);
+
     // Format dates
     let date = note.date.as_ref().map(|d| d.format("%Y-%m-%d").to_string());
     let updated = note
@@ -354,6 +614,7 @@ fn render_regular_note(
     // Get comments and compute unanchored flag
     let comments = render_comments_for_note(&site_index.comments, &note.slug);
     let has_unanchored_comments = comments.iter().any(|c| c.depth == 0 && !c.has_anchor);
+    let mathjax = uses_mathjax(config, &content);
 
     let template = PostTemplate {
         title: note.title.clone(),
@@ -364,8 +625,8 @@ fn render_regular_note(
             .unwrap_or_else(|| note.title.clone()),
         date,
         updated,
-        tags: note.tags.clone(),
-        content: content.to_string(),
+        tags: note_tag_entries(&note.tags, tag_counts, base_url),
+        content,
         toc_html: note.toc_html.clone(),
         site_title: config.site.title.clone(),
         site_author: config.site.author.clone(),
@@ -377,16 +638,24 @@ fn render_regular_note(
         has_github: true,
         css_path: base_url.to_string(),
         backlinks: backlinks.to_vec(),
+        mentions,
         base_url: base_url.to_string(),
         slug: note.slug.clone(),
         source: note.raw_body.clone(),
         comments,
         has_unanchored_comments,
+        mathjax,
+        mathjax_macros: mathjax_macros(&config.math.macros_for(Some(&note.frontmatter.macros))),
     };
 
     template.render().context("Failed to render post template")
 }
 
+/// Whether a page's math is left for MathJax to typeset client-side
+fn uses_mathjax(config: &Config, content: &str) -> bool {
+    config.math.render == MathRender::Mathjax || needs_mathjax(content)
+}
+
 fn render_comments_for_note(comments: &[monowiki_core::Comment], slug: &str) -> Vec<CommentRender> {
     // Build lookup for finding root note of orphaned replies
     let comment_map: std::collections::HashMap<_, _> = comments
@@ -661,15 +930,23 @@ fn sort_directory_tree(tree: HashMap<String, DirectoryNode>) -> Vec<DirectoryNod
 }
 
 /// Expand macros like {{directory_tree}} in content
-fn expand_macros(content: &str, site_index: &monowiki_core::SiteIndex, base_url: &str) -> String {
+fn expand_macros(
+    config: &Config,
+    content: &str,
+    site_index: &monowiki_core::SiteIndex,
+    base_url: &str,
+) -> String {
     // Check for {{directory_tree}} macro (may be wrapped in <p> tags by markdown parser)
     if !content.contains("{{directory_tree}}") {
         return content.to_string();
     }
 
-    // Build directory tree from published notes
-    let published_notes: Vec<&monowiki_core::Note> =
-        site_index.notes.iter().filter(|n| !n.is_draft()).collect();
+    // Build directory tree from published notes of listed types
+    let published_notes: Vec<&monowiki_core::Note> = site_index
+        .notes
+        .iter()
+        .filter(|n| !n.is_draft() && config.note_type_settings(&n.note_type).listed)
+        .collect();
     let directory_tree = build_directory_tree(&published_notes, base_url);
 
     // Render directory tree to HTML
@@ -690,6 +967,184 @@ fn expand_macros(content: &str, site_index: &monowiki_core::SiteIndex, base_url:
         .replace("{{directory_tree}}", &tree_html)
 }
 
+/// URL of a tag's listing page
+pub(crate) fn tag_url(tag: &str, base_url: &str) -> String {
+    format!("{}tags/{}.html", base_url, monowiki_core::tag_slug(tag))
+}
+
+fn tag_entry(tag: &str, count: usize, base_url: &str) -> TagEntry {
+    TagEntry {
+        name: tag.to_string(),
+        url: tag_url(tag, base_url),
+        count,
+        depth: tag.matches('/').count(),
+    }
+}
+
+/// Tags shown on a note page, linked to their listing pages where one exists
+fn note_tag_entries(
+    tags: &[String],
+    tag_counts: &BTreeMap<String, usize>,
+    base_url: &str,
+) -> Vec<TagEntry> {
+    tags.iter()
+        .map(|tag| {
+            let count = monowiki_core::tag_hierarchy(tag)
+                .last()
+                .and_then(|key| tag_counts.get(key))
+                .copied()
+                .unwrap_or(0);
+            TagEntry {
+                name: tag.clone(),
+                ..tag_entry(tag, count, base_url)
+            }
+        })
+        .collect()
+}
+
+/// Render `tags/index.html` and one listing page per tag. Nested tags
+/// (`rust/async`) get their own page and are also listed under each parent.
+fn render_tag_pages(
+    config: &Config,
+    site_index: &monowiki_core::SiteIndex,
+    base_url: &str,
+    writer: &mut OutputWriter,
+) -> Result<()> {
+    let notes_by_tag = site_index.notes_by_tag();
+    let year = chrono::Utc::now().year();
+
+    // Tags whose slug is already taken (reported as `tag.collision` by the
+    // builder) get no page, so an earlier tag's page is never overwritten
+    let mut slugs: HashSet<String> = HashSet::from(["index".to_string()]);
+    let tags: Vec<TagEntry> = notes_by_tag
+        .iter()
+        .filter(|(tag, _)| {
+            let slug = monowiki_core::tag_slug(tag);
+            !slug.is_empty() && slugs.insert(slug)
+        })
+        .map(|(tag, notes)| tag_entry(tag, notes.len(), base_url))
+        .collect();
+
+    let index = TagIndexTemplate {
+        tags: tags.clone(),
+        site_title: config.site.title.clone(),
+        site_author: config.site.author.clone(),
+        year,
+        nav_home: format!("{}index.html", base_url),
+        nav_about: format!("{}about.html", base_url),
+        nav_github: config.site.url.clone(),
+        has_about: false,
+        has_github: true,
+        css_path: base_url.to_string(),
+        base_url: base_url.to_string(),
+    }
+    .render()
+    .context("Failed to render tag index template")?;
+    let rel_path = "tags/index.html".to_string();
+    let page = writer.write(&rel_path, &index)?;
+    writer.record(rel_path, page);
+
+    for entry in &tags {
+        let mut notes = notes_by_tag[&entry.name].clone();
+        notes.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.title.cmp(&b.title)));
+        let items = notes
+            .iter()
+            .map(|note| NoteEntry {
+                url: note.url_with_base(base_url),
+                title: note.title.clone(),
+                date: note.date.map(|d| d.format("%Y-%m-%d").to_string()),
+                description: note
+                    .frontmatter
+                    .description
+                    .clone()
+                    .or_else(|| note.frontmatter.summary.clone()),
+                note_type: note.note_type.as_str().to_string(),
+            })
+            .collect();
+        let prefix = format!("{}/", entry.name);
+        let subtags = tags
+            .iter()
+            .filter(|t| t.depth == entry.depth + 1 && t.name.starts_with(&prefix))
+            .cloned()
+            .collect();
+
+        let html = TagTemplate {
+            tag: entry.name.clone(),
+            items,
+            subtags,
+            site_title: config.site.title.clone(),
+            site_author: config.site.author.clone(),
+            year,
+            nav_home: format!("{}index.html", base_url),
+            nav_about: format!("{}about.html", base_url),
+            nav_github: config.site.url.clone(),
+            has_about: false,
+            has_github: true,
+            css_path: base_url.to_string(),
+            base_url: base_url.to_string(),
+        }
+        .render()
+        .context("Failed to render tag template")?;
+        let rel_path = format!("tags/{}.html", monowiki_core::tag_slug(&entry.name));
+        let page = writer.write(&rel_path, &html)?;
+        writer.record(rel_path, page);
+    }
+
+    tracing::info!("Rendered {} tag pages", tags.len());
+    Ok(())
+}
+
+/// Write a redirect stub for every alias and `redirect_from` path, plus
+/// `_redirects` and `redirects.json` so hosts can serve real 301s.
+fn render_redirects(
+    config: &Config,
+    site_index: &monowiki_core::SiteIndex,
+    base_url: &str,
+    writer: &mut OutputWriter,
+) -> Result<()> {
+    use serde_json::json;
+
+    if site_index.redirects.is_empty() {
+        return Ok(());
+    }
+
+    let mut rules = String::new();
+    let mut entries = Vec::new();
+    for redirect in &site_index.redirects {
+        let Some(note) = site_index.find_by_slug(&redirect.slug) else {
+            continue;
+        };
+        let html = RedirectTemplate {
+            site_title: config.site.title.clone(),
+            title: note.title.clone(),
+            target_url: redirect.to.clone(),
+            canonical_url: absolute_url(&config.site.url, base_url, &note.output_rel_path()),
+        }
+        .render()
+        .context("Failed to render redirect template")?;
+        let page = writer.write(&redirect.from, &html)?;
+        writer.record(redirect.from.clone(), page);
+
+        // Directory-style paths are requested without the index.html
+        let from = format!(
+            "{}{}",
+            base_url,
+            redirect.from.strip_suffix("index.html").unwrap_or(&redirect.from)
+        );
+        rules.push_str(&format!("{} {} 301\n", from, redirect.to));
+        entries.push(json!({ "from": from, "to": redirect.to, "slug": redirect.slug }));
+    }
+
+    let page = writer.write("_redirects", &rules)?;
+    writer.record("_redirects".to_string(), page);
+    let json = serde_json::to_string_pretty(&entries).context("Failed to serialize redirects")?;
+    let page = writer.write("redirects.json", &json)?;
+    writer.record("redirects.json".to_string(), page);
+
+    tracing::info!("Wrote {} redirects", entries.len());
+    Ok(())
+}
+
 /// Render the 404 error page
 fn render_404_page(config: &Config, base_url: &str) -> Result<()> {
     let template = NotFoundTemplate {
@@ -744,6 +1199,26 @@ fn generate_previews_json(
             })
         };
 
+        // Numbered blocks get their own entries for hovering `@ref` links
+        for label in &note.nota_labels {
+            let mut title = label.reference_text();
+            if label.number.is_some() && !label.title.is_empty() {
+                title.push_str(&format!(" ({})", label.title));
+            }
+            previews.insert(
+                format!("{}#{}", url, label.label),
+                json!({
+                    "title": title,
+                    "preview": label.text,
+                    "type": "nota",
+                    "kind": label.kind,
+                    "number": label.number,
+                    "note": note.title,
+                    "url": format!("{}#{}", note.url_with_base(base_url), label.label),
+                }),
+            );
+        }
+
         previews.insert(
             url,
             json!({
@@ -771,25 +1246,25 @@ fn generate_index_json(
     site_index: &monowiki_core::SiteIndex,
     base_url: &str,
 ) -> Result<()> {
-    let mut index = Vec::new();
-
-    for note in &site_index.notes {
-        if note.is_draft() || note.note_type == monowiki_core::NoteType::Comment {
-            continue;
-        }
-
-        // Build section-level search entries
-        let entries = monowiki_core::build_search_index(
-            &note.slug,
-            &note.title,
-            &note.content_html,
-            &note.tags,
-            note.note_type.as_str(),
-            base_url,
-        );
-
-        index.extend(entries);
-    }
+    // Build section-level search entries in parallel, flattened in note order
+    let index: Vec<_> = site_index
+        .notes
+        .par_iter()
+        .filter(|note| !note.is_draft() && config.note_type_settings(&note.note_type).search)
+        .map(|note| {
+            monowiki_core::build_search_index(
+                &note.slug,
+                &note.title,
+                &note.content_html,
+                &note.tags,
+                note.note_type.as_str(),
+                base_url,
+            )
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flatten()
+        .collect();
 
     let output_path = config.output_dir().join("index.json");
     let json = serde_json::to_string_pretty(&index).context("Failed to serialize search index")?;
@@ -865,6 +1340,15 @@ fn generate_graph_json(
             edges.push(json!({
                 "source": note.slug,
                 "target": target,
+                "kind": "link",
+            }));
+        }
+
+        for target in &note.embeds {
+            edges.push(json!({
+                "source": note.slug,
+                "target": target,
+                "kind": "embed",
             }));
         }
     }
@@ -883,61 +1367,25 @@ fn generate_graph_json(
     Ok(())
 }
 
-/// Static asset file extensions to copy from vault
-const VAULT_ASSET_EXTENSIONS: &[&str] = &[
-    // Images
-    "png", "jpg", "jpeg", "gif", "svg", "webp", "ico", "bmp", "tiff", "tif",
-    // Documents
-    "pdf",
-    // Audio/Video
-    "mp3", "mp4", "wav", "ogg", "webm", "m4a",
-    // Data files
-    "json", "csv", "xml",
-    // Archives
-    "zip", "tar", "gz",
-    // Other
-    "woff", "woff2", "ttf", "otf", "eot",
-];
-
 /// Copy static assets (images, PDFs, etc.) from vault to output directory
-fn copy_vault_assets(config: &Config) -> Result<()> {
+fn copy_vault_assets(config: &Config, assets: &AssetIndex) -> Result<()> {
     let vault_dir = config.vault_dir();
     let output_dir = config.output_dir();
     let mut copied_count = 0;
 
-    for entry in WalkDir::new(&vault_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
+    // Preserve relative path structure
+    for relative in assets.paths() {
+        let source = vault_dir.join(relative);
+        let target = output_dir.join(relative);
 
-        // Skip markdown files (already processed)
-        if path.extension().and_then(|e| e.to_str()) == Some("md") {
-            continue;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
         }
 
-        // Check if file has an allowed extension
-        let extension = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| e.to_lowercase());
-
-        if let Some(ext) = extension {
-            if VAULT_ASSET_EXTENSIONS.contains(&ext.as_str()) {
-                // Preserve relative path structure
-                let relative = path.strip_prefix(&vault_dir).unwrap_or(path);
-                let target = output_dir.join(relative);
-
-                if let Some(parent) = target.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-
-                fs::copy(path, &target)
-                    .with_context(|| format!("Failed to copy vault asset {:?} to {:?}", path, target))?;
-                copied_count += 1;
-            }
-        }
+        fs::copy(&source, &target).with_context(|| {
+            format!("Failed to copy vault asset {:?} to {:?}", source, target)
+        })?;
+        copied_count += 1;
     }
 
     if copied_count > 0 {
@@ -1102,9 +1550,20 @@ fn generate_rss(
     base_url: &str,
 ) -> Result<()> {
     let mut items = String::new();
-    let mut notes: Vec<_> = site_index.notes.iter().filter(|n| !n.is_draft()).collect();
+    let mut notes: Vec<_> = site_index
+        .notes
+        .iter()
+        .filter(|n| !n.is_draft() && config.note_type_settings(&n.note_type).rss)
+        .collect();
 
-    notes.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.updated.cmp(&a.updated)));
+    notes.sort_by(|a, b| {
+        (b.date, b.date_time, b.updated, b.updated_time).cmp(&(
+            a.date,
+            a.date_time,
+            a.updated,
+            a.updated_time,
+        ))
+    });
 
     for note in notes {
         let link = absolute_url(&config.site.url, base_url, &note.output_rel_path());
@@ -1113,14 +1572,18 @@ fn generate_rss(
             note.frontmatter
                 .description
                 .as_ref()
-                .or_else(|| note.frontmatter.summary.as_ref())
+                .or(note.frontmatter.summary.as_ref())
                 .unwrap_or(&note.title),
         );
 
-        let pub_date = note
-            .updated
-            .or(note.date)
-            .and_then(|d| naive_to_rfc2822(&d));
+        let pub_date = match (note.updated, note.updated_time) {
+            (Some(_), Some(time)) => Some(time.to_rfc2822()),
+            (Some(date), None) => naive_to_rfc2822(&date),
+            (None, _) => note
+                .date_time
+                .map(|time| time.to_rfc2822())
+                .or_else(|| note.date.and_then(|d| naive_to_rfc2822(&d))),
+        };
 
         items.push_str(&format!(
             "<item><title>{}</title><link>{}</link><guid>{}</guid><description>{}</description>",
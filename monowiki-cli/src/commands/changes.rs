@@ -2,6 +2,7 @@
 
 use crate::cache::load_or_build_site_index;
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset};
 use monowiki_core::{
     frontmatter, markdown::MarkdownProcessor, search, slugify, Config, SectionDigest,
 };
@@ -372,65 +373,6 @@ fn compute_section_diff(
     )
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn snap(id: &str, heading: &str, content: &str, hash: &str) -> SectionSnapshot {
-        SectionSnapshot {
-            digest: SectionDigest {
-                section_id: id.to_string(),
-                heading: heading.to_string(),
-                hash: hash.into(),
-                anchor_id: None,
-            },
-            content: content.to_string(),
-        }
-    }
-
-    #[test]
-    fn diff_sections_includes_diffs_when_enabled() {
-        let prev = vec![snap("s1", "Intro", "hello world", "h1")];
-        let cur = vec![snap("s1", "Intro", "hello brave world", "h2")];
-
-        let changes = diff_sections(prev, cur, true);
-        assert_eq!(changes.len(), 1);
-        let change = &changes[0];
-        assert_eq!(change.change, "modified");
-        let diff_text = change.diff.as_ref().unwrap();
-        assert!(diff_text.contains('+'));
-        assert_eq!(change.added_tokens, Some(1));
-        assert_eq!(change.removed_tokens, Some(0));
-    }
-
-    #[test]
-    fn diff_sections_skips_diffs_when_disabled() {
-        let prev = vec![snap("s1", "Intro", "hello world", "h1")];
-        let cur = vec![snap("s1", "Intro", "hello brave world", "h2")];
-
-        let changes = diff_sections(prev, cur, false);
-        assert_eq!(changes.len(), 1);
-        let change = &changes[0];
-        assert_eq!(change.change, "modified");
-        assert!(change.diff.is_none());
-        assert!(change.added_tokens.is_none());
-        assert!(change.removed_tokens.is_none());
-    }
-
-    #[test]
-    fn compute_diff_truncates_large_output() {
-        let prev = "a ".repeat(3000);
-        let curr = "b ".repeat(3000);
-
-        let (diff, added, removed) = compute_section_diff(&prev, &curr);
-        let diff_text = diff.expect("diff");
-        assert!(diff_text.contains("...diff truncated..."));
-        assert!(diff_text.len() >= MAX_DIFF_LEN); // truncated marker appended
-        assert_eq!(added, Some(3000));
-        assert_eq!(removed, Some(3000));
-    }
-}
-
 fn git_root() -> Result<PathBuf> {
     let output = Command::new("git")
         .args(["rev-parse", "--show-toplevel"])
@@ -443,6 +385,58 @@ fn git_root() -> Result<PathBuf> {
     Ok(PathBuf::from(root))
 }
 
+/// Author dates of the first and last commit touching a file
+pub(crate) type FileDates = (DateTime<FixedOffset>, DateTime<FixedOffset>);
+
+/// First and last commit dates of each file under `dir`, keyed by path
+/// relative to `dir`
+pub(crate) fn git_file_dates(dir: &Path) -> Result<HashMap<PathBuf, FileDates>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "log",
+            "-z",
+            "--format=%x00%aI",
+            "--name-only",
+            "--no-renames",
+            "--relative",
+            "--",
+            ".",
+        ])
+        .output()
+        .context("Failed to run git log")?;
+    if !output.status.success() {
+        return Err(anyhow!("git log failed in {}", dir.display()));
+    }
+
+    // With `-z` paths are NUL-terminated and never quoted. Each commit starts
+    // with an empty field (the `%x00` in the format) followed by its date;
+    // the first path after the date keeps the newline ending the header.
+    // Commits are listed newest first: the first date seen for a file is its
+    // last change, the final one its first commit
+    let mut dates = HashMap::new();
+    let mut current = None;
+    let mut fields = output.stdout.split(|&byte| byte == 0);
+    while let Some(field) = fields.next() {
+        if field.is_empty() {
+            current = fields
+                .next()
+                .and_then(|date| std::str::from_utf8(date).ok())
+                .and_then(|date| DateTime::parse_from_rfc3339(date).ok());
+            continue;
+        }
+        let path = field.strip_prefix(b"\n").unwrap_or(field);
+        if let (Some(date), false) = (current, path.is_empty()) {
+            dates
+                .entry(PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+                .and_modify(|(first, _)| *first = date)
+                .or_insert((date, date));
+        }
+    }
+    Ok(dates)
+}
+
 fn git_diff_since(since: &str, vault_rel: &Path) -> Result<Vec<(String, PathBuf)>> {
     let vault_str = vault_rel.to_string_lossy();
     let output = Command::new("git")
@@ -523,3 +517,62 @@ fn latest_commit(path: &str) -> Result<(Option<String>, Option<i64>, Option<Stri
     let author = parts.next().map(|s| s.to_string());
     Ok((hash, ts, author))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(id: &str, heading: &str, content: &str, hash: &str) -> SectionSnapshot {
+        SectionSnapshot {
+            digest: SectionDigest {
+                section_id: id.to_string(),
+                heading: heading.to_string(),
+                hash: hash.into(),
+                anchor_id: None,
+            },
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_sections_includes_diffs_when_enabled() {
+        let prev = vec![snap("s1", "Intro", "hello world", "h1")];
+        let cur = vec![snap("s1", "Intro", "hello brave world", "h2")];
+
+        let changes = diff_sections(prev, cur, true);
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!(change.change, "modified");
+        let diff_text = change.diff.as_ref().unwrap();
+        assert!(diff_text.contains('+'));
+        assert_eq!(change.added_tokens, Some(1));
+        assert_eq!(change.removed_tokens, Some(0));
+    }
+
+    #[test]
+    fn diff_sections_skips_diffs_when_disabled() {
+        let prev = vec![snap("s1", "Intro", "hello world", "h1")];
+        let cur = vec![snap("s1", "Intro", "hello brave world", "h2")];
+
+        let changes = diff_sections(prev, cur, false);
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!(change.change, "modified");
+        assert!(change.diff.is_none());
+        assert!(change.added_tokens.is_none());
+        assert!(change.removed_tokens.is_none());
+    }
+
+    #[test]
+    fn compute_diff_truncates_large_output() {
+        let prev = "a ".repeat(3000);
+        let curr = "b ".repeat(3000);
+
+        let (diff, added, removed) = compute_section_diff(&prev, &curr);
+        let diff_text = diff.expect("diff");
+        assert!(diff_text.contains("...diff truncated..."));
+        assert!(diff_text.len() >= MAX_DIFF_LEN); // truncated marker appended
+        assert_eq!(added, Some(3000));
+        assert_eq!(removed, Some(3000));
+    }
+}
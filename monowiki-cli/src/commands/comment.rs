@@ -125,18 +125,35 @@ pub fn list_comments(
     Ok(())
 }
 
-pub fn add_comment(
-    config_path: &Path,
-    target_slug: &str,
-    target_anchor: Option<&str>,
-    reply_to: Option<&str>,
-    quote: Option<&str>,
-    author: Option<&str>,
-    git_ref: Option<&str>,
-    tags: Vec<String>,
-    status: Option<&str>,
-    body: &str,
-) -> Result<()> {
+/// A comment to be written by `add_comment`
+pub struct NewComment<'a> {
+    pub target_slug: &'a str,
+    pub target_anchor: Option<&'a str>,
+    /// Id of the comment being replied to
+    pub reply_to: Option<&'a str>,
+    pub quote: Option<&'a str>,
+    /// Defaults to `git config user.name`
+    pub author: Option<&'a str>,
+    /// Defaults to the current `HEAD`
+    pub git_ref: Option<&'a str>,
+    pub tags: Vec<String>,
+    pub status: Option<&'a str>,
+    /// Comment text, or `-` to read it from stdin
+    pub body: &'a str,
+}
+
+pub fn add_comment(config_path: &Path, comment: NewComment) -> Result<()> {
+    let NewComment {
+        target_slug,
+        target_anchor,
+        reply_to,
+        quote,
+        author,
+        git_ref,
+        tags,
+        status,
+        body,
+    } = comment;
     let config = Config::from_file(config_path).context("Failed to load configuration")?;
     let vault_dir = config.vault_dir();
     let comments_dir = vault_dir.join("comments");
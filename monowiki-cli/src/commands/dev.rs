@@ -1,6 +1,6 @@
 //! Dev server command implementation with JSON APIs.
 
-use super::build::build_site_with_index;
+use super::build::{build_site_with_index, tag_url};
 use super::search::{perform_search, SearchOptions};
 use crate::{agent, GraphDirection};
 use anyhow::{Context, Result};
@@ -12,7 +12,9 @@ use axum::{
     routing::get,
     Json, Router,
 };
-use monowiki_core::{build_search_index, slugify, CommentStatus, Config, SearchEntry};
+use monowiki_core::{
+    build_search_index, slugify, CommentStatus, Config, MentionIndex, SearchEntry,
+};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -79,7 +81,8 @@ pub async fn dev_server(config_path: &Path, port: u16) -> Result<()> {
                         match res {
                             Ok(Ok((config, site_index))) => {
                                 let base_url = config.normalized_base_url();
-                                let search_entries = compute_search_entries(&site_index, &base_url);
+                                let search_entries =
+                                    compute_search_entries(&config, &site_index, &base_url);
 
                                 let mut data = data_handle.write().await;
                                 *data = SiteData {
@@ -111,9 +114,11 @@ pub async fn dev_server(config_path: &Path, port: u16) -> Result<()> {
         .route("/api/note/{slug}", get(api_note))
         .route("/api/graph/{slug}", get(api_graph_neighbors))
         .route("/api/graph/path", get(api_graph_path))
+        .route("/api/mentions/{slug}", get(api_mentions))
         .route("/api/status", get(api_status))
         .route("/api/comments", get(api_comments))
         .route("/api/changes", get(api_changes))
+        .route("/api/tags", get(api_tags))
         .route("/{*path}", get(serve_with_404))
         .route("/", get(serve_index))
         .fallback(serve_404)
@@ -557,12 +562,57 @@ async fn api_note(AxumPath(slug): AxumPath<String>, State(state): State<AppState
     }
 }
 
+/// Unlinked mentions of a note's title or aliases in other notes
+async fn api_mentions(AxumPath(slug): AxumPath<String>, State(state): State<AppState>) -> Response {
+    let data = state.data.read().await;
+    let normalized = normalize_slugish(&slug);
+
+    let Some(target) = data
+        .site_index
+        .find_by_slug(&normalized)
+        .or_else(|| data.site_index.find_by_alias(&normalized))
+    else {
+        return (StatusCode::NOT_FOUND, "Note not found").into_response();
+    };
+
+    let mentions = MentionIndex::new(&data.site_index).mentions_of(target);
+    Json(agent::envelope(
+        "graph.mentions",
+        agent::mentions_payload(target, mentions, &data.site_index, &data.base_url),
+    ))
+    .into_response()
+}
+
+/// Tag counts, with nested tags also counted under their parents
+async fn api_tags(State(state): State<AppState>) -> Response {
+    let data = state.data.read().await;
+    let tags: Vec<_> = data
+        .site_index
+        .tag_counts()
+        .into_iter()
+        .map(|(tag, count)| agent::TagCount {
+            url: tag_url(&tag, &data.base_url),
+            tag,
+            count,
+        })
+        .collect();
+
+    Json(agent::envelope(
+        "tags.counts",
+        agent::TagsData {
+            total: tags.len(),
+            tags,
+        },
+    ))
+    .into_response()
+}
+
 // ---- helpers ----
 
 fn build_site_data(config_path: &Path) -> Result<SiteData> {
     let (config, site_index) = build_site_with_index(config_path)?;
     let base_url = config.normalized_base_url();
-    let search_entries = compute_search_entries(&site_index, &base_url);
+    let search_entries = compute_search_entries(&config, &site_index, &base_url);
 
     Ok(SiteData {
         config,
@@ -573,12 +623,13 @@ fn build_site_data(config_path: &Path) -> Result<SiteData> {
 }
 
 fn compute_search_entries(
+    config: &Config,
     site_index: &monowiki_core::SiteIndex,
     base_url: &str,
 ) -> Vec<SearchEntry> {
     let mut entries = Vec::new();
     for note in &site_index.notes {
-        if note.is_draft() || note.note_type == monowiki_core::NoteType::Comment {
+        if note.is_draft() || !config.note_type_settings(&note.note_type).search {
             continue;
         }
 
@@ -784,9 +835,15 @@ enable_backlinks: true
             tags: vec!["rust".into()],
             date: None,
             updated: None,
+            date_time: None,
+            updated_time: None,
             aliases: vec![],
             permalink: None,
             outgoing_links: vec!["note-b".into()],
+            embeds: vec![],
+            block_ids: vec![],
+            nota_labels: vec![],
+            assets: vec![],
             preview: Some("Rust content".into()),
             toc_html: None,
             raw_body: Some("# Intro\nRust content".into()),
@@ -802,12 +859,18 @@ enable_backlinks: true
             tags: vec!["memory".into()],
             date: None,
             updated: None,
+            date_time: None,
+            updated_time: None,
             aliases: vec![],
             permalink: None,
             outgoing_links: vec!["note-a".into()],
+            embeds: vec![],
+            block_ids: vec![],
+            nota_labels: vec![],
+            assets: vec![],
             preview: Some("Memory".into()),
             toc_html: None,
-            raw_body: Some("Memory".into()),
+            raw_body: Some("Memory, unlike note a, is plain.".into()),
             source_path: None,
         };
 
@@ -816,6 +879,7 @@ enable_backlinks: true
             graph,
             comments: Vec::new(),
             diagnostics: Vec::new(),
+            redirects: Vec::new(),
         };
 
         let base_url = config.normalized_base_url();
@@ -894,4 +958,35 @@ enable_backlinks: true
         let nodes = value["data"]["nodes"].as_array().expect("nodes array");
         assert!(nodes.iter().any(|n| n["slug"] == "note-b"));
     }
+
+    #[tokio::test]
+    async fn api_tags_returns_counts() {
+        let state = sample_state();
+        let response = api_tags(State(state)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["kind"], "tags.counts");
+        let tags = value["data"]["tags"].as_array().expect("tags array");
+        let rust = tags.iter().find(|t| t["tag"] == "rust").unwrap();
+        assert_eq!(rust["count"], 1);
+        assert_eq!(rust["url"], "/tags/rust.html");
+    }
+
+    #[tokio::test]
+    async fn api_mentions_returns_unlinked_mentions() {
+        let state = sample_state();
+        let response = api_mentions(AxumPath("note-a".into()), State(state)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["kind"], "graph.mentions");
+        let mentions = value["data"]["mentions"]
+            .as_array()
+            .expect("mentions array");
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0]["slug"], "note-b");
+        assert_eq!(mentions[0]["url"], "/note-b.html");
+        assert_eq!(mentions[0]["snippet"], "Memory, unlike note a, is plain.");
+    }
 }
@@ -2,7 +2,7 @@
 
 use crate::{agent, cache::load_or_build_site_index, GraphDirection};
 use anyhow::Result;
-use monowiki_core::slugify;
+use monowiki_core::{slugify, MentionIndex};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::Path;
 
@@ -124,6 +124,46 @@ pub fn graph_path(
     Ok(())
 }
 
+/// Plain-text mentions of a note's title or aliases that aren't linked yet
+pub fn graph_mentions(config_path: &Path, slug: &str, json: bool) -> Result<()> {
+    let (config, site_index) = load_or_build_site_index(config_path)?;
+    let base_url = config.normalized_base_url();
+    let normalized = normalize_slug(slug);
+
+    let Some(target) = site_index
+        .find_by_slug(&normalized)
+        .or_else(|| site_index.find_by_alias(&normalized))
+    else {
+        anyhow::bail!("Note '{}' not found", slug);
+    };
+    let mentions = MentionIndex::new(&site_index).mentions_of(target);
+    let payload = agent::mentions_payload(target, mentions, &site_index, &base_url);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&agent::envelope("graph.mentions", payload))?
+        );
+    } else if payload.mentions.is_empty() {
+        println!("No unlinked mentions of '{}'", target.title);
+    } else {
+        println!("Unlinked mentions of '{}':", target.title);
+        for mention in &payload.mentions {
+            if mention.section.is_empty() {
+                println!("- {} ({})", mention.title, mention.url);
+            } else {
+                println!(
+                    "- {} › {} ({})",
+                    mention.title, mention.section, mention.url
+                );
+            }
+            println!("    {}", mention.snippet);
+        }
+    }
+
+    Ok(())
+}
+
 fn normalize_slug(input: &str) -> String {
     let trimmed = input.trim().trim_matches('/');
     let without_html = trimmed.strip_suffix(".html").unwrap_or(trimmed);
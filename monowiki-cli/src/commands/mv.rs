@@ -0,0 +1,505 @@
+//! Vault-wide rename/move of a note, rewriting references to it.
+
+use anyhow::{Context, Result};
+use monowiki_core::frontmatter::{add_alias, set_field};
+use monowiki_core::{slugify, Config, Note, SiteBuilder};
+use once_cell::sync::Lazy;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::{Captures, Regex};
+use similar::TextDiff;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+static WIKILINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(!?)\[\[([^\[\]\n]+)\]\]").unwrap());
+
+/// Inline Markdown link or image destination, with an optional title
+static MD_LINK_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(\]\()([^()\s<>]+)((?:\s+"[^"\n]*")?\))"#).unwrap());
+
+/// Characters escaped when writing a link destination back
+const DEST_ESCAPES: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'%')
+    .add(b'(')
+    .add(b')')
+    .add(b'<')
+    .add(b'>');
+
+/// The note being moved, and how references to it change
+struct Move<'a> {
+    /// Wikilink spellings that resolve to the note (empty if its slug stays)
+    keys: &'a HashSet<String>,
+    new_slug: &'a str,
+    /// Vault-relative source path before and after the move
+    from: &'a str,
+    to: &'a str,
+}
+
+/// A file whose contents (and possibly location) change
+struct FileEdit {
+    from: String,
+    to: String,
+    before: String,
+    after: String,
+}
+
+/// Move `old` (slug, alias or vault-relative path) to `new` and rewrite
+/// wikilinks, Markdown links and comment targets that pointed at it, as well
+/// as the note's own relative links. The old slug is kept as an alias. With
+/// `dry_run`, print a unified diff instead of writing.
+pub fn move_note(config_path: &Path, old: &str, new: &str, dry_run: bool) -> Result<()> {
+    let config = Config::from_file(config_path).context("Failed to load configuration")?;
+    let vault_dir = config.vault_dir();
+    let notes = SiteBuilder::new(config.clone())
+        .scan_notes()
+        .context("Failed to scan vault")?;
+
+    let note = find_note(&notes, old).with_context(|| format!("Note '{}' not found", old))?;
+    let from = note
+        .source_path
+        .clone()
+        .with_context(|| format!("Note '{}' has no source file", note.slug))?;
+    let to = target_path(&from, new);
+    let new_slug = Path::new(&to)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(slugify)
+        .unwrap_or_default();
+
+    if new_slug.is_empty() {
+        anyhow::bail!("Cannot derive a slug from '{}'", new);
+    }
+    if to != from && vault_dir.join(&to).exists() {
+        anyhow::bail!("{} already exists", to);
+    }
+    if let Some(other) = notes.iter().find(|other| {
+        other.source_path != note.source_path
+            && (other.slug == new_slug || other.aliases.iter().any(|a| slugify(a) == new_slug))
+    }) {
+        anyhow::bail!(
+            "Slug '{}' is already used by {}",
+            new_slug,
+            other.source_path.as_deref().unwrap_or(&other.slug)
+        );
+    }
+
+    // Link spellings that resolve to the note: its slug and its aliases
+    // (unless another note owns that slug)
+    let mut keys: HashSet<String> = HashSet::from([note.slug.clone()]);
+    keys.extend(
+        note.aliases
+            .iter()
+            .map(|alias| slugify(alias))
+            .filter(|key| !notes.iter().any(|other| &other.slug == key)),
+    );
+    let renamed = new_slug != note.slug;
+    if !renamed {
+        keys.clear();
+    }
+    let mv = Move {
+        keys: &keys,
+        new_slug: &new_slug,
+        from: &from,
+        to: &to,
+    };
+
+    let mut edits = Vec::new();
+    let mut links = 0;
+    for other in &notes {
+        let Some(rel) = other.source_path.clone() else {
+            continue;
+        };
+        let before = fs::read_to_string(vault_dir.join(&rel))
+            .with_context(|| format!("Failed to read {}", rel))?;
+        let (mut after, count) = rewrite_links(&before, &mv, &rel);
+        links += count;
+
+        if renamed {
+            // Comment threads refer to notes and comments by slug
+            for (key, value) in [
+                ("target_slug", &other.frontmatter.target_slug),
+                ("parent_id", &other.frontmatter.parent_id),
+            ] {
+                if value.as_deref() == Some(note.slug.as_str()) {
+                    after = set_field(&after, key, &new_slug);
+                }
+            }
+        }
+
+        let is_moved = rel == from;
+        if is_moved && renamed {
+            if other.frontmatter.title.is_empty() {
+                // The title came from the filename; keep it
+                after = set_field(&after, "title", &note.title);
+            }
+            if other.frontmatter.slug.is_some() {
+                after = set_field(&after, "slug", &new_slug);
+            }
+            if !note.aliases.iter().any(|a| slugify(a) == note.slug) {
+                after = add_alias(&after, &note.slug);
+            }
+        }
+
+        if is_moved || after != before {
+            edits.push(FileEdit {
+                to: if is_moved { to.clone() } else { rel.clone() },
+                from: rel,
+                before,
+                after,
+            });
+        }
+    }
+
+    if dry_run {
+        for edit in &edits {
+            print!("{}", file_diff(edit));
+        }
+        return Ok(());
+    }
+
+    for edit in &edits {
+        let target = vault_dir.join(&edit.to);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        fs::write(&target, &edit.after).with_context(|| format!("Failed to write {}", edit.to))?;
+        if edit.to != edit.from {
+            fs::remove_file(vault_dir.join(&edit.from))
+                .with_context(|| format!("Failed to remove {}", edit.from))?;
+        }
+    }
+
+    println!("Moved {} -> {}", from, to);
+    if links > 0 {
+        println!("Rewrote {} link(s) across {} file(s)", links, edits.len());
+    }
+    if renamed {
+        println!("'{}' kept as an alias", note.slug);
+    }
+    Ok(())
+}
+
+/// Resolve a note by vault-relative path, slug or alias
+fn find_note<'a>(notes: &'a [Note], query: &str) -> Option<&'a Note> {
+    let query = query.trim().trim_start_matches("./");
+    let path = if query.ends_with(".md") {
+        query.to_string()
+    } else {
+        format!("{query}.md")
+    };
+    let slug = slugify(query.strip_suffix(".md").unwrap_or(query));
+
+    notes
+        .iter()
+        .find(|note| note.source_path.as_deref() == Some(path.as_str()))
+        .or_else(|| notes.iter().find(|note| note.slug == slug))
+        .or_else(|| {
+            notes
+                .iter()
+                .find(|note| note.aliases.iter().any(|alias| slugify(alias) == slug))
+        })
+}
+
+/// Vault-relative destination: a bare name stays in the note's directory
+fn target_path(from: &str, new: &str) -> String {
+    let new = new.trim().trim_start_matches("./");
+    let new = if new.ends_with(".md") {
+        new.to_string()
+    } else {
+        format!("{new}.md")
+    };
+    match (new.contains('/'), from.rsplit_once('/')) {
+        (false, Some((dir, _))) => format!("{dir}/{new}"),
+        _ => new,
+    }
+}
+
+/// Rewrite the references in the file at `path` (its location before the
+/// move) that `mv` changes: wikilinks and embeds whose target resolves to
+/// one of the moved note's keys, keeping fragments and the text readers saw,
+/// and relative Markdown links (see `rebase_link`). Fenced and inline code
+/// are left alone. Returns the new content and the number of links rewritten.
+fn rewrite_links(content: &str, mv: &Move, path: &str) -> (String, usize) {
+    let mut out = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut fence: Option<&str> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => {}
+        }
+        if fence.is_some() || marker.is_some() {
+            out.push_str(line);
+            continue;
+        }
+
+        for (segment, is_code) in code_spans(line) {
+            if is_code {
+                out.push_str(segment);
+                continue;
+            }
+            let segment = WIKILINK_RE.replace_all(segment, |caps: &Captures| {
+                let embed = &caps[1];
+                let inner = &caps[2];
+                let (target, display) = match inner.split_once('|') {
+                    Some((target, display)) => (target, Some(display)),
+                    None => (inner, None),
+                };
+                let (base, fragment) = match target.split_once('#') {
+                    Some((base, fragment)) => (base, Some(fragment)),
+                    None => (target, None),
+                };
+                let base = base.trim();
+                if base.is_empty() || !mv.keys.contains(&slugify(base)) {
+                    return caps[0].to_string();
+                }
+
+                count += 1;
+                let fragment = fragment.map(|f| format!("#{f}")).unwrap_or_default();
+                let display = match display {
+                    Some(display) => format!("|{display}"),
+                    // Keep the text the link used to show
+                    None if embed.is_empty() => format!("|{}", target.trim()),
+                    None => String::new(),
+                };
+                format!("{embed}[[{}{fragment}{display}]]", mv.new_slug)
+            });
+            let segment = MD_LINK_RE.replace_all(&segment, |caps: &Captures| {
+                match rebase_link(&caps[2], mv, path) {
+                    Some(dest) => {
+                        count += 1;
+                        format!("{}{}{}", &caps[1], dest, &caps[3])
+                    }
+                    None => caps[0].to_string(),
+                }
+            });
+            out.push_str(&segment);
+        }
+    }
+
+    (out, count)
+}
+
+/// Split a line into text and inline code spans (flagged `true`). A run of
+/// backticks without a closing run of the same length is plain text.
+fn code_spans(line: &str) -> Vec<(&str, bool)> {
+    let bytes = line.as_bytes();
+    let backticks = |at: usize| bytes[at..].iter().take_while(|&&b| b == b'`').count();
+    let mut parts = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let run = backticks(i);
+        let mut close = None;
+        let mut j = i + run;
+        while j < bytes.len() {
+            if bytes[j] != b'`' {
+                j += 1;
+                continue;
+            }
+            let n = backticks(j);
+            if n == run {
+                close = Some(j + n);
+                break;
+            }
+            j += n;
+        }
+        match close {
+            Some(end) => {
+                parts.push((&line[text_start..i], false));
+                parts.push((&line[i..end], true));
+                text_start = end;
+                i = end;
+            }
+            None => i += run,
+        }
+    }
+    parts.push((&line[text_start..], false));
+    parts
+}
+
+/// New destination for a local Markdown link or image in the file at `path`
+/// (before the move), or `None` if it stays: links to the moved note follow
+/// it, and the moved note's own relative links are re-based on its new
+/// directory. Fragments and queries are kept.
+fn rebase_link(dest: &str, mv: &Move, path: &str) -> Option<String> {
+    let first = dest.split('/').next().unwrap_or_default();
+    if first.contains(':') || dest.starts_with(['#', '?']) {
+        return None;
+    }
+    let (link, suffix) = dest.split_at(dest.find(['#', '?']).unwrap_or(dest.len()));
+    let decoded = percent_decode_str(link).decode_utf8().ok()?;
+    let absolute = link.starts_with('/');
+    let base = if absolute { "" } else { parent_dir(path) };
+    let resolved = resolve_path(base, &decoded)?;
+
+    let new_path = if path == mv.from { mv.to } else { path };
+    let target = if resolved == mv.from {
+        mv.to
+    } else {
+        &resolved
+    };
+    if target == resolved && (absolute || parent_dir(new_path) == parent_dir(path)) {
+        return None;
+    }
+    let new_link = if absolute {
+        format!("/{target}")
+    } else {
+        relative_path(parent_dir(new_path), target)
+    };
+    Some(format!(
+        "{}{}",
+        utf8_percent_encode(&new_link, DEST_ESCAPES),
+        suffix
+    ))
+}
+
+/// Directory part of a vault-relative path ("" at the vault root)
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/')
+        .map(|(dir, _)| dir)
+        .unwrap_or_default()
+}
+
+/// Vault-relative path of `link` seen from `dir`, or `None` if it climbs
+/// out of the vault
+fn resolve_path(dir: &str, link: &str) -> Option<String> {
+    let mut segments: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in link.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Relative link from `dir` to the vault-relative path `to`
+fn relative_path(dir: &str, to: &str) -> String {
+    let from: Vec<&str> = dir.split('/').filter(|s| !s.is_empty()).collect();
+    let to: Vec<&str> = to.split('/').collect();
+    let common = from
+        .iter()
+        .zip(&to[..to.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
+
+fn file_diff(edit: &FileEdit) -> String {
+    let mut out = String::new();
+    if edit.from != edit.to {
+        out.push_str(&format!("rename {} -> {}\n", edit.from, edit.to));
+    }
+    if edit.before != edit.after {
+        out.push_str(
+            &TextDiff::from_lines(&edit.before, &edit.after)
+                .unified_diff()
+                .header(&format!("a/{}", edit.from), &format!("b/{}", edit.to))
+                .to_string(),
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moving<'a>(keys: &'a HashSet<String>, from: &'a str, to: &'a str) -> Move<'a> {
+        Move {
+            keys,
+            new_slug: "new-name",
+            from,
+            to,
+        }
+    }
+
+    #[test]
+    fn rewrites_every_spelling_and_keeps_display_text() {
+        let keys = HashSet::from(["old-name".to_string(), "legacy".to_string()]);
+        let mv = moving(&keys, "old-name.md", "new-name.md");
+        let content = "See [[Old Name]], [[old-name#Intro|the intro]], [[Legacy#^b1]].\n\
+                       ![[old-name#Intro]] and [[other]].\n\
+                       ```\n[[Old Name]]\n```\n";
+        let (out, count) = rewrite_links(content, &mv, "other.md");
+        assert_eq!(count, 4);
+        assert_eq!(
+            out,
+            "See [[new-name|Old Name]], [[new-name#Intro|the intro]], [[new-name#^b1|Legacy#^b1]].\n\
+             ![[new-name#Intro]] and [[other]].\n\
+             ```\n[[Old Name]]\n```\n"
+        );
+    }
+
+    #[test]
+    fn inline_code_is_left_alone() {
+        let keys = HashSet::from(["old-name".to_string()]);
+        let mv = moving(&keys, "old-name.md", "new-name.md");
+        let content = "Write `[[Old Name]]` or ``a ` [[old-name]]`` for [[Old Name]]; \
+                       `[x](old-name.md)` stays.\n";
+        let (out, count) = rewrite_links(content, &mv, "other.md");
+        assert_eq!(count, 1);
+        assert_eq!(
+            out,
+            "Write `[[Old Name]]` or ``a ` [[old-name]]`` for [[new-name|Old Name]]; \
+             `[x](old-name.md)` stays.\n"
+        );
+    }
+
+    #[test]
+    fn markdown_links_follow_the_moved_note() {
+        let keys = HashSet::new();
+        let mv = moving(&keys, "essays/old.md", "archive/new.md");
+        let content = "[a](old.md#Intro), [b](./old.md \"Title\"), [c](/essays/old.md), \
+                       [d](other.md) and [e](https://example.com/old.md).\n";
+        let (out, count) = rewrite_links(content, &mv, "essays/index.md");
+        assert_eq!(count, 3);
+        assert_eq!(
+            out,
+            "[a](../archive/new.md#Intro), [b](../archive/new.md \"Title\"), \
+             [c](/archive/new.md), [d](other.md) and [e](https://example.com/old.md).\n"
+        );
+
+        let (out, _) = rewrite_links("[back](../essays/old.md)\n", &mv, "guides/setup.md");
+        assert_eq!(out, "[back](../archive/new.md)\n");
+    }
+
+    #[test]
+    fn moved_note_relative_links_are_rebased() {
+        let keys = HashSet::new();
+        let mv = moving(&keys, "essays/old.md", "archive/2024/new.md");
+        let content = "![plot](img/my%20plot.png) [sib](sibling.md#Part) [self](old.md) \
+                       [root](/guides/setup.md) [up](../index.md)\n";
+        let (out, count) = rewrite_links(content, &mv, "essays/old.md");
+        assert_eq!(count, 4);
+        assert_eq!(
+            out,
+            "![plot](../../essays/img/my%20plot.png) [sib](../../essays/sibling.md#Part) \
+             [self](new.md) [root](/guides/setup.md) [up](../../index.md)\n"
+        );
+    }
+
+    #[test]
+    fn bare_names_stay_in_the_same_directory() {
+        assert_eq!(target_path("essays/old.md", "new"), "essays/new.md");
+        assert_eq!(target_path("essays/old.md", "notes/new.md"), "notes/new.md");
+        assert_eq!(target_path("old.md", "new"), "new.md");
+    }
+}
@@ -1,4 +1,5 @@
-///! Search command implementation
+//! Search command implementation
+
 use crate::agent;
 use anyhow::{Context, Result};
 use monowiki_core::{Config, SearchEntry};
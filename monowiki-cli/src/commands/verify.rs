@@ -1,7 +1,7 @@
 //! Verify vault health and emit diagnostics for agents.
 
 use anyhow::{Context, Result};
-use monowiki_core::{Config, SiteBuilder};
+use monowiki_core::{AssetIndex, Config, Diagnostic, DiagnosticSeverity, SiteBuilder};
 use serde::Serialize;
 use std::path::Path;
 
@@ -22,7 +22,22 @@ pub fn verify_site(config_path: &Path, json: bool) -> Result<()> {
         .build()
         .context("Failed to build site for verification")?;
 
-    let diagnostics = site_index.diagnostics;
+    let mut diagnostics = site_index.diagnostics;
+    // Assets no note refers to, published or not, can be pruned from the vault
+    let assets = AssetIndex::scan(&config);
+    let used = builder
+        .referenced_assets(&assets)
+        .context("Failed to collect asset references")?;
+    diagnostics.extend(assets.orphaned(&used).into_iter().map(|path| Diagnostic {
+        code: "asset.orphaned".to_string(),
+        message: format!("Asset '{}' is not referenced by any note", path),
+        severity: DiagnosticSeverity::Info,
+        note_slug: None,
+        source_path: Some(path.to_string()),
+        context: None,
+        anchor: None,
+        span: None,
+    }));
     let errors = diagnostics
         .iter()
         .filter(|d| d.severity == monowiki_core::DiagnosticSeverity::Error)
@@ -61,7 +76,10 @@ pub fn verify_site(config_path: &Path, json: bool) -> Result<()> {
             let source = diag
                 .source_path
                 .as_deref()
-                .map(|s| format!(" ({})", s))
+                .map(|s| match &diag.span {
+                    Some(span) => format!(" ({}:{}:{})", s, span.line, span.column),
+                    None => format!(" ({})", s),
+                })
                 .unwrap_or_default();
             println!(
                 "- {:?} {}{} {}: {}",
@@ -0,0 +1,473 @@
+//! Responsive images: resized and WebP variants of vault raster images, and
+//! rewriting of `<img>` tags into `srcset` / `<picture>` markup.
+//!
+//! Variants are cached outside the output directory by the source image's
+//! content hash, so unchanged images are not decoded again on later builds.
+//! Variant names carry a prefix of that hash too, so images whose names
+//! differ only by extension don't share variants.
+
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use monowiki_core::config::ImageConfig;
+use monowiki_core::manifest::content_hash;
+use monowiki_core::{AssetIndex, Config};
+use once_cell::sync::Lazy;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use rayon::prelude::*;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+
+static IMG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<img\s[^>]*>").unwrap());
+static SRC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\ssrc="([^"]*)""#).unwrap());
+static WIDTH_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\swidth="(\d+)""#).unwrap());
+
+/// Cache of generated variants, inside `Config::cache_dir`
+const CACHE_DIR: &str = "images";
+
+/// Raster formats that get variants
+const RASTER_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Characters escaped in candidate URLs (spaces would split a `srcset`)
+const URL_ESCAPES: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?');
+
+/// Hex digits of the source hash in variant names
+const NAME_HASH_LEN: usize = 8;
+
+/// One size of an image: its URL and pixel width
+#[derive(Debug, Clone, PartialEq)]
+struct Candidate {
+    url: String,
+    width: u32,
+}
+
+/// Variants of a single source image
+#[derive(Debug, Clone)]
+struct ResponsiveImage {
+    width: u32,
+    height: u32,
+    /// Same-format candidates, smallest first; the last is the fallback `src`
+    sizes: Vec<Candidate>,
+    /// WebP candidates (empty when disabled or the source is WebP already)
+    webp: Vec<Candidate>,
+}
+
+/// A generated variant file to place in the output directory
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// Output path relative to the output directory
+    pub path: String,
+    /// Cached file holding the variant
+    pub cached: PathBuf,
+    /// Content key of the variant (source hash, width and quality)
+    pub hash: String,
+}
+
+/// Responsive variants keyed by the vault-relative path of the original
+#[derive(Debug, Default)]
+pub struct ImageSet {
+    images: HashMap<String, ResponsiveImage>,
+    variants: Vec<Variant>,
+    base_url: String,
+}
+
+impl ImageSet {
+    /// Generate (or reuse cached) variants for every raster image in the
+    /// vault; `variants` lists the files to copy into the output directory.
+    /// Images that cannot be decoded are left alone (the original is still
+    /// copied as an asset).
+    pub fn build(config: &Config, assets: &AssetIndex, base_url: &str) -> Result<Self> {
+        let settings = &config.images;
+        if !settings.responsive {
+            return Ok(Self::default());
+        }
+
+        let vault_dir = config.vault_dir();
+        let cache_dir = config.cache_dir().join(CACHE_DIR);
+        fs::create_dir_all(&cache_dir)
+            .with_context(|| format!("Failed to create image cache {:?}", cache_dir))?;
+
+        let raster: Vec<&str> = assets
+            .paths()
+            .filter(|path| raster_extension(path).is_some())
+            .collect();
+        let processed = raster
+            .par_iter()
+            .filter_map(|rel| {
+                match process_image(settings, &vault_dir, &cache_dir, rel, base_url) {
+                    Ok((image, variants)) => Some(Ok((rel.to_string(), image, variants))),
+                    Err(err) if err.is::<image::ImageError>() => {
+                        tracing::warn!("Skipping responsive variants for {}: {}", rel, err);
+                        None
+                    }
+                    Err(err) => Some(Err(err)),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut set = Self {
+            base_url: base_url.to_string(),
+            ..Default::default()
+        };
+        for (rel, image, variants) in processed {
+            set.images.insert(rel, image);
+            set.variants.extend(variants);
+        }
+        tracing::info!(
+            "Prepared responsive variants for {} images",
+            set.images.len()
+        );
+        Ok(set)
+    }
+
+    /// Variant files to copy into the output directory
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// Rewrite `<img>` tags pointing at known images into `srcset` markup
+    /// (wrapped in `<picture>` when WebP variants exist), with intrinsic
+    /// `width` / `height`. `page` is the page's path in the output directory,
+    /// for relative `src`s. Tags with `data-no-resize` or their own `srcset`
+    /// are left alone.
+    pub fn rewrite_html<'a>(&self, html: &'a str, page: &str) -> Cow<'a, str> {
+        if self.images.is_empty() || !html.contains("<img") {
+            return Cow::Borrowed(html);
+        }
+        IMG_RE.replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            if tag.contains("data-no-resize") || tag.contains("srcset=") {
+                return tag.to_string();
+            }
+            let image = SRC_RE
+                .captures(tag)
+                .and_then(|src| self.source_path(&src[1], page))
+                .and_then(|rel| self.images.get(&rel));
+            match image {
+                Some(image) => picture_markup(tag, image),
+                None => tag.to_string(),
+            }
+        })
+    }
+}
+
+impl ImageSet {
+    /// Vault-relative path of the image an `<img src>` on `page` loads:
+    /// percent-decoded, with `./` and `../` resolved. `None` for other sites.
+    fn source_path(&self, src: &str, page: &str) -> Option<String> {
+        let src = src.split(['?', '#']).next().unwrap_or_default();
+        let src = percent_decode_str(src).decode_utf8().ok()?;
+        let path = match src.strip_prefix(self.base_url.as_str()) {
+            Some(rel) => rel.to_string(),
+            None if src.starts_with('/') || src.contains(':') => return None,
+            None => match page.rsplit_once('/') {
+                Some((dir, _)) => format!("{dir}/{src}"),
+                None => src.into_owned(),
+            },
+        };
+
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop()?;
+                }
+                segment => segments.push(segment),
+            }
+        }
+        Some(segments.join("/"))
+    }
+}
+
+fn raster_extension(path: &str) -> Option<String> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    RASTER_EXTENSIONS.contains(&ext.as_str()).then_some(ext)
+}
+
+/// Generate (or reuse cached) variants of one image, to be placed next to
+/// the original in the output directory
+fn process_image(
+    settings: &ImageConfig,
+    vault_dir: &Path,
+    cache_dir: &Path,
+    rel: &str,
+    base_url: &str,
+) -> Result<(ResponsiveImage, Vec<Variant>)> {
+    let ext = raster_extension(rel).unwrap_or_default();
+    let bytes = fs::read(vault_dir.join(rel)).with_context(|| format!("Failed to read {}", rel))?;
+    let hash = content_hash(&bytes);
+    let (width, height) = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()?
+        .into_dimensions()?;
+
+    // Configured widths below the original's, plus the original itself when
+    // it isn't larger than the largest configured width
+    let max_width = settings.widths.iter().copied().max().unwrap_or(width);
+    let mut widths: Vec<u32> = settings
+        .widths
+        .iter()
+        .copied()
+        .filter(|w| *w > 0 && *w < width)
+        .collect();
+    if width <= max_width {
+        widths.push(width);
+    }
+    widths.sort_unstable();
+    widths.dedup();
+
+    let stem = rel.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(rel);
+    let name_hash = &hash[..NAME_HASH_LEN.min(hash.len())];
+    let mut decoded: Option<DynamicImage> = None;
+    let mut files = Vec::new();
+    let mut variant = |w: u32, format: &str| -> Result<Candidate> {
+        let rel_out = if w == width && format == ext {
+            rel.to_string()
+        } else {
+            format!("{stem}-{name_hash}-{w}w.{format}")
+        };
+        if rel_out != rel {
+            // Only JPEG output depends on the quality setting
+            let quality = match format {
+                "jpg" | "jpeg" => format!("-q{}", settings.jpeg_quality),
+                _ => String::new(),
+            };
+            let key = format!("{hash}-{w}{quality}");
+            let cached = cache_dir.join(format!("{key}.{format}"));
+            if !cached.exists() {
+                let source = match decoded.take() {
+                    Some(image) => image,
+                    None => image::load_from_memory(&bytes)?,
+                };
+                let resized = if w == width {
+                    source.clone()
+                } else {
+                    source.resize(w, u32::MAX, FilterType::Lanczos3)
+                };
+                encode(&resized, format, settings.jpeg_quality, &cached)?;
+                decoded = Some(source);
+            }
+            files.push(Variant {
+                path: rel_out.clone(),
+                cached,
+                hash: key,
+            });
+        }
+        Ok(Candidate {
+            url: format!("{base_url}{}", utf8_percent_encode(&rel_out, URL_ESCAPES)),
+            width: w,
+        })
+    };
+
+    let sizes = widths
+        .iter()
+        .map(|w| variant(*w, &ext))
+        .collect::<Result<Vec<_>>>()?;
+    let webp = if settings.webp && ext != "webp" {
+        widths
+            .iter()
+            .map(|w| variant(*w, "webp"))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+
+    Ok((
+        ResponsiveImage {
+            width,
+            height,
+            sizes,
+            webp,
+        },
+        files,
+    ))
+}
+
+fn encode(image: &DynamicImage, format: &str, jpeg_quality: u8, path: &Path) -> Result<()> {
+    let file = fs::File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    match format {
+        "jpg" | "jpeg" => {
+            let encoder = JpegEncoder::new_with_quality(&mut writer, jpeg_quality.clamp(1, 100));
+            image.to_rgb8().write_with_encoder(encoder)?;
+        }
+        "webp" => image.to_rgba8().write_to(&mut writer, ImageFormat::WebP)?,
+        _ => image.write_to(&mut writer, ImageFormat::Png)?,
+    }
+    Ok(())
+}
+
+fn srcset(candidates: &[Candidate]) -> String {
+    candidates
+        .iter()
+        .map(|c| format!("{} {}w", c.url, c.width))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `tag` with `srcset`, the fallback `src` and intrinsic dimensions, wrapped
+/// in `<picture>` when there are WebP candidates
+fn picture_markup(tag: &str, image: &ResponsiveImage) -> String {
+    let Some(fallback) = image.sizes.last() else {
+        return tag.to_string();
+    };
+    let body = tag.trim_end_matches('>').trim_end_matches('/').trim_end();
+    let mut img = SRC_RE
+        .replace(body, format!(" src=\"{}\"", fallback.url))
+        .into_owned();
+
+    // Keep a width the author asked for and scale the height to match
+    let mut sizes = String::new();
+    match WIDTH_RE
+        .captures(tag)
+        .and_then(|caps| caps[1].parse::<u32>().ok())
+    {
+        Some(width) => {
+            if !tag.contains(" height=") {
+                let height = (u64::from(width) * u64::from(image.height)
+                    / u64::from(image.width.max(1))) as u32;
+                img.push_str(&format!(" height=\"{height}\""));
+            }
+            sizes = format!(" sizes=\"{width}px\"");
+        }
+        None => img.push_str(&format!(
+            " width=\"{}\" height=\"{}\"",
+            image.width, image.height
+        )),
+    }
+    if image.sizes.len() > 1 {
+        img.push_str(&format!(" srcset=\"{}\"{}", srcset(&image.sizes), sizes));
+    }
+    img.push('>');
+
+    if image.webp.is_empty() {
+        return img;
+    }
+    format!(
+        "<picture><source type=\"image/webp\" srcset=\"{}\"{}>{}</picture>",
+        srcset(&image.webp),
+        sizes,
+        img
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_set() -> ImageSet {
+        let candidate = |url: &str, width| Candidate {
+            url: url.to_string(),
+            width,
+        };
+        let image = ResponsiveImage {
+            width: 1200,
+            height: 600,
+            sizes: vec![
+                candidate("/img/a-480w.png", 480),
+                candidate("/img/a.png", 1200),
+            ],
+            webp: vec![
+                candidate("/img/a-480w.webp", 480),
+                candidate("/img/a-1200w.webp", 1200),
+            ],
+        };
+        ImageSet {
+            images: HashMap::from([("img/a.png".to_string(), image)]),
+            variants: Vec::new(),
+            base_url: "/".to_string(),
+        }
+    }
+
+    #[test]
+    fn img_tags_become_picture_markup() {
+        let html = image_set()
+            .rewrite_html("<p><img src=\"/img/a.png\" alt=\"A\" /></p>", "a.html")
+            .into_owned();
+        assert_eq!(
+            html,
+            "<p><picture><source type=\"image/webp\" srcset=\"/img/a-480w.webp 480w, \
+             /img/a-1200w.webp 1200w\"><img src=\"/img/a.png\" alt=\"A\" width=\"1200\" \
+             height=\"600\" srcset=\"/img/a-480w.png 480w, /img/a.png 1200w\"></picture></p>"
+        );
+    }
+
+    #[test]
+    fn author_widths_scale_height_and_opt_outs_are_kept() {
+        let set = image_set();
+        let html = set.rewrite_html("<img src=\"/img/a.png\" alt=\"A\" width=\"400\">", "a.html");
+        assert!(html.contains("width=\"400\" height=\"200\""));
+        assert!(html.contains("sizes=\"400px\""));
+
+        let kept = "<img src=\"/img/a.png\" data-no-resize> <img src=\"/other.png\">";
+        assert_eq!(set.rewrite_html(kept, "a.html"), kept);
+    }
+
+    #[test]
+    fn srcs_are_normalized_before_lookup() {
+        let mut set = image_set();
+        let image = set.images.remove("img/a.png").unwrap();
+        set.images.insert("img/my photo.png".to_string(), image);
+
+        for (src, page) in [
+            ("/img/my%20photo.png", "a.html"),
+            ("./img/my%20photo.png", "a.html"),
+            ("../img/my photo.png?v=2", "essays/a.html"),
+        ] {
+            let tag = format!("<img src=\"{src}\">");
+            let html = set.rewrite_html(&tag, page);
+            assert!(html.starts_with("<picture>"), "{src}: {html}");
+        }
+        let remote = "<img src=\"https://example.com/img/my%20photo.png\">";
+        assert_eq!(set.rewrite_html(remote, "a.html"), remote);
+    }
+
+    #[test]
+    fn variant_names_are_unique_per_source() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let vault = dir.path().join("vault");
+        let cache = dir.path().join("cache");
+        fs::create_dir_all(vault.join("a"))?;
+        fs::create_dir_all(vault.join("b"))?;
+        fs::create_dir_all(&cache)?;
+        for (rel, shade) in [("a/x.png", 10), ("b/x.png", 200), ("a/x.jpg", 90)] {
+            let format = ImageFormat::from_path(rel)?;
+            image::RgbImage::from_pixel(64, 32, image::Rgb([shade, shade, shade]))
+                .save_with_format(vault.join(rel), format)?;
+        }
+        let settings = ImageConfig {
+            responsive: true,
+            widths: vec![32, 64],
+            webp: true,
+            jpeg_quality: 80,
+        };
+
+        let mut paths = Vec::new();
+        for rel in ["a/x.png", "b/x.png", "a/x.jpg"] {
+            let (image, variants) = process_image(&settings, &vault, &cache, rel, "/")?;
+            let dir = rel.split('/').next().unwrap();
+            assert!(variants
+                .iter()
+                .all(|v| v.path.starts_with(&format!("{dir}/x-"))));
+            assert_eq!(image.webp.len(), 2);
+            paths.extend(variants.into_iter().map(|v| v.path));
+        }
+        let count = paths.len();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), count, "{paths:?}");
+        Ok(())
+    }
+}
@@ -5,8 +5,10 @@
 mod agent;
 mod cache;
 mod commands;
+mod images;
 
 use clap::{Parser, Subcommand, ValueEnum};
+use monowiki_core::dates::{parse_date, ParsedDate};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -34,7 +36,16 @@ enum Commands {
     },
 
     /// Build the static site
-    Build,
+    Build {
+        /// Re-render every note, ignoring the incremental build manifest
+        #[arg(long)]
+        force: bool,
+
+        /// Build the site as of this date or timestamp, e.g. to preview notes
+        /// scheduled with publish_after
+        #[arg(long, value_parser = parse_as_of)]
+        as_of: Option<ParsedDate>,
+    },
 
     /// Start development server with live reload
     Dev {
@@ -114,6 +125,19 @@ enum Commands {
         with_diff: bool,
     },
 
+    /// Move or rename a note, rewriting wikilinks and comment targets
+    Mv {
+        /// Note slug, alias or vault-relative path
+        old: String,
+
+        /// New name or vault-relative path (a bare name stays in the same directory)
+        new: String,
+
+        /// Print a unified diff of the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Verify vault health and emit diagnostics
     Verify {
         /// Emit JSON instead of text
@@ -179,7 +203,9 @@ async fn main() -> anyhow::Result<()> {
 
     match cli.command {
         Commands::Init { path } => commands::init_project(path.as_deref()),
-        Commands::Build => commands::build_site(&cli.config),
+        Commands::Build { force, as_of } => {
+            commands::build_site(&cli.config, commands::BuildOptions { force, as_of })
+        }
         Commands::Dev { port } => commands::dev_server(&cli.config, port).await,
         Commands::Search {
             query,
@@ -216,6 +242,9 @@ async fn main() -> anyhow::Result<()> {
                 max_depth,
                 json,
             } => commands::graph_path(&cli.config, &from, &to, max_depth, json),
+            GraphCommands::Mentions { slug, json } => {
+                commands::graph_mentions(&cli.config, &slug, json)
+            }
         },
         Commands::Export { command } => match command {
             ExportCommands::Sections {
@@ -243,6 +272,7 @@ async fn main() -> anyhow::Result<()> {
             with_sections || with_diff || json,
             with_diff || json,
         ),
+        Commands::Mv { old, new, dry_run } => commands::move_note(&cli.config, &old, &new, dry_run),
         Commands::Verify { json } => commands::verify_site(&cli.config, json),
         Commands::Comment { command } => match command {
             CommentCommands::List { slug, status, json } => {
@@ -260,15 +290,17 @@ async fn main() -> anyhow::Result<()> {
                 body,
             } => commands::add_comment(
                 &cli.config,
-                &slug,
-                anchor.as_deref(),
-                reply_to.as_deref(),
-                quote.as_deref(),
-                author.as_deref(),
-                git_ref.as_deref(),
-                tags,
-                status.as_deref(),
-                &body,
+                commands::NewComment {
+                    target_slug: &slug,
+                    target_anchor: anchor.as_deref(),
+                    reply_to: reply_to.as_deref(),
+                    quote: quote.as_deref(),
+                    author: author.as_deref(),
+                    git_ref: git_ref.as_deref(),
+                    tags,
+                    status: status.as_deref(),
+                    body: &body,
+                },
             ),
         },
         Commands::Status {
@@ -284,6 +316,12 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+fn parse_as_of(value: &str) -> Result<ParsedDate, String> {
+    parse_date(value).ok_or_else(|| {
+        format!("expected YYYY-MM-DD or a timestamp like 2024-08-19T10:00:00Z, got '{value}'")
+    })
+}
+
 #[derive(Copy, Clone, ValueEnum)]
 pub enum NoteFormat {
     Json,
@@ -341,6 +379,16 @@ pub enum GraphCommands {
         #[arg(long)]
         json: bool,
     },
+
+    /// Unlinked mentions of a note's title or aliases in other notes
+    Mentions {
+        /// Note slug or alias
+        slug: String,
+
+        /// Emit JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
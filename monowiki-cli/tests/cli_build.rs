@@ -0,0 +1,822 @@
+use assert_cmd::Command;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_site(root: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(root.join("vault"))?;
+    fs::write(
+        root.join("monowiki.yml"),
+        r#"
+site:
+  title: "Test"
+  author: "Tester"
+  description: "Desc"
+  url: "https://example.com"
+paths:
+  vault: "vault"
+  output: "docs"
+base_url: "/"
+"#,
+    )?;
+    fs::write(
+        root.join("vault/alpha.md"),
+        "---\ntitle: Alpha\n---\n\nLinks to [[beta]].\n",
+    )?;
+    fs::write(
+        root.join("vault/beta.md"),
+        "---\ntitle: Beta\n---\n\nOriginal beta body.\n",
+    )?;
+    Ok(())
+}
+
+fn build(root: &Path, extra: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    #[allow(deprecated)]
+    Command::cargo_bin("monowiki")?
+        .current_dir(root)
+        .arg("build")
+        .args(extra)
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn incremental_build_tracks_changes_and_removes_stale_pages(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    assert_eq!(manifest["notes"]["alpha.md"]["links"][0], "beta");
+    assert!(manifest["outputs"]["beta.html"].is_string());
+
+    // Editing a note is picked up on the next build
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\n---\n\nUpdated beta body.\n",
+    )?;
+    build(dir.path(), &[])?;
+    let beta = fs::read_to_string(docs.join("beta.html"))?;
+    assert!(beta.contains("Updated beta body."));
+
+    // Removing a note removes its page and re-resolves links pointing at it
+    fs::remove_file(dir.path().join("vault/beta.md"))?;
+    build(dir.path(), &[])?;
+    assert!(!docs.join("beta.html").exists());
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    assert_eq!(
+        manifest["notes"]["alpha.md"]["diagnostics"][0]["code"],
+        "link.unresolved"
+    );
+
+    // Forced builds still succeed and rewrite the manifest
+    build(dir.path(), &["--force"])?;
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    assert!(manifest["notes"]["beta.md"].is_null());
+    assert!(manifest["outputs"]["beta.html"].is_null());
+
+    Ok(())
+}
+
+#[test]
+fn build_output_is_independent_of_thread_count() -> Result<(), Box<dyn std::error::Error>> {
+    let mut outputs = Vec::new();
+    for threads in ["1", "4"] {
+        let dir = tempdir()?;
+        write_site(dir.path())?;
+        for i in 0..20 {
+            fs::write(
+                dir.path().join(format!("vault/note-{i}.md")),
+                format!("# Note {i}\n\n## Section\n\nSee [[alpha]] and [[missing-{i}]].\n"),
+            )?;
+        }
+
+        #[allow(deprecated)]
+        Command::cargo_bin("monowiki")?
+            .current_dir(dir.path())
+            .env("RAYON_NUM_THREADS", threads)
+            .arg("build")
+            .assert()
+            .success();
+
+        let docs = dir.path().join("docs");
+        let mut artifacts = Vec::new();
+        for name in ["index.json", "graph.json", ".build_manifest.json"] {
+            artifacts.push(fs::read_to_string(docs.join(name))?);
+        }
+        outputs.push(artifacts);
+    }
+
+    assert_eq!(outputs[0], outputs[1]);
+    Ok(())
+}
+
+#[test]
+fn embedded_notes_rerender_hosts_and_record_embed_edges() -> Result<(), Box<dyn std::error::Error>>
+{
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    fs::write(
+        dir.path().join("vault/host.md"),
+        "---\ntitle: Host\n---\n\nBefore.\n\n![[beta]]\n\nAfter.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let host = fs::read_to_string(docs.join("host.html"))?;
+    assert!(host.contains("class=\"transclusion\""));
+    assert!(host.contains("Original beta body."));
+
+    let graph: Value = serde_json::from_slice(&fs::read(docs.join("graph.json"))?)?;
+    let edges = graph["edges"].as_array().unwrap();
+    assert!(edges.iter().any(|edge| edge["source"] == "host"
+        && edge["target"] == "beta"
+        && edge["kind"] == "embed"));
+
+    // Only the embedded note changed, but the host must pick up the edit
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\n---\n\nUpdated beta body.\n",
+    )?;
+    build(dir.path(), &[])?;
+    let host = fs::read_to_string(docs.join("host.html"))?;
+    assert!(host.contains("Updated beta body."));
+
+    Ok(())
+}
+
+#[test]
+fn tag_pages_list_notes_and_roll_up_nested_tags() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    fs::write(
+        dir.path().join("vault/tokio.md"),
+        "---\ntitle: Tokio\ndate: 2024-03-01\ndescription: Async runtime notes\ntags: [rust/async]\n---\n\nBody.\n",
+    )?;
+    fs::write(
+        dir.path().join("vault/ownership.md"),
+        "---\ntitle: Ownership\ndate: 2023-01-15\ntags: [rust]\n---\n\nBody.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let index = fs::read_to_string(docs.join("tags/index.html"))?;
+    assert!(index.contains("href=\"/tags/rust.html\""));
+    assert!(index.contains("href=\"/tags/rust/async.html\""));
+
+    // The parent tag lists notes from nested tags, newest first
+    let rust = fs::read_to_string(docs.join("tags/rust.html"))?;
+    let tokio = rust.find("Tokio").unwrap();
+    let ownership = rust.find("Ownership").unwrap();
+    assert!(tokio < ownership);
+    assert!(rust.contains("2024-03-01"));
+    assert!(rust.contains("Async runtime notes"));
+
+    let nested = fs::read_to_string(docs.join("tags/rust/async.html"))?;
+    assert!(nested.contains("Tokio") && !nested.contains("Ownership"));
+
+    // Tags on a post link to their listing page
+    let post = fs::read_to_string(docs.join("tokio.html"))?;
+    assert!(post.contains("<a href=\"/tags/rust/async.html\"><code>rust/async</code></a>"));
+
+    // Pages for tags that disappear are removed
+    fs::remove_file(dir.path().join("vault/tokio.md"))?;
+    build(dir.path(), &[])?;
+    assert!(!docs.join("tags/rust/async.html").exists());
+    assert!(docs.join("tags/rust.html").exists());
+
+    Ok(())
+}
+
+#[test]
+fn note_type_settings_control_rss_and_search() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str(
+        "note_types:\n  paper: {}\n  meeting: { listed: false, rss: false, search: false }\n",
+    );
+    fs::write(&config_path, config)?;
+    fs::write(
+        dir.path().join("vault/standup.md"),
+        "---\ntitle: Standup\ntype: meeting\n---\n\nInternal notes.\n",
+    )?;
+    fs::write(
+        dir.path().join("vault/results.md"),
+        "---\ntitle: Results\ntype: paper\n---\n\nFindings.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    assert!(docs.join("standup.html").exists());
+    let rss = fs::read_to_string(docs.join("rss.xml"))?;
+    assert!(rss.contains("Results") && !rss.contains("Standup"));
+
+    let index: Value = serde_json::from_slice(&fs::read(docs.join("index.json"))?)?;
+    let entries = index.as_array().unwrap();
+    assert!(entries.iter().any(|e| e["type"] == "paper"));
+    assert!(!entries.iter().any(|e| e["title"] == "Standup"));
+
+    Ok(())
+}
+
+#[test]
+fn git_dates_fill_missing_dates_and_timestamps_reach_rss() -> Result<(), Box<dyn std::error::Error>>
+{
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str("git_dates: true\n");
+    fs::write(&config_path, config)?;
+    fs::write(
+        dir.path().join("vault/timed.md"),
+        "---\ntitle: Timed\ndate: 2024-08-19T10:00:00+02:00\n---\n\nBody.\n",
+    )?;
+    // git quotes non-ASCII paths unless they are NUL-terminated
+    fs::write(
+        dir.path().join("vault/résumé notes.md"),
+        "---\ntitle: Résumé\nslug: resume\n---\n\nBody.\n",
+    )?;
+
+    let git = |args: &[&str], date: &str| -> Result<(), Box<dyn std::error::Error>> {
+        let status = std::process::Command::new("git")
+            .current_dir(dir.path())
+            .args(["-c", "user.name=Tester", "-c", "user.email=t@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .status()?;
+        assert!(status.success());
+        Ok(())
+    };
+    git(&["init", "-q"], "2023-01-01T00:00:00Z")?;
+    git(&["add", "."], "2023-01-01T00:00:00Z")?;
+    git(&["commit", "-q", "-m", "init"], "2023-01-02T09:00:00+01:00")?;
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\n---\n\nEdited.\n",
+    )?;
+    git(&["commit", "-q", "-am", "edit"], "2023-03-04T12:00:00Z")?;
+
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let rss = fs::read_to_string(docs.join("rss.xml"))?;
+    assert!(rss.contains("<pubDate>Mon, 19 Aug 2024 10:00:00 +0200</pubDate>"));
+    // beta has no frontmatter dates: created and updated come from git
+    assert!(rss.contains("<pubDate>Sat, 4 Mar 2023 12:00:00 +0000</pubDate>"));
+    let beta = fs::read_to_string(docs.join("beta.html"))?;
+    assert!(beta.contains("Published: 2023-01-02"));
+    assert!(beta.contains("Updated: 2023-03-04"));
+    let resume = fs::read_to_string(docs.join("resume.html"))?;
+    assert!(resume.contains("Published: 2023-01-02"));
+
+    Ok(())
+}
+
+#[test]
+fn scheduled_notes_are_hidden_until_as_of() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\npublish_after: 3000-01-01T09:00:00Z\n---\n\nOriginal beta body.\n",
+    )?;
+    let docs = dir.path().join("docs");
+    let published = |docs: &Path| -> Result<bool, Box<dyn std::error::Error>> {
+        let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+        let listed = ["rss.xml", "sitemap.xml", "index.json", "graph.json"]
+            .iter()
+            .map(|file| fs::read_to_string(docs.join(file)))
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .filter(|contents| contents.contains("beta"))
+            .count();
+        let linked = alpha.contains("href=\"/beta.html\"");
+        assert_eq!(docs.join("beta.html").exists(), linked);
+        assert_eq!(listed == 4, linked, "beta listed in {listed} of 4 files");
+        Ok(linked)
+    };
+
+    build(dir.path(), &[])?;
+    assert!(!published(&docs)?);
+    assert!(fs::read_to_string(docs.join("alpha.html"))?.contains("Links to beta."));
+
+    build(dir.path(), &["--as-of", "3000-01-01 10:00"])?;
+    assert!(published(&docs)?);
+
+    // Leaving the preview removes the page again
+    build(dir.path(), &[])?;
+    assert!(!published(&docs)?);
+
+    #[allow(deprecated)]
+    Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["build", "--as-of", "someday"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn unlinked_mentions_panel_and_command() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str("enable_mentions: true\n");
+    fs::write(&config_path, config)?;
+    fs::write(
+        dir.path().join("vault/alpha.md"),
+        "---\ntitle: Alpha\n---\n\nLinks to [[beta]].\n\n## Later\n\nBeta again, unlinked.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let beta = fs::read_to_string(dir.path().join("docs/beta.html"))?;
+    assert!(beta.contains("Unlinked mentions"));
+    assert!(beta.contains("href=\"/alpha.html#later\""));
+    assert!(beta.contains("Beta again, unlinked."));
+    let alpha = fs::read_to_string(dir.path().join("docs/alpha.html"))?;
+    assert!(!alpha.contains("Unlinked mentions"));
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["graph", "mentions", "beta", "--json"])
+        .output()?;
+    assert!(output.status.success());
+    let value: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(value["kind"], "graph.mentions");
+    assert_eq!(value["data"]["total"], 1);
+    assert_eq!(value["data"]["mentions"][0]["section"], "Later");
+    Ok(())
+}
+
+#[test]
+fn aliases_and_redirect_from_write_redirect_stubs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\naliases: [Second Letter, Alpha]\nredirect_from: [/old/beta/]\n---\n\nBody.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let stub = fs::read_to_string(docs.join("second-letter.html"))?;
+    assert!(stub.contains("<meta http-equiv=\"refresh\" content=\"0; url=/beta.html\">"));
+    assert!(stub.contains("<link rel=\"canonical\" href=\"https://example.com/beta.html\">"));
+    assert!(docs.join("old/beta/index.html").exists());
+    // The real alpha page wins over the alias
+    assert!(fs::read_to_string(docs.join("alpha.html"))?.contains("Links to"));
+
+    let rules = fs::read_to_string(docs.join("_redirects"))?;
+    assert_eq!(
+        rules,
+        "/second-letter.html /beta.html 301\n/old/beta/ /beta.html 301\n"
+    );
+    let manifest: Value = serde_json::from_str(&fs::read_to_string(docs.join("redirects.json"))?)?;
+    assert_eq!(manifest[1]["from"], "/old/beta/");
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["verify", "--json"])
+        .output()?;
+    let report = String::from_utf8(output.stdout)?;
+    assert!(report.contains("redirect.collision"), "{report}");
+
+    // Dropping the alias removes its stub on the next build
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\n---\n\nBody.\n",
+    )?;
+    build(dir.path(), &[])?;
+    assert!(!docs.join("second-letter.html").exists());
+    assert!(!docs.join("_redirects").exists());
+    Ok(())
+}
+
+#[test]
+fn attachments_resolve_and_verify_reports_missing_and_orphaned_assets(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let vault = dir.path().join("vault");
+    fs::create_dir_all(vault.join("assets"))?;
+    fs::write(vault.join("assets/diagram.png"), b"png")?;
+    fs::write(vault.join("assets/unused.pdf"), b"pdf")?;
+    fs::write(vault.join("assets/clip.mp4"), b"mp4")?;
+    fs::write(vault.join("assets/user guide.pdf"), b"pdf")?;
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\n![[diagram.png|400]]\n\n![chart](assets/chart.png)\n\n\
+         <video src=\"assets/clip.mp4\" controls></video>\n\n\
+         Read <a href=\"assets/user%20guide.pdf\">the guide</a>.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains("<img src=\"/assets/diagram.png\" alt=\"diagram.png\" width=\"400\">"));
+    assert!(docs.join("assets/diagram.png").exists());
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["verify", "--json"])
+        .output()?;
+    let report = String::from_utf8(output.stdout)?;
+    assert!(report.contains("\"code\": \"asset.missing\""), "{report}");
+    assert!(report.contains("\"context\": \"assets/chart.png\""));
+    assert!(report.contains("\"code\": \"asset.orphaned\""));
+    assert!(report.contains("\"source_path\": \"assets/unused.pdf\""));
+    assert!(!report.contains("\"source_path\": \"assets/diagram.png\""));
+    // Raw HTML references count as uses
+    assert!(!report.contains("\"source_path\": \"assets/clip.mp4\""));
+    assert!(!report.contains("\"source_path\": \"assets/user guide.pdf\""));
+
+    // Assets used only by a scheduled note aren't orphaned
+    fs::write(vault.join("assets/future.png"), b"png")?;
+    fs::write(
+        vault.join("later.md"),
+        "---\ntitle: Later\npublish_after: 2999-01-01\n---\n\n![[future.png]]\n",
+    )?;
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["verify", "--json"])
+        .output()?;
+    let report = String::from_utf8(output.stdout)?;
+    assert!(
+        report.contains("\"source_path\": \"assets/unused.pdf\""),
+        "{report}"
+    );
+    assert!(
+        !report.contains("\"source_path\": \"assets/future.png\""),
+        "{report}"
+    );
+    fs::remove_file(vault.join("later.md"))?;
+
+    // Adding the missing image re-renders the note on the next build
+    fs::write(vault.join("assets/chart.png"), b"png")?;
+    build(dir.path(), &[])?;
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains("<img src=\"/assets/chart.png\" alt=\"chart\""));
+    Ok(())
+}
+
+#[test]
+fn raster_images_get_responsive_variants() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str("images:\n  responsive: true\n");
+    fs::write(&config_path, config)?;
+    let vault = dir.path().join("vault");
+    fs::create_dir_all(vault.join("img"))?;
+    image::RgbImage::from_pixel(1200, 600, image::Rgb([40, 90, 160]))
+        .save(vault.join("img/wide.png"))?;
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\n![[wide.png]]\n\n![[wide.png|noresize]]\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    // Variant names carry a prefix of the source's content hash
+    let hash = |path: &Path| -> Result<String, Box<dyn std::error::Error>> {
+        Ok(monowiki_core::manifest::content_hash(&fs::read(path)?)[..8].to_string())
+    };
+    let h = hash(&vault.join("img/wide.png"))?;
+    let docs = dir.path().join("docs");
+    for variant in [
+        format!("img/wide-{h}-480w.png"),
+        format!("img/wide-{h}-960w.png"),
+        format!("img/wide-{h}-480w.webp"),
+    ] {
+        assert!(docs.join(&variant).exists(), "missing {variant}");
+    }
+    let (width, height) = image::image_dimensions(docs.join(format!("img/wide-{h}-480w.png")))?;
+    assert_eq!((width, height), (480, 240));
+
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains(&format!(
+        "<picture><source type=\"image/webp\" srcset=\"/img/wide-{h}-480w.webp 480w, \
+         /img/wide-{h}-960w.webp 960w, /img/wide-{h}-1200w.webp 1200w\">"
+    )));
+    assert!(alpha.contains(&format!(
+        "width=\"1200\" height=\"600\" srcset=\"/img/wide-{h}-480w.png 480w, \
+         /img/wide-{h}-960w.png 960w, /img/wide.png 1200w\"></picture>"
+    )));
+    assert!(alpha.contains("<img src=\"/img/wide.png\" alt=\"wide.png\" data-no-resize>"));
+
+    // Variants are cached by content hash outside the output and survive a
+    // forced rebuild
+    let cache = dir.path().join(".monowiki-cache/images");
+    let cached = fs::read_dir(&cache)?.count();
+    assert_eq!(cached, 5);
+    build(dir.path(), &["--force"])?;
+    assert_eq!(fs::read_dir(&cache)?.count(), cached);
+    assert!(!docs.join(".image_cache").exists());
+
+    // Variants of the previous version of an edited image are removed
+    image::RgbImage::from_pixel(600, 300, image::Rgb([40, 90, 160]))
+        .save(vault.join("img/wide.png"))?;
+    build(dir.path(), &[])?;
+    let shrunk = hash(&vault.join("img/wide.png"))?;
+    assert!(docs.join(format!("img/wide-{shrunk}-480w.png")).exists());
+    assert!(!docs.join(format!("img/wide-{shrunk}-960w.png")).exists());
+    assert!(!docs.join(format!("img/wide-{h}-480w.png")).exists());
+    assert!(!docs.join(format!("img/wide-{h}-960w.webp")).exists());
+    Ok(())
+}
+
+#[test]
+fn nota_references_preview_and_follow_renumbering() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let vault = dir.path().join("vault");
+    fs::write(
+        vault.join("beta.md"),
+        "---\ntitle: Beta\n---\n\n@Theorem[fermat]{Fermat}: No solutions for n > 2.\n",
+    )?;
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\nSee [[beta#fermat]].\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains(">Theorem 1</a>"));
+    let previews: Value = serde_json::from_slice(&fs::read(docs.join("previews.json"))?)?;
+    let preview = &previews["beta.html#fermat"];
+    assert_eq!(preview["title"], "Theorem 1 (Fermat)");
+    assert_eq!(preview["preview"], "No solutions for n > 2.");
+    assert_eq!(preview["url"], "/beta.html#fermat");
+
+    // Only beta changed, but alpha's reference text must follow the number
+    fs::write(
+        vault.join("beta.md"),
+        "---\ntitle: Beta\n---\n\n@Theorem{Warmup}: Easy.\n\n\
+         @Theorem[fermat]{Fermat}: No solutions for n > 2.\n",
+    )?;
+    build(dir.path(), &[])?;
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains(">Theorem 2</a>"));
+    Ok(())
+}
+
+#[test]
+fn unresolved_markdown_link_resolves_once_file_appears() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let vault = dir.path().join("vault");
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\nSee [the guide](docs/guide.md).\n",
+    )?;
+    build(dir.path(), &[])?;
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains("See the guide."), "{alpha}");
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    assert_eq!(
+        manifest["notes"]["alpha.md"]["links"],
+        serde_json::json!([])
+    );
+    assert_eq!(
+        manifest["notes"]["alpha.md"]["diagnostics"][0]["code"],
+        "link.unresolved"
+    );
+
+    // Only the target appeared, but alpha must now link to it
+    fs::create_dir_all(vault.join("docs"))?;
+    fs::write(
+        vault.join("docs/guide.md"),
+        "---\ntitle: Guide\n---\n\nHow to.\n",
+    )?;
+    build(dir.path(), &[])?;
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(
+        alpha.contains("<a href=\"/guide.html\">the guide</a>"),
+        "{alpha}"
+    );
+    Ok(())
+}
+
+#[test]
+fn unresolved_nota_reference_resolves_once_label_appears() -> Result<(), Box<dyn std::error::Error>>
+{
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let vault = dir.path().join("vault");
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\nBy @ref[beta#lem:new].\n",
+    )?;
+    build(dir.path(), &[])?;
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains("nota-ref-unresolved"));
+
+    // Only beta changed, but alpha must pick up the new label
+    fs::write(
+        vault.join("beta.md"),
+        "---\ntitle: Beta\n---\n\n@Lemma[lem:new]{}: Now it exists.\n",
+    )?;
+    build(dir.path(), &[])?;
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains("href=\"/beta.html#lem:new\""));
+    assert!(alpha.contains(">Lemma 1</a>"));
+    Ok(())
+}
+
+#[test]
+fn unresolved_equation_reference_resolves_once_label_appears(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let vault = dir.path().join("vault");
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\nBy \\eqref{beta#eq:later} and \\ref{beta#eq:later}.\n",
+    )?;
+    build(dir.path(), &[])?;
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains(">(??)</span>"));
+
+    fs::write(
+        vault.join("beta.md"),
+        "---\ntitle: Beta\n---\n\n$$\nx = 1 \\label{eq:later}\n$$\n",
+    )?;
+    build(dir.path(), &[])?;
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(!alpha.contains("nota-ref-unresolved"));
+    assert!(alpha.contains("href=\"/beta.html#eq:later\""));
+    assert!(alpha.contains(">(1)</a>"));
+    assert!(alpha.contains(">1</a>"));
+    Ok(())
+}
+
+#[test]
+fn mathml_mode_renders_math_at_build_time() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str("math:\n  render: mathml\n");
+    fs::write(&config_path, config)?;
+    let vault = dir.path().join("vault");
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\nLet $\\alpha \\in \\mathbb{R}$.\n",
+    )?;
+    fs::write(
+        vault.join("beta.md"),
+        "---\ntitle: Beta\n---\n\n$$\n\\newcommand{\\R}{\\mathbb{R}} \\R\n$$\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    // Pages whose math all converted don't load MathJax
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha.contains("<mi>α</mi><mo>∈</mo><mi>ℝ</mi>"));
+    assert!(!alpha.contains("MathJax-script"));
+    let beta = fs::read_to_string(docs.join("beta.html"))?;
+    assert!(beta.contains("MathJax-script"));
+
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    let diagnostic = &manifest["notes"]["beta.md"]["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "math.fallback");
+    assert_eq!(diagnostic["severity"], "info");
+    Ok(())
+}
+
+#[test]
+fn math_macros_reach_mathjax_and_are_checked() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str(
+        "math:\n  macros:\n    R: \\mathbb{R}\n    norm: '\\left\\lVert #1 \\right\\rVert'\n",
+    );
+    fs::write(&config_path, config)?;
+    fs::write(
+        dir.path().join("vault/beta.md"),
+        "---\ntitle: Beta\nmacros:\n  R: \\mathbf{R}\n---\n\nLet $\\norm$ be in $\\R$.\n",
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let alpha = fs::read_to_string(docs.join("alpha.html"))?;
+    assert!(alpha
+        .contains(r#"macros: {"R":"\\mathbb{R}","norm":["\\left\\lVert #1 \\right\\rVert",1]},"#));
+    let beta = fs::read_to_string(docs.join("beta.html"))?;
+    assert!(beta.contains(r#"{"R":"\\mathbf{R}","#));
+
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    let diagnostic = &manifest["notes"]["beta.md"]["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "math.macro_arity");
+    assert_eq!(
+        diagnostic["message"],
+        "\\norm takes 1 argument but is given 0"
+    );
+    Ok(())
+}
+
+#[test]
+fn citation_styles_come_from_config_notes_and_csl_files() -> Result<(), Box<dyn std::error::Error>>
+{
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let config_path = dir.path().join("monowiki.yml");
+    let mut config = fs::read_to_string(&config_path)?;
+    config.push_str("bibliography: [vault/refs.bib]\ncitation_style: chicago-author-date\n");
+    fs::write(&config_path, config)?;
+    let vault = dir.path().join("vault");
+    fs::write(
+        vault.join("refs.bib"),
+        "@book{pierce2002,\n  title = {Types and Programming Languages},\n  \
+         author = {Pierce, Benjamin C.},\n  publisher = {MIT Press},\n  date = {2002}\n}\n",
+    )?;
+    fs::write(
+        vault.join("titles.csl"),
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<style xmlns="http://purl.org/net/xbiblio/csl" class="in-text" version="1.0">
+  <info><title>Titles</title><id>titles</id><updated>2024-01-01T00:00:00+00:00</updated></info>
+  <citation><layout prefix="{" suffix="}"><text variable="title"/></layout></citation>
+  <bibliography><layout><text variable="publisher"/></layout></bibliography>
+</style>
+"#,
+    )?;
+    let note = |style: &str| format!("---\ntitle: Note\n{style}---\n\nAs shown [@pierce2002].\n");
+    fs::write(vault.join("alpha.md"), note(""))?;
+    fs::write(vault.join("beta.md"), note("citation_style: ieee\n"))?;
+    fs::write(
+        vault.join("custom.md"),
+        note("citation_style: vault/titles.csl\n"),
+    )?;
+    fs::write(
+        vault.join("unknown.md"),
+        note("citation_style: no-such-style\n"),
+    )?;
+    fs::write(
+        vault.join("unknown2.md"),
+        note("citation_style: no-such-style\n"),
+    )?;
+    build(dir.path(), &[])?;
+
+    let docs = dir.path().join("docs");
+    let page = |slug: &str| fs::read_to_string(docs.join(format!("{slug}.html")));
+    assert!(page("alpha")?.contains(">(Pierce 2002)</a>"));
+    assert!(page("beta")?.contains(">[1]</a>"));
+    let custom = page("custom")?;
+    assert!(custom.contains(">{Types and Programming Languages}</a>"));
+    assert!(custom.contains("<li id=\"ref-1\">MIT Press"));
+    // Unknown styles fall back to the default numeric style
+    assert!(page("unknown")?.contains(">[1]</a>"));
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["verify", "--json"])
+        .output()?;
+    let report = String::from_utf8(output.stdout)?;
+    // Reported for every note that picked the style
+    assert_eq!(
+        report
+            .matches("\"code\": \"citation.style_invalid\"")
+            .count(),
+        2,
+        "{report}"
+    );
+    assert!(report.contains("\"context\": \"no-such-style\""));
+    assert!(report.contains("\"note_slug\": \"unknown\""));
+    assert!(report.contains("\"note_slug\": \"unknown2\""));
+
+    // Editing the style file re-renders the notes using it
+    let csl = fs::read_to_string(vault.join("titles.csl"))?;
+    fs::write(
+        vault.join("titles.csl"),
+        csl.replace("prefix=\"{\"", "prefix=\"&lt;\""),
+    )?;
+    build(dir.path(), &[])?;
+    assert!(page("custom")?.contains(">&lt;Types and Programming Languages}</a>"));
+    // Notes reused from the last build keep reporting their broken style
+    let manifest: Value = serde_json::from_slice(&fs::read(docs.join(".build_manifest.json"))?)?;
+    assert_eq!(
+        manifest["notes"]["unknown.md"]["diagnostics"][0]["code"],
+        "citation.style_invalid"
+    );
+    Ok(())
+}
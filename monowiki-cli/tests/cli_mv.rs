@@ -0,0 +1,149 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_site(root: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(root.join("vault/essays"))?;
+    fs::create_dir_all(root.join("vault/comments"))?;
+    fs::write(
+        root.join("monowiki.yml"),
+        r#"
+site:
+  title: "Test"
+  author: "Tester"
+  description: "Desc"
+  url: "https://example.com"
+paths:
+  vault: "vault"
+  output: "docs"
+base_url: "/"
+"#,
+    )?;
+    fs::write(
+        root.join("vault/essays/rust-safety.md"),
+        "---\ntitle: Rust Safety\naliases:\n  - borrowck\n---\n\n## Memory Model\n\nBody.\n",
+    )?;
+    fs::write(
+        root.join("vault/alpha.md"),
+        "---\ntitle: Alpha\n---\n\nSee [[Rust Safety]], [[borrowck|the checker]] and \
+         [[rust-safety#Memory Model]].\n\n![[rust-safety#Memory Model]]\n",
+    )?;
+    fs::write(
+        root.join("vault/comments/rust-safety-1.md"),
+        "---\ntitle: Comment on rust-safety\ntype: comment\ntarget_slug: rust-safety\nstatus: open\n---\n\nNice.\n",
+    )?;
+    Ok(())
+}
+
+fn monowiki(root: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("monowiki")?
+        .current_dir(root)
+        .args(args)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn dry_run_prints_a_diff_and_writes_nothing() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let before = fs::read_to_string(dir.path().join("vault/alpha.md"))?;
+
+    let diff = monowiki(dir.path(), &["mv", "rust-safety", "ownership", "--dry-run"])?;
+    assert!(diff.contains("rename essays/rust-safety.md -> essays/ownership.md"));
+    assert!(diff.contains("--- a/alpha.md\n+++ b/alpha.md"));
+    assert!(diff.contains("+See [[ownership|Rust Safety]]"));
+    assert!(diff.contains("+target_slug: ownership"));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("vault/alpha.md"))?,
+        before
+    );
+    assert!(dir.path().join("vault/essays/rust-safety.md").exists());
+    Ok(())
+}
+
+#[test]
+fn mv_rewrites_links_comments_and_keeps_old_slug() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+
+    monowiki(dir.path(), &["mv", "essays/rust-safety.md", "ownership"])?;
+
+    let vault = dir.path().join("vault");
+    assert!(!vault.join("essays/rust-safety.md").exists());
+    let moved = fs::read_to_string(vault.join("essays/ownership.md"))?;
+    assert!(moved.contains("aliases:\n  - borrowck\n  - rust-safety\n"));
+
+    let alpha = fs::read_to_string(vault.join("alpha.md"))?;
+    assert!(alpha.contains(
+        "See [[ownership|Rust Safety]], [[ownership|the checker]] and \
+         [[ownership#Memory Model|rust-safety#Memory Model]]."
+    ));
+    assert!(alpha.contains("![[ownership#Memory Model]]"));
+
+    let comment = fs::read_to_string(vault.join("comments/rust-safety-1.md"))?;
+    assert!(comment.contains("target_slug: ownership\n"));
+
+    // The renamed vault builds without unresolved links
+    let report = monowiki(dir.path(), &["verify", "--json"])?;
+    assert!(!report.contains("link.unresolved"), "{report}");
+    Ok(())
+}
+
+#[test]
+fn mv_refuses_to_take_an_existing_slug() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+
+    #[allow(deprecated)]
+    Command::cargo_bin("monowiki")?
+        .current_dir(dir.path())
+        .args(["mv", "rust-safety", "alpha"])
+        .assert()
+        .failure();
+    assert!(dir.path().join("vault/essays/rust-safety.md").exists());
+    Ok(())
+}
+
+#[test]
+fn mv_to_another_directory_rewrites_markdown_links() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    write_site(dir.path())?;
+    let vault = dir.path().join("vault");
+    fs::create_dir_all(vault.join("essays/img"))?;
+    fs::write(vault.join("essays/img/plot.png"), b"png")?;
+    fs::write(
+        vault.join("essays/rust-safety.md"),
+        "---\ntitle: Rust Safety\n---\n\n![Plot](img/plot.png) and [Alpha](../alpha.md).\n",
+    )?;
+    fs::write(
+        vault.join("alpha.md"),
+        "---\ntitle: Alpha\n---\n\nSee [safety](essays/rust-safety.md#Memory) \
+         but not `[code](essays/rust-safety.md)`.\n",
+    )?;
+
+    monowiki(
+        dir.path(),
+        &["mv", "essays/rust-safety.md", "archive/rust-safety.md"],
+    )?;
+
+    let moved = fs::read_to_string(vault.join("archive/rust-safety.md"))?;
+    assert!(moved.contains("![Plot](../essays/img/plot.png) and [Alpha](../alpha.md)."));
+    let alpha = fs::read_to_string(vault.join("alpha.md"))?;
+    assert!(alpha.contains(
+        "See [safety](archive/rust-safety.md#Memory) but not `[code](essays/rust-safety.md)`."
+    ));
+
+    let report = monowiki(dir.path(), &["verify", "--json"])?;
+    assert!(!report.contains("link.unresolved"), "{report}");
+    assert!(!report.contains("asset.missing"), "{report}");
+    Ok(())
+}
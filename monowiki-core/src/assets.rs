@@ -0,0 +1,239 @@
+//! Vault assets (images, PDFs, media) and resolution of references to them.
+
+use crate::config::Config;
+use percent_encoding::percent_decode_str;
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// File extensions copied from the vault into the output as assets
+#[rustfmt::skip]
+pub const ASSET_EXTENSIONS: &[&str] = &[
+    // Images
+    "png", "jpg", "jpeg", "gif", "svg", "webp", "ico", "bmp", "tiff", "tif",
+    // Documents
+    "pdf",
+    // Audio/Video
+    "mp3", "mp4", "wav", "ogg", "webm", "m4a",
+    // Data files
+    "json", "csv", "xml",
+    // Archives
+    "zip", "tar", "gz",
+    // Other
+    "woff", "woff2", "ttf", "otf", "eot",
+];
+
+/// Extensions rendered as `<img>` when embedded
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "svg", "webp", "ico", "bmp", "tiff", "tif",
+];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "m4a"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm"];
+
+/// How an embedded asset is presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Image,
+    Audio,
+    Video,
+    /// Anything else is linked to
+    File,
+}
+
+/// Lowercased extension of `path`, if any
+fn extension(path: &str) -> Option<String> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+}
+
+/// Whether `path` names an asset (by extension)
+pub fn is_asset_path(path: &str) -> bool {
+    extension(path).is_some_and(|ext| ASSET_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// How an asset at `path` should be embedded
+pub fn asset_kind(path: &str) -> AssetKind {
+    match extension(path).as_deref() {
+        Some(ext) if IMAGE_EXTENSIONS.contains(&ext) => AssetKind::Image,
+        Some(ext) if AUDIO_EXTENSIONS.contains(&ext) => AssetKind::Audio,
+        Some(ext) if VIDEO_EXTENSIONS.contains(&ext) => AssetKind::Video,
+        _ => AssetKind::File,
+    }
+}
+
+/// Whether a link destination points into the vault rather than elsewhere
+pub fn is_local_reference(dest: &str) -> bool {
+    !(dest.is_empty()
+        || dest.starts_with('#')
+        || dest.starts_with("//")
+        || dest.contains("://")
+        || dest.starts_with("mailto:")
+        || dest.starts_with("data:"))
+}
+
+/// Every asset file in the vault, by vault-relative path
+#[derive(Debug, Clone, Default)]
+pub struct AssetIndex {
+    paths: BTreeSet<String>,
+    /// Vault-relative folder searched first for attachments found by name
+    attachments_dir: Option<String>,
+}
+
+impl AssetIndex {
+    pub fn new(paths: impl IntoIterator<Item = String>, attachments_dir: Option<&str>) -> Self {
+        Self {
+            paths: paths.into_iter().collect(),
+            attachments_dir: attachments_dir
+                .map(|dir| dir.trim_matches('/').to_string())
+                .filter(|dir| !dir.is_empty()),
+        }
+    }
+
+    /// Scan the vault for asset files
+    pub fn scan(config: &Config) -> Self {
+        let vault_dir = config.vault_dir();
+        let paths = WalkDir::new(&vault_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(&vault_dir).ok()?;
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                is_asset_path(&relative).then_some(relative)
+            });
+        let attachments = config
+            .paths
+            .attachments
+            .as_deref()
+            .map(|dir| dir.to_string_lossy().to_string());
+        Self::new(paths, attachments.as_deref())
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+
+    /// Resolve a reference made from the note at `from` (vault-relative).
+    ///
+    /// Tries the path relative to the note, then relative to the vault root,
+    /// then (like Obsidian) looks the file name up in the attachments folder
+    /// and anywhere in the vault, preferring the shortest path.
+    pub fn resolve(&self, from: Option<&str>, reference: &str) -> Option<String> {
+        let reference = reference.split(['#', '?']).next().unwrap_or_default();
+        let reference = percent_decode_str(reference).decode_utf8_lossy();
+        let reference = reference.as_ref();
+        if reference.is_empty() {
+            return None;
+        }
+
+        let relative = from
+            .filter(|_| !reference.starts_with('/'))
+            .and_then(|from| join(Path::new(from).parent(), reference));
+        let rooted = join(None, reference);
+        if let Some(found) = [relative, rooted]
+            .into_iter()
+            .flatten()
+            .find(|path| self.paths.contains(path))
+        {
+            return Some(found);
+        }
+
+        let name = reference.trim_start_matches('/');
+        if let Some(dir) = &self.attachments_dir {
+            let path = format!("{dir}/{name}");
+            if self.paths.contains(&path) {
+                return Some(path);
+            }
+        }
+        let suffix = format!("/{name}");
+        self.paths
+            .iter()
+            .filter(|path| path.ends_with(&suffix))
+            .min_by_key(|path| (path.matches('/').count(), path.as_str()))
+            .cloned()
+    }
+
+    /// Assets missing from `used` (see `SiteBuilder::referenced_assets`)
+    pub fn orphaned(&self, used: &HashSet<String>) -> Vec<&str> {
+        self.paths().filter(|path| !used.contains(*path)).collect()
+    }
+}
+
+/// Normalize `reference` joined onto `dir`, or `None` if it climbs out of
+/// the vault
+fn join(dir: Option<&Path>, reference: &str) -> Option<String> {
+    let mut segments: Vec<String> = dir
+        .map(|dir| {
+            dir.iter()
+                .map(|s| s.to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    for segment in reference.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment.to_string()),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> AssetIndex {
+        AssetIndex::new(
+            [
+                "essays/img/plot.png".to_string(),
+                "attachments/diagram.png".to_string(),
+                "old/diagram.png".to_string(),
+                "deep/nested/photo.jpg".to_string(),
+                "files/paper.pdf".to_string(),
+            ],
+            Some("attachments/"),
+        )
+    }
+
+    #[test]
+    fn references_resolve_relative_rooted_and_by_name() {
+        let index = index();
+        let from = Some("essays/intro.md");
+        assert_eq!(
+            index.resolve(from, "img/plot.png").as_deref(),
+            Some("essays/img/plot.png")
+        );
+        assert_eq!(
+            index.resolve(from, "../files/paper.pdf#page=2").as_deref(),
+            Some("files/paper.pdf")
+        );
+        assert_eq!(
+            index.resolve(from, "/files/paper.pdf").as_deref(),
+            Some("files/paper.pdf")
+        );
+        // By name: the attachments folder wins, then the shortest path
+        assert_eq!(
+            index.resolve(from, "diagram.png").as_deref(),
+            Some("attachments/diagram.png")
+        );
+        assert_eq!(
+            index.resolve(None, "photo.jpg").as_deref(),
+            Some("deep/nested/photo.jpg")
+        );
+        assert_eq!(index.resolve(from, "missing.png"), None);
+        assert_eq!(index.resolve(from, "../../escape.png"), None);
+    }
+
+    #[test]
+    fn kinds_follow_extensions() {
+        assert_eq!(asset_kind("a/B.PNG"), AssetKind::Image);
+        assert_eq!(asset_kind("clip.webm"), AssetKind::Video);
+        assert_eq!(asset_kind("paper.pdf"), AssetKind::File);
+        assert!(is_asset_path("x.svg") && !is_asset_path("note.md"));
+        assert!(!is_local_reference("https://example.com/a.png"));
+    }
+}
@@ -1,19 +1,25 @@
-//! Bibliography loading and lookup for citation support.
+//! Bibliography and citation style loading for citation support.
 
+use hayagriva::archive::ArchivedStyle;
+use hayagriva::citationberg::{IndependentStyle, Style};
 use hayagriva::{io::from_biblatex_str, Entry, Library};
 use std::{
     collections::HashMap,
     fs, mem,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tracing::warn;
 
-use crate::models::{Diagnostic, DiagnosticSeverity};
+use crate::models::{Diagnostic, DiagnosticSeverity, Note};
 
 /// Cached bibliography loader to avoid re-reading the same `.bib` files.
 #[derive(Debug, Default)]
 pub struct BibliographyStore {
     cache: HashMap<PathBuf, Library>,
+    styles: HashMap<String, Arc<CitationStyle>>,
+    /// Why each style that failed to load did
+    style_errors: HashMap<String, String>,
     diagnostics: Vec<Diagnostic>,
 }
 
@@ -21,6 +27,8 @@ impl BibliographyStore {
     pub fn new() -> Self {
         Self {
             cache: HashMap::new(),
+            styles: HashMap::new(),
+            style_errors: HashMap::new(),
             diagnostics: Vec::new(),
         }
     }
@@ -75,6 +83,7 @@ impl BibliographyStore {
                         source_path: Some(path.to_string_lossy().to_string()),
                         context: None,
                         anchor: None,
+                        span: None,
                     });
                     self.cache.insert(path.to_path_buf(), Library::new());
                 }
@@ -89,12 +98,81 @@ impl BibliographyStore {
                     source_path: Some(path.to_string_lossy().to_string()),
                     context: None,
                     anchor: None,
+                    span: None,
                 });
                 self.cache.insert(path.to_path_buf(), Library::new());
             }
         }
     }
 
+    /// Load the citation style `spec` names: a bundled style (`apa`, `ieee`,
+    /// ...) or the path of a `.csl` file. `None` and styles that fail to load
+    /// give the default style; a failure is reported the first time.
+    pub fn style(&mut self, spec: Option<&str>) -> Arc<CitationStyle> {
+        let spec = spec.unwrap_or(DEFAULT_CITATION_STYLE);
+        let first_use = !self.styles.contains_key(spec);
+        let style = self.load_style(spec);
+        if first_use {
+            let diagnostic = self.style_diagnostic(spec, None);
+            self.diagnostics.extend(diagnostic);
+        }
+        style
+    }
+
+    /// Load the style a note picked with `citation_style` in its frontmatter,
+    /// along with a diagnostic for that note if the style failed to load.
+    pub fn note_style(
+        &mut self,
+        spec: &str,
+        note: &Note,
+    ) -> (Arc<CitationStyle>, Option<Diagnostic>) {
+        let style = self.load_style(spec);
+        (style, self.style_diagnostic(spec, Some(note)))
+    }
+
+    fn load_style(&mut self, spec: &str) -> Arc<CitationStyle> {
+        if let Some(style) = self.styles.get(spec) {
+            return style.clone();
+        }
+
+        let loaded = if spec.ends_with(".csl") {
+            fs::read_to_string(spec)
+                .map_err(|err| err.to_string())
+                .and_then(|xml| CitationStyle::from_xml(&xml))
+        } else {
+            CitationStyle::by_name(spec).ok_or_else(|| "no bundled style by that name".to_string())
+        };
+        let style = match loaded {
+            Ok(style) => Arc::new(style),
+            Err(err) => {
+                warn!("Failed to load citation style '{}': {}", spec, err);
+                self.style_errors.insert(spec.to_string(), err);
+                self.load_style(DEFAULT_CITATION_STYLE)
+            }
+        };
+        self.styles.insert(spec.to_string(), style.clone());
+        style
+    }
+
+    /// `citation.style_invalid` for a style that failed to load, reported
+    /// against `note` when the note picked it
+    fn style_diagnostic(&self, spec: &str, note: Option<&Note>) -> Option<Diagnostic> {
+        let err = self.style_errors.get(spec)?;
+        Some(Diagnostic {
+            code: "citation.style_invalid".to_string(),
+            message: format!("Failed to load citation style '{}': {}", spec, err),
+            severity: DiagnosticSeverity::Warning,
+            note_slug: note.map(|note| note.slug.clone()),
+            source_path: match note {
+                Some(note) => note.source_path.clone(),
+                None => spec.ends_with(".csl").then(|| spec.to_string()),
+            },
+            context: Some(spec.to_string()),
+            anchor: None,
+            span: None,
+        })
+    }
+
     /// Take accumulated diagnostics (clearing the internal buffer).
     pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
         mem::take(&mut self.diagnostics)
@@ -118,3 +196,49 @@ impl Bibliography {
         self.entries.is_empty()
     }
 }
+
+/// Style used when neither the config nor the note picks one
+pub const DEFAULT_CITATION_STYLE: &str = "ieee";
+
+/// A CSL style that formats citation labels and reference lists.
+#[derive(Debug, Clone)]
+pub struct CitationStyle {
+    csl: IndependentStyle,
+}
+
+impl CitationStyle {
+    /// Lookup a style bundled with hayagriva by name.
+    pub fn by_name(name: &str) -> Option<Self> {
+        ArchivedStyle::by_name(name).and_then(|archived| Self::from_style(archived.get()))
+    }
+
+    /// Parse a `.csl` file; dependent styles must name a bundled parent.
+    pub fn from_xml(xml: &str) -> Result<Self, String> {
+        let style = Style::from_xml(xml).map_err(|err| err.to_string())?;
+        Self::from_style(style).ok_or_else(|| "unknown parent style".to_string())
+    }
+
+    fn from_style(style: Style) -> Option<Self> {
+        let csl = match style {
+            Style::Independent(csl) => csl,
+            Style::Dependent(dependent) => {
+                match ArchivedStyle::by_id(&dependent.parent_link.href)?.get() {
+                    Style::Independent(csl) => csl,
+                    Style::Dependent(_) => return None,
+                }
+            }
+        };
+        Some(Self { csl })
+    }
+
+    /// The parsed CSL style.
+    pub fn csl(&self) -> &IndependentStyle {
+        &self.csl
+    }
+}
+
+impl Default for CitationStyle {
+    fn default() -> Self {
+        Self::by_name(DEFAULT_CITATION_STYLE).expect("default citation style is bundled")
+    }
+}
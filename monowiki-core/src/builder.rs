@@ -1,18 +1,29 @@
 //! Site building logic - orchestrates parsing, rendering, and output.
 
 use crate::{
-    bibliography::BibliographyStore,
+    assets::AssetIndex,
+    bibliography::{Bibliography, BibliographyStore, CitationStyle},
     config::Config,
-    frontmatter::parse_frontmatter,
-    markdown::{citations::CitationContext, MarkdownProcessor},
+    dates::{parse_date, ParsedDate},
+    frontmatter::{field_position, parse_frontmatter, strip_frontmatter, FrontmatterError},
+    manifest::{content_hash, file_hash, BuildManifest, NoteManifest},
+    markdown::{
+        block_ids::block_anchor,
+        citations::CitationContext,
+        transclusion::{EmbedContext, EmbedSource},
+        wikilinks::{markdown_link_target, resolve_vault_path},
+        BlockIndex, LabelIndex, MarkdownProcessor, NotaLabel, RenderContext,
+    },
     models::*,
     search::section_digests_from_html,
-    slug::slugify,
+    slug::{slugify, tag_slug},
 };
+use rayon::prelude::*;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 use walkdir::WalkDir;
 
@@ -26,24 +37,114 @@ pub enum BuildError {
 
     #[error("Duplicate slug: {0}")]
     DuplicateSlug(String),
+
+    #[error("Output path collision: {0}")]
+    OutputCollision(String),
+}
+
+/// Rendered state from a previous build that can be reused.
+#[derive(Clone, Copy)]
+pub struct PreviousBuild<'a> {
+    pub index: &'a SiteIndex,
+    pub manifest: &'a BuildManifest,
+}
+
+/// Result of an incremental build.
+pub struct IncrementalBuild {
+    pub index: SiteIndex,
+    pub manifest: BuildManifest,
+    /// Slugs of notes whose markdown was rendered (not reused) in this build
+    pub rendered: Vec<String>,
+}
+
+/// Per-note inputs for the parallel render pass.
+struct RenderJob<'a> {
+    bib_hashes: BTreeMap<String, String>,
+    /// Previously rendered note and its manifest entry when it can be reused as-is
+    cached: Option<(&'a Note, &'a NoteManifest)>,
+    bibliography: Option<Bibliography>,
+    citation_style: Option<Arc<CitationStyle>>,
+    /// Failure to load the citation style the note picked
+    style_diagnostic: Option<Diagnostic>,
+}
+
+/// Rendered note output that is not stored on the note itself.
+struct RenderOutput {
+    diagnostics: Vec<Diagnostic>,
+    /// Embed dependencies for the manifest (slug map key -> source hash)
+    embeds: BTreeMap<String, String>,
 }
 
 /// Main site builder
 pub struct SiteBuilder {
     config: Config,
     processor: MarkdownProcessor,
+    /// Moment `publish_after` / `expires` are checked against
+    as_of: ParsedDate,
 }
 
 impl SiteBuilder {
     pub fn new(config: Config) -> Self {
+        let processor = MarkdownProcessor::new()
+            .with_toc_depth(config.toc.min_depth, config.toc.max_depth)
+            .with_nota(config.nota.clone())
+            .with_math(config.math.clone());
         Self {
             config,
-            processor: MarkdownProcessor::new(),
+            processor,
+            as_of: ParsedDate::now(),
         }
     }
 
+    /// Build the site as it would look at `as_of` (defaults to now)
+    pub fn as_of(mut self, as_of: ParsedDate) -> Self {
+        self.as_of = as_of;
+        self
+    }
+
     /// Build the entire site
     pub fn build(&self) -> Result<SiteIndex, BuildError> {
+        self.build_incremental(None).map(|build| build.index)
+    }
+
+    /// Every note in the vault with its frontmatter parsed but nothing
+    /// rendered, including scheduled and expired notes. Notes whose
+    /// frontmatter fails to parse get filename-derived defaults.
+    pub fn scan_notes(&self) -> Result<Vec<Note>, BuildError> {
+        let mut notes = Vec::new();
+        for file_path in self.discover_markdown_files()? {
+            let content = fs::read_to_string(&file_path)?;
+            let frontmatter = parse_frontmatter(&content)
+                .map(|(frontmatter, _)| frontmatter)
+                .unwrap_or_default();
+            notes.push(self.note_from_frontmatter(&file_path, frontmatter));
+        }
+        Ok(notes)
+    }
+
+    /// Vault-relative paths of the assets referenced by any note, including
+    /// scheduled and expired notes that are left out of the build.
+    pub fn referenced_assets(&self, assets: &AssetIndex) -> Result<HashSet<String>, BuildError> {
+        let mut used = HashSet::new();
+        for file_path in self.discover_markdown_files()? {
+            let content = fs::read_to_string(&file_path)?;
+            let source_path = self.source_rel_path(&file_path);
+            used.extend(
+                self.processor
+                    .asset_refs(strip_frontmatter(&content))
+                    .iter()
+                    .filter_map(|reference| assets.resolve(source_path.as_deref(), reference)),
+            );
+        }
+        Ok(used)
+    }
+
+    /// Build the site, reusing rendered notes from a previous build where
+    /// neither the source nor any recorded dependency has changed.
+    pub fn build_incremental(
+        &self,
+        previous: Option<PreviousBuild<'_>>,
+    ) -> Result<IncrementalBuild, BuildError> {
         // Create output directory
         fs::create_dir_all(self.config.output_dir())?;
 
@@ -52,89 +153,430 @@ impl SiteBuilder {
 
         tracing::info!("Found {} markdown files", markdown_files.len());
 
+        let config_hash = self.config_hash();
+        let previous = previous.filter(|prev| prev.manifest.is_compatible(&config_hash));
+        let previous_notes: HashMap<&str, &Note> = previous
+            .map(|prev| {
+                prev.index
+                    .notes
+                    .iter()
+                    .filter_map(|n| n.source_path.as_deref().map(|p| (p, n)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         let mut bibliography_store = BibliographyStore::new();
         bibliography_store.preload_paths(&self.config.bibliography_paths());
+        // Report a broken configured style even when every note is cached
+        bibliography_store.style(self.config.citation_style().as_deref());
 
         // Parse all notes (first pass - without link resolution)
         let mut notes = Vec::new();
+        let mut sources: Vec<(String, String)> = Vec::new(); // (content, source hash)
         let mut slug_map: HashMap<String, String> = HashMap::new();
         let base_url = self.config.normalized_base_url();
         let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        // Slugs and alias slugs of notes left out because of publish_after / expires
+        let mut unpublished: HashSet<String> = HashSet::new();
+        // Vault-relative source path -> slug, for plain `[text](note.md)` links
+        let mut note_paths: HashMap<String, String> = HashMap::new();
 
         for file_path in &markdown_files {
-            match self.parse_note(file_path) {
-                Ok(note) => {
-                    // Check for duplicate slugs
-                    if slug_map.contains_key(&note.slug) {
-                        tracing::warn!("Duplicate slug: {}", note.slug);
-                        return Err(BuildError::DuplicateSlug(note.slug.clone()));
-                    }
-
-                    let href = format!("{}{}", base_url, note.output_rel_path());
-                    slug_map.insert(note.slug.clone(), href.clone());
-                    // Aliases also resolve to the same target
-                    for alias in &note.aliases {
-                        let alias_slug = slugify(alias);
-                        if let Some(existing) = slug_map.get(&alias_slug) {
-                            // Only flag if the alias would point somewhere else
-                            if existing != &href {
-                                diagnostics.push(Diagnostic {
-                                    code: "alias.duplicate".to_string(),
-                                    message: format!(
-                                        "Alias '{}' on '{}' conflicts with an existing target",
-                                        alias, note.slug
-                                    ),
-                                    severity: DiagnosticSeverity::Warning,
-                                    note_slug: Some(note.slug.clone()),
-                                    source_path: note.source_path.clone(),
-                                    context: Some(alias_slug.clone()),
-                                    anchor: None,
-                                });
-                            }
-                        } else {
-                            slug_map.insert(alias_slug, href.clone());
+            let content = match fs::read_to_string(file_path) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::error!("Failed to read {:?}: {}", file_path, e);
+                    continue;
+                }
+            };
+            let source_hash = content_hash(content.as_bytes());
+
+            let note = match parse_frontmatter(&content) {
+                Ok((frontmatter, _body)) => {
+                    let note = self.note_from_frontmatter(file_path, frontmatter);
+                    diagnostics.extend(self.unknown_type_diagnostic(&note, &content));
+                    diagnostics.extend(date_diagnostics(&note, &content));
+                    if let Some(diag) = self.schedule_diagnostic(&note) {
+                        diagnostics.push(diag);
+                        unpublished.insert(note.slug.clone());
+                        unpublished.extend(note.aliases.iter().map(|a| slugify(a)));
+                        if let Some(rel) = &note.source_path {
+                            note_paths.insert(rel.clone(), note.slug.clone());
                         }
+                        continue;
                     }
-                    notes.push(note);
+                    note
                 }
                 Err(e) => {
+                    // Keep the note with filename-derived defaults so links to it still resolve
                     tracing::error!("Failed to parse {:?}: {}", file_path, e);
-                    // Continue with other files
+                    let note = self.note_from_frontmatter(file_path, Frontmatter::default());
+                    diagnostics.push(frontmatter_diagnostic(&e, &content, &note));
+                    note
                 }
+            };
+            notes.push(note);
+            sources.push((content, source_hash));
+        }
+
+        // Disambiguate duplicate slugs and colliding output paths
+        diagnostics.extend(resolve_collisions(
+            &mut notes,
+            self.config.recover_collisions,
+        )?);
+        // Frontmatter diagnostics were reported under the slug a note had
+        // before any renaming
+        let final_slugs: HashMap<&str, &str> = notes
+            .iter()
+            .filter_map(|note| Some((note.source_path.as_deref()?, note.slug.as_str())))
+            .collect();
+        for diag in &mut diagnostics {
+            if let Some(slug) = diag.source_path.as_deref().and_then(|p| final_slugs.get(p)) {
+                diag.note_slug = Some(slug.to_string());
             }
         }
 
-        // Second pass - render markdown with link resolution
-        for (idx, note) in notes.iter_mut().enumerate() {
-            let markdown = fs::read_to_string(&markdown_files[idx])?;
-            let (frontmatter, body) = parse_frontmatter(&markdown)?;
+        // Slug map key -> note index, for resolving embeds
+        let mut embed_index: HashMap<String, usize> = HashMap::new();
+        for (idx, note) in notes.iter().enumerate() {
+            if let Some(rel) = &note.source_path {
+                note_paths.insert(rel.clone(), note.slug.clone());
+            }
+            let href = format!("{}{}", base_url, note.output_rel_path());
+            slug_map.insert(note.slug.clone(), href.clone());
+            embed_index.insert(note.slug.clone(), idx);
+            // Aliases also resolve to the same target
+            for alias in &note.aliases {
+                let alias_slug = slugify(alias);
+                if let Some(existing) = slug_map.get(&alias_slug) {
+                    // Only flag if the alias would point somewhere else
+                    if existing != &href {
+                        diagnostics.push(Diagnostic {
+                            code: "alias.duplicate".to_string(),
+                            message: format!(
+                                "Alias '{}' on '{}' conflicts with an existing target",
+                                alias, note.slug
+                            ),
+                            severity: DiagnosticSeverity::Warning,
+                            note_slug: Some(note.slug.clone()),
+                            source_path: note.source_path.clone(),
+                            context: Some(alias_slug.clone()),
+                            anchor: None,
+                            span: None,
+                        });
+                    }
+                } else {
+                    embed_index.entry(alias_slug.clone()).or_insert(idx);
+                    slug_map.insert(alias_slug, href.clone());
+                }
+            }
+        }
 
-            let bibliography_paths = self.bibliography_paths(&frontmatter);
-            let bibliography = bibliography_store.collect(&bibliography_paths);
-            let citation_ctx = if bibliography.is_empty() {
-                None
-            } else {
-                Some(CitationContext {
-                    bibliography: &bibliography,
+        // Note bodies are needed up front so notes can embed each other
+        let embed_sources: Vec<EmbedSource> = notes
+            .iter()
+            .zip(&sources)
+            .map(|(note, (content, source_hash))| {
+                let body = strip_frontmatter(content);
+                EmbedSource {
+                    slug: note.slug.clone(),
+                    title: note.title.clone(),
+                    source_path: note.source_path.clone(),
+                    body: body.to_string(),
+                    line_offset: content[..content.len() - body.len()].matches('\n').count(),
+                    source_hash: source_hash.clone(),
+                }
+            })
+            .collect();
+        let embed_hashes: HashMap<String, String> = embed_index
+            .iter()
+            .map(|(key, idx)| (key.clone(), sources[*idx].1.clone()))
+            .collect();
+
+        // Block ids are also needed up front to check `[[note#^block-id]]` links
+        let note_block_ids: Vec<Vec<String>> = embed_sources
+            .par_iter()
+            .map(|source| self.processor.block_ids(&source.body))
+            .collect();
+        for (note, block_ids) in notes.iter_mut().zip(&note_block_ids) {
+            note.block_ids = block_ids.clone();
+        }
+        let block_index: BlockIndex = embed_index
+            .iter()
+            .map(|(key, idx)| (key.clone(), note_block_ids[*idx].iter().cloned().collect()))
+            .collect();
+
+        // Nota block labels and numbers, for `[[note#label]]` references
+        let note_labels: Vec<Vec<NotaLabel>> = embed_sources
+            .par_iter()
+            .map(|source| self.processor.nota_labels(&source.body))
+            .collect();
+        for (note, labels) in notes.iter_mut().zip(&note_labels) {
+            note.nota_labels = labels.clone();
+        }
+        let label_index: LabelIndex = embed_index
+            .iter()
+            .map(|(key, idx)| (key.clone(), note_labels[*idx].clone()))
+            .collect();
+        let changed_blocks = previous
+            .map(|prev| changed_block_keys(prev.manifest, &block_index, &label_index))
+            .unwrap_or_default();
+
+        // Asset references, resolved now so notes re-render when an asset
+        // they refer to appears or disappears ("" marks a missing asset)
+        let asset_index = AssetIndex::scan(&self.config);
+        let note_assets: Vec<BTreeMap<String, String>> = embed_sources
+            .par_iter()
+            .map(|source| {
+                self.processor
+                    .asset_refs(&source.body)
+                    .into_iter()
+                    .map(|reference| {
+                        let resolved = asset_index
+                            .resolve(source.source_path.as_deref(), &reference)
+                            .unwrap_or_default();
+                        (reference, resolved)
+                    })
+                    .collect()
+            })
+            .collect();
+        // Likewise for Markdown links to `.md` files, so a link to a missing
+        // file is rendered again once the file appears ("" marks a missing note)
+        let note_links: Vec<BTreeMap<String, String>> = embed_sources
+            .par_iter()
+            .map(|source| {
+                self.processor
+                    .note_link_refs(&source.body)
+                    .into_iter()
+                    .map(|dest| {
+                        let slug = markdown_link_target(&dest)
+                            .and_then(|(path, _)| {
+                                resolve_vault_path(source.source_path.as_deref(), &path)
+                            })
+                            .and_then(|rel| note_paths.get(&rel).cloned())
+                            .unwrap_or_default();
+                        (dest, slug)
+                    })
+                    .collect()
+            })
+            .collect();
+        for (note, assets) in notes.iter_mut().zip(&note_assets) {
+            let mut paths: Vec<String> = assets
+                .values()
+                .filter(|path| !path.is_empty())
+                .cloned()
+                .collect();
+            paths.sort();
+            paths.dedup();
+            note.assets = paths;
+        }
+
+        let render_ctx = RenderContext {
+            block_index: Some(&block_index),
+            unpublished: Some(&unpublished),
+            note_paths: Some(&note_paths),
+            assets: Some(&asset_index),
+            labels: Some(&label_index),
+            ..RenderContext::new(&slug_map, &base_url)
+        };
+        let embed_ctx = EmbedContext {
+            sources: &embed_sources,
+            index: &embed_index,
+            render: render_ctx,
+        };
+
+        let mut manifest = BuildManifest::new(config_hash);
+        let mut bib_hash_cache: HashMap<PathBuf, String> = HashMap::new();
+        let mut rendered = Vec::new();
+
+        // Decide which notes need rendering and gather their inputs. This stays
+        // serial because loading bibliographies fills the shared store.
+        let mut jobs: Vec<RenderJob> = Vec::with_capacity(notes.len());
+        for (idx, note) in notes.iter().enumerate() {
+            let bibliography_paths = self.bibliography_paths(&note.frontmatter);
+            let citation_style = self.citation_style(&note.frontmatter);
+            // A custom `.csl` style is tracked like a bibliography file
+            let style_path = citation_style
+                .as_deref()
+                .filter(|style| style.ends_with(".csl"))
+                .map(PathBuf::from);
+            let bib_hashes: BTreeMap<String, String> = bibliography_paths
+                .iter()
+                .chain(style_path.iter())
+                .map(|path| {
+                    let hash = bib_hash_cache
+                        .entry(path.clone())
+                        .or_insert_with(|| file_hash(path))
+                        .clone();
+                    (path.to_string_lossy().to_string(), hash)
                 })
+                .collect();
+
+            let previous_entry = note
+                .source_path
+                .as_deref()
+                .and_then(|rel| previous.and_then(|prev| prev.manifest.notes.get(rel)));
+
+            // Reuse the previously rendered note when its source is unchanged,
+            // it still lives at the same slug and path, and no dependency moved
+            let cached = match (previous_entry, previous) {
+                (Some(entry), Some(prev))
+                    if entry.source_hash == sources[idx].1
+                        && entry.assets == note_assets[idx]
+                        && entry.note_links == note_links[idx]
+                        && !entry.dependencies_changed(
+                            &prev.manifest.slug_map,
+                            &slug_map,
+                            &bib_hashes,
+                            &embed_hashes,
+                            &changed_blocks,
+                        ) =>
+                {
+                    note.source_path
+                        .as_deref()
+                        .and_then(|rel| previous_notes.get(rel))
+                        .filter(|prev_note| {
+                            prev_note.slug == note.slug
+                                && prev_note.output_rel_path() == note.output_rel_path()
+                        })
+                        .map(|prev_note| (*prev_note, entry))
+                }
+                _ => None,
             };
 
-            let (html, outgoing_links, toc_html, mut note_diags) = self.processor.convert(
-                &body,
-                &slug_map,
-                &base_url,
-                None, // typst_preamble removed - math now rendered client-side by MathJax
-                citation_ctx.as_ref(),
-                Some(&note.slug),
-                note.source_path.as_deref(),
-            );
-            note.content_html = html;
-            note.outgoing_links = outgoing_links;
-            note.toc_html = toc_html;
-            note.raw_body = Some(body);
+            let (bibliography, citation_style, style_diagnostic) = match cached {
+                Some(_) => (None, None, None),
+                None => {
+                    // A style the note picks itself is reported for the note
+                    let note_style = note
+                        .frontmatter
+                        .citation_style
+                        .as_deref()
+                        .and_then(|style| self.config.resolve_citation_style(style));
+                    let (style, diagnostic) = match note_style {
+                        Some(spec) => bibliography_store.note_style(&spec, note),
+                        None => (bibliography_store.style(citation_style.as_deref()), None),
+                    };
+                    (
+                        Some(bibliography_store.collect(&bibliography_paths)),
+                        Some(style),
+                        diagnostic,
+                    )
+                }
+            };
+
+            jobs.push(RenderJob {
+                bib_hashes,
+                cached,
+                bibliography,
+                citation_style,
+                style_diagnostic,
+            });
+        }
+
+        // Second pass - render markdown with link resolution. Notes are
+        // independent once the slug map exists, so render them in parallel;
+        // the indexed collect keeps results in note order.
+        let results: Vec<Option<RenderOutput>> = notes
+            .par_iter_mut()
+            .zip(jobs.par_iter())
+            .zip(embed_sources.par_iter())
+            .map(|((note, job), source)| {
+                if let Some((prev_note, _)) = job.cached {
+                    note.content_html = prev_note.content_html.clone();
+                    note.outgoing_links = prev_note.outgoing_links.clone();
+                    note.embeds = prev_note.embeds.clone();
+                    note.toc_html = prev_note.toc_html.clone();
+                    note.raw_body = prev_note.raw_body.clone();
+                    return None;
+                }
+
+                let citation_ctx = job
+                    .bibliography
+                    .as_ref()
+                    .filter(|bibliography| !bibliography.is_empty())
+                    .zip(job.citation_style.as_deref())
+                    .map(|(bibliography, style)| CitationContext {
+                        bibliography,
+                        style,
+                    });
+
+                let note_ctx = RenderContext {
+                    note_slug: Some(&note.slug),
+                    source_path: note.source_path.as_deref(),
+                    line_offset: source.line_offset,
+                    note_macros: Some(&note.frontmatter.macros),
+                    citations: citation_ctx.as_ref(),
+                    ..render_ctx
+                };
+                let (html, outgoing_links, toc_html, mut note_diags) =
+                    self.processor.convert(&source.body, &note_ctx);
+                note_diags.extend(job.style_diagnostic.clone());
+                let expansion = self.processor.expand_embeds(
+                    &html,
+                    &note.slug,
+                    note.source_path.as_deref(),
+                    &embed_ctx,
+                );
+                note_diags.extend(expansion.diagnostics);
+                note.content_html = expansion.html;
+                note.outgoing_links = outgoing_links;
+                note.embeds = expansion.embeds;
+                note.toc_html = toc_html.filter(|_| note.frontmatter.toc != Some(false));
+                note.raw_body = Some(source.body.clone());
+                Some(RenderOutput {
+                    diagnostics: note_diags,
+                    embeds: expansion.dependencies,
+                })
+            })
+            .collect();
+
+        for ((note, (job, (assets, note_links))), (result, (_, source_hash))) in notes
+            .iter()
+            .zip(
+                jobs.into_iter()
+                    .zip(note_assets.into_iter().zip(note_links)),
+            )
+            .zip(results.into_iter().zip(&sources))
+        {
+            let (mut note_diags, embeds) = match result {
+                Some(output) => {
+                    rendered.push(note.slug.clone());
+                    (output.diagnostics, output.embeds)
+                }
+                None => job
+                    .cached
+                    .map(|(_, entry)| (entry.diagnostics.clone(), entry.embeds.clone()))
+                    .unwrap_or_default(),
+            };
+
+            if let Some(rel) = &note.source_path {
+                let href = format!("{}{}", base_url, note.output_rel_path());
+                let mut slug_keys = vec![note.slug.clone()];
+                slug_keys.extend(note.aliases.iter().map(|a| slugify(a)));
+                manifest.notes.insert(
+                    rel.clone(),
+                    NoteManifest {
+                        source_hash: source_hash.clone(),
+                        slug: note.slug.clone(),
+                        slug_keys,
+                        href,
+                        links: note.outgoing_links.clone(),
+                        bibliography: job.bib_hashes,
+                        block_ids: note.block_ids.clone(),
+                        nota_labels: note.nota_labels.clone(),
+                        embeds,
+                        assets,
+                        note_links,
+                        diagnostics: note_diags.clone(),
+                    },
+                );
+            }
             diagnostics.append(&mut note_diags);
         }
 
+        manifest.slug_map = slug_map.into_iter().collect();
+
         // Build link graph
         let mut graph = LinkGraph::new();
         for note in &notes {
@@ -142,23 +584,45 @@ impl SiteBuilder {
                 continue;
             }
             for target in &note.outgoing_links {
-                graph.add_link(&note.slug, target);
+                if !unpublished.contains(target) {
+                    graph.add_link(&note.slug, target);
+                }
+            }
+            for target in &note.embeds {
+                graph.add_embed(&note.slug, target);
             }
         }
 
         // Carry over bibliography load diagnostics
         diagnostics.extend(bibliography_store.take_diagnostics());
 
-        tracing::info!("Built site index with {} notes", notes.len());
+        tracing::info!(
+            "Built site index with {} notes ({} rendered, {} reused)",
+            notes.len(),
+            rendered.len(),
+            notes.len() - rendered.len()
+        );
 
         // Collect comments and resolve anchors
         let comments = collect_comments(&notes);
 
-        Ok(SiteIndex {
+        let (redirects, redirect_diags) = collect_redirects(&notes, &base_url);
+        diagnostics.extend(redirect_diags);
+
+        let mut index = SiteIndex {
             notes,
             graph,
             diagnostics,
             comments,
+            redirects,
+        };
+        let tag_diags = tag_collisions(&index);
+        index.diagnostics.extend(tag_diags);
+
+        Ok(IncrementalBuild {
+            index,
+            manifest,
+            rendered,
         })
     }
 
@@ -192,14 +656,15 @@ impl SiteBuilder {
             }
         }
 
+        // Directory iteration order is filesystem-dependent; sort so note,
+        // diagnostic and artifact ordering is stable across machines.
+        files.sort();
+
         Ok(files)
     }
 
-    /// Parse a single markdown file into a Note (without rendering markdown yet)
-    fn parse_note(&self, path: &Path) -> Result<Note, BuildError> {
-        let content = fs::read_to_string(path)?;
-        let (frontmatter, _body) = parse_frontmatter(&content)?;
-
+    /// Build a Note from parsed frontmatter (without rendering markdown yet)
+    fn note_from_frontmatter(&self, path: &Path, frontmatter: Frontmatter) -> Note {
         // Fall back to filename when title/frontmatter is missing (e.g., pure markdown)
         let mut title = frontmatter.title.clone();
         if title.trim().is_empty() {
@@ -214,57 +679,131 @@ impl SiteBuilder {
         let slug = frontmatter.slug.clone().unwrap_or_else(|| {
             path.file_stem()
                 .and_then(|s| s.to_str())
-                .map(|s| slugify(s))
+                .map(slugify)
                 .unwrap_or_else(|| slugify(&frontmatter.title))
         });
 
-        // Determine note type
+        // Determine note type; undeclared types are reported and render as essays
         let note_type = frontmatter
             .note_type
             .as_ref()
-            .and_then(|t| NoteType::from_str(t))
+            .and_then(|t| self.config.resolve_note_type(t))
             .unwrap_or(NoteType::Essay);
 
-        // Parse dates
-        let date = frontmatter
-            .date
-            .as_ref()
-            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
-
-        let updated = frontmatter
-            .updated
-            .as_ref()
-            .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+        // Parse dates; unparseable values are reported by `date_diagnostics`
+        let date = frontmatter.date.as_deref().and_then(parse_date);
+        let updated = frontmatter.updated.as_deref().and_then(parse_date);
 
         // Capture source path relative to vault root
-        let vault_dir = self.config.vault_dir();
-        let source_path = path
-            .strip_prefix(&vault_dir)
-            .ok()
-            .and_then(|p| p.to_str())
-            .map(|s| s.to_string());
+        let source_path = self.source_rel_path(path);
 
-        Ok(Note {
+        Note {
             slug,
             title,
             content_html: String::new(), // Will be filled in second pass
             frontmatter: frontmatter.clone(),
             note_type,
             tags: frontmatter.tags.clone(),
-            date,
-            updated,
+            date: date.map(|d| d.date),
+            updated: updated.map(|d| d.date),
+            date_time: date.and_then(|d| d.time),
+            updated_time: updated.and_then(|d| d.time),
             aliases: frontmatter.aliases.clone(),
             permalink: frontmatter.permalink.clone(),
             outgoing_links: Vec::new(), // Will be filled in second pass
+            embeds: Vec::new(),
+            block_ids: Vec::new(),
+            nota_labels: Vec::new(),
+            assets: Vec::new(),
             preview: frontmatter.summary.clone(),
             toc_html: None, // TODO: Generate TOC
             raw_body: None,
             source_path,
-        })
+        }
     }
 }
 
 impl SiteBuilder {
+    /// Diagnostic for a frontmatter `type:` that is neither built in nor
+    /// declared under `note_types`
+    fn unknown_type_diagnostic(&self, note: &Note, content: &str) -> Option<Diagnostic> {
+        let name = note.frontmatter.note_type.as_deref()?;
+        if self.config.resolve_note_type(name).is_some() {
+            return None;
+        }
+        Some(Diagnostic {
+            code: "note.unknown_type".to_string(),
+            message: format!(
+                "Unknown note type '{}'; declare it under note_types in monowiki.yml (rendering as essay)",
+                name
+            ),
+            severity: DiagnosticSeverity::Warning,
+            note_slug: Some(note.slug.clone()),
+            source_path: note.source_path.clone(),
+            context: None,
+            anchor: None,
+            span: field_position(content, "type")
+                .map(|(line, column)| SourceSpan::point(line, column)),
+        })
+    }
+
+    /// Info diagnostic for a note hidden by `publish_after` or `expires` at
+    /// the build's `as_of` moment; `None` if the note is live. Unparseable
+    /// values are ignored (they are reported by `date_diagnostics`).
+    fn schedule_diagnostic(&self, note: &Note) -> Option<Diagnostic> {
+        let parse = |value: &Option<String>| value.as_deref().and_then(parse_date);
+        let message = match (
+            parse(&note.frontmatter.publish_after),
+            parse(&note.frontmatter.expires),
+        ) {
+            (Some(start), _) if !self.as_of.reached(&start) => format!(
+                "Scheduled for {}; left out of the site",
+                note.frontmatter
+                    .publish_after
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+            (_, Some(end)) if self.as_of.reached(&end) => format!(
+                "Expired on {}; left out of the site",
+                note.frontmatter.expires.as_deref().unwrap_or_default()
+            ),
+            _ => return None,
+        };
+        Some(Diagnostic {
+            code: "note.unpublished".to_string(),
+            message,
+            severity: DiagnosticSeverity::Info,
+            note_slug: Some(note.slug.clone()),
+            source_path: note.source_path.clone(),
+            context: None,
+            anchor: None,
+            span: None,
+        })
+    }
+
+    /// Source path relative to the vault root (e.g., "essays/foo.md")
+    fn source_rel_path(&self, path: &Path) -> Option<String> {
+        path.strip_prefix(self.config.vault_dir())
+            .ok()
+            .and_then(|p| p.to_str())
+            .map(|s| s.to_string())
+    }
+
+    /// Hash of the configuration; any config change invalidates the manifest.
+    fn config_hash(&self) -> String {
+        let serialized = serde_json::to_string(&self.config).unwrap_or_default();
+        content_hash(serialized.as_bytes())
+    }
+
+    /// The note's citation style, falling back to the configured one
+    fn citation_style(&self, frontmatter: &Frontmatter) -> Option<String> {
+        frontmatter
+            .citation_style
+            .as_deref()
+            .and_then(|style| self.config.resolve_citation_style(style))
+            .or_else(|| self.config.citation_style())
+    }
+
     fn bibliography_paths(&self, frontmatter: &Frontmatter) -> Vec<PathBuf> {
         let mut paths = self.config.bibliography_paths();
         for extra in &frontmatter.bibliography {
@@ -293,6 +832,315 @@ fn should_ignore(path: &str, ignores: &[Regex]) -> bool {
     ignores.iter().any(|re| re.is_match(path))
 }
 
+/// Detect duplicate slugs and colliding output paths.
+///
+/// In strict mode the first collision aborts the build. With `recover` set,
+/// the first note (in source path order) keeps its slug or path and the
+/// others are disambiguated with a path-qualified slug; every rename is
+/// reported as a diagnostic naming all conflicting source paths.
+fn resolve_collisions(notes: &mut [Note], recover: bool) -> Result<Vec<Diagnostic>, BuildError> {
+    let mut diagnostics = Vec::new();
+
+    let mut by_slug: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, note) in notes.iter().enumerate() {
+        by_slug.entry(note.slug.clone()).or_default().push(idx);
+    }
+    let mut taken: std::collections::HashSet<String> = by_slug.keys().cloned().collect();
+
+    for (slug, idxs) in &by_slug {
+        if idxs.len() < 2 {
+            continue;
+        }
+        if !recover {
+            tracing::warn!("Duplicate slug: {}", slug);
+            return Err(BuildError::DuplicateSlug(slug.clone()));
+        }
+
+        let paths = conflicting_paths(notes, idxs);
+        for &idx in &idxs[1..] {
+            let new_slug = disambiguated_slug(&notes[idx], &mut taken);
+            diagnostics.push(collision_diagnostic(
+                "slug.duplicate",
+                format!(
+                    "Slug '{}' is shared by {}; using '{}' for this note",
+                    slug, paths, new_slug
+                ),
+                &notes[idx],
+                slug,
+            ));
+            notes[idx].slug = new_slug;
+        }
+    }
+
+    // Renaming can expose new collisions (e.g. a permalink that matches a
+    // renamed slug), so repeat until output paths are unique.
+    loop {
+        let mut by_output: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (idx, note) in notes.iter().enumerate() {
+            by_output
+                .entry(note.output_rel_path())
+                .or_default()
+                .push(idx);
+        }
+
+        let Some((output, idxs)) = by_output.into_iter().find(|(_, idxs)| idxs.len() > 1) else {
+            break;
+        };
+        if !recover {
+            tracing::warn!("Output path collision: {}", output);
+            return Err(BuildError::OutputCollision(output));
+        }
+
+        let paths = conflicting_paths(notes, &idxs);
+        for &idx in &idxs[1..] {
+            let note = &notes[idx];
+            // Drop a colliding permalink before renaming the slug itself
+            let fallback = if note.permalink.is_some()
+                && note.output_rel_path() != format!("{}.html", note.slug)
+            {
+                None
+            } else {
+                Some(disambiguated_slug(note, &mut taken))
+            };
+            let resolution = match &fallback {
+                Some(new_slug) => format!("writing this note to '{}.html'", new_slug),
+                None => format!("ignoring its permalink and writing to '{}.html'", note.slug),
+            };
+            diagnostics.push(collision_diagnostic(
+                "output.collision",
+                format!(
+                    "Output path '{}' is produced by {}; {}",
+                    output, paths, resolution
+                ),
+                note,
+                &output,
+            ));
+
+            let note = &mut notes[idx];
+            note.permalink = None;
+            if let Some(new_slug) = fallback {
+                note.slug = new_slug;
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// Diagnostics for `date` / `updated` values that could not be parsed.
+fn date_diagnostics(note: &Note, content: &str) -> Vec<Diagnostic> {
+    [
+        ("date", note.frontmatter.date.as_deref()),
+        ("updated", note.frontmatter.updated.as_deref()),
+        ("publish_after", note.frontmatter.publish_after.as_deref()),
+        ("expires", note.frontmatter.expires.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some((key, value?)))
+    .filter(|(_, value)| parse_date(value).is_none())
+    .map(|(key, value)| Diagnostic {
+        code: "frontmatter.invalid_date".to_string(),
+        message: format!(
+            "Could not parse {} '{}'; expected YYYY-MM-DD or a timestamp like 2024-08-19T10:00:00Z",
+            key, value
+        ),
+        severity: DiagnosticSeverity::Warning,
+        note_slug: Some(note.slug.clone()),
+        source_path: note.source_path.clone(),
+        context: None,
+        anchor: None,
+        span: field_position(content, key).map(|(line, column)| SourceSpan::point(line, column)),
+    })
+    .collect()
+}
+
+/// Diagnostic for a note whose frontmatter failed to parse.
+fn frontmatter_diagnostic(err: &FrontmatterError, content: &str, note: &Note) -> Diagnostic {
+    let code = match err {
+        FrontmatterError::YamlError(_) => "frontmatter.invalid",
+        FrontmatterError::MissingField(_) => "frontmatter.missing_field",
+    };
+    let span = err
+        .source_position(content)
+        .map(|(line, column)| SourceSpan::point(line, column));
+    // serde_yaml reports positions relative to the YAML block; replace them
+    // with the position in the source file
+    let detail = err.to_string();
+    let detail = match (span, detail.rfind(" at line ")) {
+        (Some(_), Some(idx)) => &detail[..idx],
+        _ => detail.as_str(),
+    };
+    let location = span
+        .map(|s| format!(" (line {}, column {})", s.line, s.column))
+        .unwrap_or_default();
+
+    Diagnostic {
+        code: code.to_string(),
+        message: format!(
+            "{}{}; using filename defaults for this note",
+            detail, location
+        ),
+        severity: DiagnosticSeverity::Error,
+        note_slug: Some(note.slug.clone()),
+        source_path: note.source_path.clone(),
+        context: None,
+        anchor: None,
+        span,
+    }
+}
+
+/// Comma-separated source paths of the given notes, for diagnostics.
+fn conflicting_paths(notes: &[Note], idxs: &[usize]) -> String {
+    idxs.iter()
+        .map(|&idx| {
+            notes[idx]
+                .source_path
+                .clone()
+                .unwrap_or_else(|| notes[idx].slug.clone())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Pick a unique slug qualified by the note's source path (e.g.
+/// "essays/intro.md" -> "essays-intro"), adding a numeric suffix if needed.
+fn disambiguated_slug(note: &Note, taken: &mut std::collections::HashSet<String>) -> String {
+    let base = note
+        .source_path
+        .as_deref()
+        .map(|path| slugify(&path.trim_end_matches(".md").replace('/', " ")))
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| note.slug.clone());
+
+    let mut candidate = base.clone();
+    let mut counter = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{}-{}", base, counter);
+        counter += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+fn collision_diagnostic(code: &str, message: String, note: &Note, context: &str) -> Diagnostic {
+    Diagnostic {
+        code: code.to_string(),
+        message,
+        severity: DiagnosticSeverity::Warning,
+        note_slug: Some(note.slug.clone()),
+        source_path: note.source_path.clone(),
+        context: Some(context.to_string()),
+        anchor: None,
+        span: None,
+    }
+}
+
+/// Output paths written by the build itself, besides note pages
+const RESERVED_OUTPUTS: &[&str] = &["index.html", "404.html"];
+
+/// Redirect stubs for every published note's aliases and `redirect_from`
+/// entries. Stubs that would overwrite a page or another stub are dropped
+/// and reported.
+fn collect_redirects(notes: &[Note], base_url: &str) -> (Vec<Redirect>, Vec<Diagnostic>) {
+    let published = || {
+        notes
+            .iter()
+            .filter(|n| !n.is_draft() && n.note_type != NoteType::Comment)
+    };
+    let pages: HashMap<String, &Note> = published()
+        .map(|note| (note.output_rel_path(), note))
+        .collect();
+
+    let mut redirects: Vec<Redirect> = Vec::new();
+    let mut diagnostics = Vec::new();
+    for note in published() {
+        let own_path = note.output_rel_path();
+        let sources = note
+            .aliases
+            .iter()
+            .map(|alias| (alias, format!("{}.html", slugify(alias))))
+            .chain(
+                note.frontmatter
+                    .redirect_from
+                    .iter()
+                    .map(|from| (from, normalize_permalink(from))),
+            );
+
+        for (source, from) in sources {
+            if source.trim().is_empty() || from == ".html" || from == own_path {
+                continue;
+            }
+            let taken_by = if let Some(page) = pages.get(&from) {
+                Some(format!("the page for '{}'", page.slug))
+            } else if RESERVED_OUTPUTS.contains(&from.as_str()) || from.starts_with("tags/") {
+                Some("a generated page".to_string())
+            } else {
+                redirects
+                    .iter()
+                    .find(|r| r.from == from)
+                    .filter(|r| r.slug != note.slug)
+                    .map(|r| format!("a redirect to '{}'", r.slug))
+            };
+            if let Some(taken_by) = taken_by {
+                diagnostics.push(collision_diagnostic(
+                    "redirect.collision",
+                    format!(
+                        "Redirect from '{}' on '{}' would overwrite {} at '{}'; skipping it",
+                        source, note.slug, taken_by, from
+                    ),
+                    note,
+                    &from,
+                ));
+                continue;
+            }
+            if redirects.iter().any(|r| r.from == from) {
+                continue;
+            }
+            redirects.push(Redirect {
+                from,
+                to: format!("{}{}", base_url, own_path),
+                slug: note.slug.clone(),
+            });
+        }
+    }
+
+    (redirects, diagnostics)
+}
+
+/// Tags whose listing pages would land on the same `tags/<slug>.html`, or
+/// on the tag index. The first tag in sorted order keeps the page; the
+/// others are reported and get no page of their own.
+fn tag_collisions(index: &SiteIndex) -> Vec<Diagnostic> {
+    let mut pages: HashMap<String, String> = HashMap::new();
+    pages.insert("index".to_string(), "the tag index".to_string());
+
+    let mut diagnostics = Vec::new();
+    for (tag, notes) in index.notes_by_tag() {
+        let slug = tag_slug(&tag);
+        if slug.is_empty() {
+            continue;
+        }
+        match pages.get(&slug) {
+            Some(taken_by) => {
+                let path = format!("tags/{}.html", slug);
+                diagnostics.push(collision_diagnostic(
+                    "tag.collision",
+                    format!(
+                        "Tag '{}' would overwrite {} at '{}'; skipping its page",
+                        tag, taken_by, path
+                    ),
+                    notes[0],
+                    &path,
+                ));
+            }
+            None => {
+                pages.insert(slug, format!("the page for tag '{}'", tag));
+            }
+        }
+    }
+    diagnostics
+}
+
 /// Extract comments/annotations from notes of type Comment and resolve anchors.
 fn collect_comments(notes: &[Note]) -> Vec<Comment> {
     // Build lookup of content notes by slug for resolution
@@ -382,6 +1230,38 @@ fn collect_comments(notes: &[Note]) -> Vec<Comment> {
     comments
 }
 
+/// Slug map keys whose notes define a different set of block ids, or nota
+/// labels that render differently, than in the previous build.
+fn changed_block_keys(
+    previous: &BuildManifest,
+    block_index: &BlockIndex,
+    label_index: &LabelIndex,
+) -> HashSet<String> {
+    let mut old: HashMap<&str, (HashSet<&str>, &[NotaLabel])> = HashMap::new();
+    for entry in previous.notes.values() {
+        let ids: HashSet<&str> = entry.block_ids.iter().map(String::as_str).collect();
+        for key in &entry.slug_keys {
+            old.entry(key.as_str())
+                .or_insert_with(|| (ids.clone(), entry.nota_labels.as_slice()));
+        }
+    }
+
+    let mut changed: HashSet<String> = old
+        .keys()
+        .filter(|key| !block_index.contains_key(**key))
+        .map(|key| key.to_string())
+        .collect();
+    for (key, ids) in block_index {
+        let now: HashSet<&str> = ids.iter().map(String::as_str).collect();
+        let labels = label_index.get(key).map(Vec::as_slice).unwrap_or_default();
+        let (old_ids, old_labels) = old.get(key.as_str()).cloned().unwrap_or_default();
+        if old_ids != now || old_labels != labels {
+            changed.insert(key.clone());
+        }
+    }
+    changed
+}
+
 /// Resolve a comment that targets another comment (returns synthetic anchor)
 /// Note: Returns resolved=false because comment pages aren't rendered yet,
 /// so there's no DOM element to navigate to. The synthetic anchor is kept
@@ -502,6 +1382,13 @@ fn resolve_anchor(
         return (None, false);
     };
 
+    // 0) Block id (`^block-id`)
+    if let Some(id) = target_anchor.and_then(|anchor| anchor.strip_prefix('^')) {
+        if target.block_ids.iter().any(|block| block == id) {
+            return (Some(block_anchor(id)), true);
+        }
+    }
+
     let sections = section_digests_from_html(&target.slug, &target.title, &target.content_html);
 
     // 1) Exact match on stable section id
@@ -524,6 +1411,13 @@ fn resolve_anchor(
         }
     }
 
+    // 2b) Bare block id that doesn't name a section
+    if let Some(anchor) = target_anchor {
+        if target.block_ids.iter().any(|block| block == anchor) {
+            return (Some(block_anchor(anchor)), true);
+        }
+    }
+
     // 3) Fuzzy quote match: find section containing the quote in its content
     if let Some(q) = quote {
         if let Some(section_id) = find_section_by_quote(target, q) {
@@ -575,3 +1469,436 @@ fn find_section_by_quote(note: &Note, quote: &str) -> Option<String> {
     }
     best.map(|(_, id)| id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(slug: &str, source_path: &str, permalink: Option<&str>) -> Note {
+        Note {
+            slug: slug.into(),
+            title: slug.into(),
+            content_html: "".into(),
+            frontmatter: Frontmatter::default(),
+            note_type: NoteType::Essay,
+            tags: vec![],
+            date: None,
+            updated: None,
+            date_time: None,
+            updated_time: None,
+            aliases: vec![],
+            permalink: permalink.map(|p| p.to_string()),
+            outgoing_links: vec![],
+            embeds: vec![],
+            block_ids: vec![],
+            nota_labels: vec![],
+            assets: vec![],
+            preview: None,
+            toc_html: None,
+            raw_body: None,
+            source_path: Some(source_path.into()),
+        }
+    }
+
+    #[test]
+    fn duplicate_slug_is_fatal_in_strict_mode() {
+        let mut notes = vec![
+            note("intro", "a/intro.md", None),
+            note("intro", "b/intro.md", None),
+        ];
+        assert!(matches!(
+            resolve_collisions(&mut notes, false),
+            Err(BuildError::DuplicateSlug(slug)) if slug == "intro"
+        ));
+    }
+
+    #[test]
+    fn duplicate_slug_is_path_qualified_when_recovering() {
+        let mut notes = vec![
+            note("intro", "a/intro.md", None),
+            note("intro", "b/intro.md", None),
+            note("b-intro", "b-intro.md", None),
+        ];
+        let diags = resolve_collisions(&mut notes, true).unwrap();
+
+        assert_eq!(notes[0].slug, "intro");
+        assert_eq!(notes[1].slug, "b-intro-2");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "slug.duplicate");
+        assert!(diags[0].message.contains("a/intro.md, b/intro.md"));
+        assert_eq!(diags[0].source_path.as_deref(), Some("b/intro.md"));
+    }
+
+    #[test]
+    fn frontmatter_diagnostics_follow_renamed_slugs() {
+        let index = build_vault_with_config(
+            "recover_collisions: true\n",
+            &[
+                ("a/intro.md", "---\ntitle: Intro\n---\n\nBody.\n"),
+                (
+                    "b/intro.md",
+                    "---\ntitle: Intro\ntype: esay\ndate: last spring\n---\n\nBody.\n",
+                ),
+            ],
+        );
+
+        assert!(index.find_by_slug("b-intro").is_some());
+        for code in [
+            "slug.duplicate",
+            "note.unknown_type",
+            "frontmatter.invalid_date",
+        ] {
+            let diag = index
+                .diagnostics
+                .iter()
+                .find(|d| d.code == code)
+                .unwrap_or_else(|| panic!("missing {code}"));
+            assert_eq!(diag.source_path.as_deref(), Some("b/intro.md"));
+            assert_eq!(diag.note_slug.as_deref(), Some("b-intro"), "{code}");
+        }
+    }
+
+    #[test]
+    fn permalink_colliding_with_slug_is_reported() {
+        let mut notes = vec![
+            note("about", "about.md", None),
+            note("profile", "profile.md", Some("/about")),
+        ];
+        assert!(matches!(
+            resolve_collisions(&mut notes, false),
+            Err(BuildError::OutputCollision(path)) if path == "about.html"
+        ));
+
+        let diags = resolve_collisions(&mut notes, true).unwrap();
+        assert_eq!(notes[0].output_rel_path(), "about.html");
+        assert_eq!(notes[1].output_rel_path(), "profile.html");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "output.collision");
+        assert!(diags[0].message.contains("about.md, profile.md"));
+    }
+
+    /// Build a temporary vault containing `files` (path, contents)
+    fn build_vault(files: &[(&str, &str)]) -> SiteIndex {
+        build_vault_with_config("", files)
+    }
+
+    /// Like `build_vault`, appending `extra_config` to monowiki.yml
+    fn build_vault_with_config(extra_config: &str, files: &[(&str, &str)]) -> SiteIndex {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = dir.path().join("vault");
+        fs::create_dir_all(&vault).unwrap();
+        let config_path = dir.path().join("monowiki.yml");
+        fs::write(
+            &config_path,
+            format!(
+                "site:\n  title: T\n  author: A\n  description: D\n  url: https://example.com\npaths:\n  vault: vault\n  output: docs\n{}",
+                extra_config
+            ),
+        )
+        .unwrap();
+        for (path, contents) in files {
+            let path = vault.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let config = Config::from_file(&config_path).unwrap();
+        SiteBuilder::new(config).build().unwrap()
+    }
+
+    #[test]
+    fn broken_frontmatter_keeps_note_and_reports_position() {
+        let index = build_vault(&[
+            (
+                "broken.md",
+                "---\ntitle: Broken\ndraft: maybe\n---\n\nStill rendered.\n",
+            ),
+            ("linker.md", "See [[broken]].\n"),
+        ]);
+
+        let broken = index.find_by_slug("broken").expect("note kept");
+        assert_eq!(broken.title, "broken");
+        assert!(broken.content_html.contains("Still rendered."));
+
+        let diag = index
+            .diagnostics
+            .iter()
+            .find(|d| d.code == "frontmatter.invalid")
+            .expect("frontmatter diagnostic");
+        assert_eq!(diag.source_path.as_deref(), Some("broken.md"));
+        assert_eq!(diag.span, Some(SourceSpan::point(3, 8)));
+        assert!(!index
+            .diagnostics
+            .iter()
+            .any(|d| d.code == "link.unresolved"));
+    }
+
+    #[test]
+    fn block_references_resolve_links_and_comment_anchors() {
+        let index = build_vault(&[
+            ("paper.md", "# Paper\n\nThe key claim. ^claim\n"),
+            (
+                "reader.md",
+                "See [[paper#^claim]], not [[paper#^missing]].\n",
+            ),
+            (
+                "review.md",
+                "---\ntitle: Review\ntype: comment\ntarget_slug: paper\ntarget_anchor: ^claim\n---\n\nNeeds a source.\n",
+            ),
+        ]);
+
+        let paper = index.find_by_slug("paper").unwrap();
+        assert_eq!(paper.block_ids, vec!["claim"]);
+        assert!(paper
+            .content_html
+            .contains("<p id=\"^claim\">The key claim.</p>"));
+
+        let reader = index.find_by_slug("reader").unwrap();
+        assert!(reader.content_html.contains("href=\"/paper.html#^claim\""));
+        let unresolved: Vec<_> = index
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "link.unresolved_block")
+            .collect();
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].context.as_deref(), Some("paper#^missing"));
+
+        let comment = &index.comments[0];
+        assert!(comment.resolved);
+        assert_eq!(comment.resolved_anchor.as_deref(), Some("^claim"));
+    }
+
+    #[test]
+    fn declared_note_types_resolve_and_unknown_types_are_reported() {
+        let index = build_vault_with_config(
+            "note_types:\n  rfc: { rss: false }\n",
+            &[
+                (
+                    "proposal.md",
+                    "---\ntitle: Proposal\ntype: RFC\n---\n\nBody.\n",
+                ),
+                ("typo.md", "---\ntitle: Typo\ntype: esay\n---\n\nBody.\n"),
+            ],
+        );
+
+        let proposal = index.find_by_slug("proposal").unwrap();
+        assert_eq!(proposal.note_type, NoteType::Custom("rfc".into()));
+
+        let typo = index.find_by_slug("typo").unwrap();
+        assert_eq!(typo.note_type, NoteType::Essay);
+        let diags: Vec<_> = index
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "note.unknown_type")
+            .collect();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].source_path.as_deref(), Some("typo.md"));
+        assert!(diags[0].message.contains("'esay'"));
+        assert_eq!(diags[0].span, Some(SourceSpan::point(3, 7)));
+    }
+
+    #[test]
+    fn timestamps_parse_and_bad_dates_are_reported() {
+        let index = build_vault(&[
+            (
+                "timed.md",
+                "---\ntitle: Timed\ndate: 2024-08-19 10:00\nupdated: 2024-09-01T08:30:00+02:00\n---\n\nBody.\n",
+            ),
+            ("vague.md", "---\ntitle: Vague\ndate: last spring\n---\n\nBody.\n"),
+        ]);
+
+        let timed = index.find_by_slug("timed").unwrap();
+        assert_eq!(timed.date, chrono::NaiveDate::from_ymd_opt(2024, 8, 19));
+        assert_eq!(
+            timed.updated_time.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2024-09-01T08:30:00+02:00")
+        );
+
+        let vague = index.find_by_slug("vague").unwrap();
+        assert_eq!(vague.date, None);
+        let diag = index
+            .diagnostics
+            .iter()
+            .find(|d| d.code == "frontmatter.invalid_date")
+            .expect("date diagnostic");
+        assert_eq!(diag.source_path.as_deref(), Some("vague.md"));
+        assert_eq!(diag.span, Some(SourceSpan::point(3, 7)));
+    }
+
+    #[test]
+    fn scheduled_and_expired_notes_are_left_out() {
+        let index = build_vault(&[
+            (
+                "soon.md",
+                "---\ntitle: Soon\naliases: [Coming Up]\npublish_after: 3000-01-01\n---\n\nLater.\n",
+            ),
+            ("old.md", "---\ntitle: Old\nexpires: 2000-01-01\n---\n\nGone.\n"),
+            (
+                "live.md",
+                "---\ntitle: Live\npublish_after: 2000-01-01\nexpires: 3000-01-01\n---\n\nSee [[Coming Up]] and [[old]].\n",
+            ),
+        ]);
+
+        assert!(index.find_by_slug("soon").is_none());
+        assert!(index.find_by_slug("old").is_none());
+        let live = index.find_by_slug("live").unwrap();
+        assert!(live.content_html.contains("See Coming Up and old."));
+        assert!(index.graph.outgoing("live").is_empty());
+
+        let codes: Vec<_> = index.diagnostics.iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(
+            codes.iter().filter(|c| **c == "note.unpublished").count(),
+            2
+        );
+        assert_eq!(
+            codes.iter().filter(|c| **c == "link.unpublished").count(),
+            2
+        );
+        assert!(!codes.contains(&"link.unresolved"));
+    }
+
+    #[test]
+    fn aliases_and_retired_urls_get_redirects() {
+        let index = build_vault(&[
+            (
+                "ownership.md",
+                "---
+title: Ownership
+aliases: [Rust Safety, Borrowing]
+redirect_from:
+  - /essays/rust-safety/
+  - borrowing
+---
+
+Body.
+",
+            ),
+            (
+                "borrowing.md",
+                "---
+title: Borrowing
+---
+
+Body.
+",
+            ),
+        ]);
+
+        let redirects: Vec<_> = index
+            .redirects
+            .iter()
+            .map(|r| (r.from.as_str(), r.to.as_str()))
+            .collect();
+        assert_eq!(
+            redirects,
+            vec![
+                ("rust-safety.html", "/ownership.html"),
+                ("essays/rust-safety/index.html", "/ownership.html"),
+            ]
+        );
+
+        let collisions: Vec<_> = index
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "redirect.collision")
+            .collect();
+        assert_eq!(collisions.len(), 2);
+        assert!(collisions
+            .iter()
+            .all(|d| d.context.as_deref() == Some("borrowing.html")));
+    }
+
+    #[test]
+    fn colliding_tag_slugs_are_reported() {
+        let index = build_vault(&[
+            (
+                "a.md",
+                "---\ntitle: A\ntags: [c, c++, index]\n---\n\nBody.\n",
+            ),
+            ("b.md", "---\ntitle: B\ntags: [rust]\n---\n\nBody.\n"),
+        ]);
+
+        let collisions: Vec<_> = index
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "tag.collision")
+            .collect();
+        assert_eq!(collisions.len(), 2);
+        assert!(collisions[0].message.contains("Tag 'c++'"));
+        assert_eq!(collisions[0].context.as_deref(), Some("tags/c.html"));
+        assert!(collisions[1].message.contains("the tag index"));
+        assert_eq!(collisions[0].note_slug.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn markdown_links_to_vault_files_join_the_graph() {
+        let index = build_vault(&[
+            (
+                "essays/intro.md",
+                "---\ntitle: Intro\n---\n\nRead [the guide](../guides/setup.md#install) \
+                 and [this](gone.md).\n",
+            ),
+            (
+                "guides/setup.md",
+                "---\ntitle: Setup\n---\n\n## Install\n\nSteps.\n",
+            ),
+        ]);
+
+        let intro = index.find_by_slug("intro").unwrap();
+        assert!(intro.content_html.contains("href=\"/setup.html#install\""));
+        assert!(intro.content_html.contains("and this."));
+        assert_eq!(index.graph.outgoing("intro"), vec!["setup"]);
+        assert_eq!(index.graph.backlinks("setup"), vec!["intro"]);
+
+        let unresolved: Vec<_> = index
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == "link.unresolved")
+            .collect();
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(
+            unresolved[0].source_path.as_deref(),
+            Some("essays/intro.md")
+        );
+    }
+
+    #[test]
+    fn toc_depth_comes_from_config_and_notes_can_opt_out() {
+        let index = build_vault_with_config(
+            "toc:\n  min_depth: 2\n  max_depth: 2\n",
+            &[
+                ("a.md", "# Top\n\n## Part\n\n### Detail\n"),
+                ("b.md", "---\ntitle: B\ntoc: false\n---\n\n## Part\n"),
+            ],
+        );
+
+        let toc = index.find_by_slug("a").unwrap().toc_html.clone().unwrap();
+        assert!(toc.contains("#part") && !toc.contains("#top") && !toc.contains("#detail"));
+        assert!(index.find_by_slug("b").unwrap().toc_html.is_none());
+    }
+
+    #[test]
+    fn nota_labels_resolve_across_notes() {
+        let index = build_vault(&[
+            (
+                "algebra.md",
+                "---\ntitle: Algebra\n---\n\n@Lemma[l1]{First}: a\n\n@Lemma[l2]{Second}: b\n",
+            ),
+            ("uses.md", "By [[algebra#l2]] and [[Algebra#l1|this]].\n"),
+        ]);
+
+        let algebra = index.find_by_slug("algebra").unwrap();
+        assert_eq!(algebra.nota_labels.len(), 2);
+        let uses = index.find_by_slug("uses").unwrap();
+        assert!(uses
+            .content_html
+            .contains("href=\"/algebra.html#l2\" data-nota-kind=\"lemma\">Lemma 2</a>"));
+        assert!(uses
+            .content_html
+            .contains("#l1\" data-nota-kind=\"lemma\">this</a>"));
+        assert!(index
+            .graph
+            .outgoing("uses")
+            .contains(&"algebra".to_string()));
+    }
+}
@@ -1,7 +1,8 @@
 //! Configuration parsing and management.
 
+use crate::models::NoteType;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -39,6 +40,11 @@ pub struct Config {
     #[serde(default)]
     pub bibliography: Vec<PathBuf>,
 
+    /// Citation style: a CSL style bundled with hayagriva (`apa`,
+    /// `chicago-author-date`, `ieee`, ...) or the path of a `.csl` file
+    #[serde(default)]
+    pub citation_style: Option<String>,
+
     #[serde(default)]
     pub theme_overrides: Option<PathBuf>,
 
@@ -51,9 +57,44 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub enable_backlinks: bool,
 
+    /// Show an "Unlinked mentions" panel on note pages
+    #[serde(default)]
+    pub enable_mentions: bool,
+
+    /// Report duplicate slugs and output path collisions as diagnostics
+    /// and disambiguate them instead of failing the build
+    #[serde(default)]
+    pub recover_collisions: bool,
+
+    /// Fill in missing `date` / `updated` from the first and last git
+    /// commit touching each note
+    #[serde(default)]
+    pub git_dates: bool,
+
+    /// Resized and WebP variants of raster images
+    #[serde(default)]
+    pub images: ImageConfig,
+
+    /// Heading levels shown in each note's table of contents
+    #[serde(default)]
+    pub toc: TocConfig,
+
+    /// Numbering of `@Theorem[label]{Title}:` style nota blocks
+    #[serde(default)]
+    pub nota: NotaConfig,
+
+    /// How LaTeX math is typeset
+    #[serde(default)]
+    pub math: MathConfig,
+
     #[serde(default)]
     pub adapters: Vec<AdapterConfig>,
 
+    /// Note types beyond the built-ins (essay, thought, draft, doc, comment),
+    /// or overrides for built-in ones, keyed by the frontmatter `type:` value
+    #[serde(default)]
+    pub note_types: BTreeMap<String, NoteTypeConfig>,
+
     // Internal: path to config file (for relative path resolution)
     #[serde(skip)]
     config_path: Option<PathBuf>,
@@ -88,6 +129,16 @@ pub struct PathsConfig {
 
     #[serde(default)]
     pub theme: Option<PathBuf>,
+
+    /// Folder (relative to the vault) searched first for `![[file.png]]`
+    /// attachments
+    #[serde(default)]
+    pub attachments: Option<PathBuf>,
+
+    /// Directory for build caches that aren't deployed (defaults to
+    /// `.monowiki-cache` next to the config file)
+    #[serde(default)]
+    pub cache: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,6 +163,204 @@ fn default_port() -> u16 {
     8000
 }
 
+/// Responsive image settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageConfig {
+    /// Generate variants and rewrite `<img>` tags into `srcset` markup
+    #[serde(default)]
+    pub responsive: bool,
+
+    /// Widths (in pixels) of the resized variants; images are never upscaled
+    #[serde(default = "default_image_widths")]
+    pub widths: Vec<u32>,
+
+    /// Also offer WebP versions through `<picture>`
+    #[serde(default = "default_true")]
+    pub webp: bool,
+
+    /// JPEG quality of resized JPEG variants (1-100)
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+}
+
+fn default_image_widths() -> Vec<u32> {
+    vec![480, 960, 1600]
+}
+
+fn default_jpeg_quality() -> u8 {
+    80
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            responsive: false,
+            widths: default_image_widths(),
+            webp: true,
+            jpeg_quality: default_jpeg_quality(),
+        }
+    }
+}
+
+/// Table of contents settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TocConfig {
+    /// Shallowest heading level listed (1 = `#`)
+    #[serde(default = "default_toc_min_depth")]
+    pub min_depth: u8,
+
+    /// Deepest heading level listed
+    #[serde(default = "default_toc_max_depth")]
+    pub max_depth: u8,
+}
+
+fn default_toc_min_depth() -> u8 {
+    1
+}
+
+fn default_toc_max_depth() -> u8 {
+    6
+}
+
+impl Default for TocConfig {
+    fn default() -> Self {
+        Self {
+            min_depth: default_toc_min_depth(),
+            max_depth: default_toc_max_depth(),
+        }
+    }
+}
+
+/// How nota blocks (theorems, lemmas, definitions...) are numbered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotaConfig {
+    /// One counter per kind (Theorem 1, Lemma 1) or one shared by all
+    /// kinds (Theorem 1, Lemma 2)
+    #[serde(default)]
+    pub counter: NotaCounter,
+
+    /// Restart numbering at each `##` section and prefix the section
+    /// number (Theorem 2.1)
+    #[serde(default)]
+    pub by_section: bool,
+
+    /// Kinds that are never numbered (compared case-insensitively)
+    #[serde(default = "default_unnumbered_kinds")]
+    pub unnumbered: Vec<String>,
+}
+
+fn default_unnumbered_kinds() -> Vec<String> {
+    vec!["proof".to_string()]
+}
+
+impl Default for NotaConfig {
+    fn default() -> Self {
+        Self {
+            counter: NotaCounter::default(),
+            by_section: false,
+            unnumbered: default_unnumbered_kinds(),
+        }
+    }
+}
+
+/// Counter shared between nota block kinds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotaCounter {
+    #[default]
+    PerKind,
+    Shared,
+}
+
+/// Math rendering settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MathConfig {
+    /// `mathjax` typesets in the browser; `mathml` converts to MathML at
+    /// build time, falling back to MathJax for unsupported constructs
+    #[serde(default)]
+    pub render: MathRender,
+
+    /// LaTeX macros available in every note, e.g. `R: \mathbb{R}` or
+    /// `norm: \left\lVert #1 \right\rVert` (arguments are `#1`..`#9`)
+    #[serde(default)]
+    pub macros: MathMacros,
+}
+
+/// LaTeX macro definitions by name (with or without the leading `\`)
+pub type MathMacros = BTreeMap<String, String>;
+
+impl MathConfig {
+    /// Site macros with a note's own `macros` frontmatter applied on top
+    pub fn macros_for(&self, overrides: Option<&MathMacros>) -> MathMacros {
+        let mut macros: MathMacros = self
+            .macros
+            .iter()
+            .map(|(name, body)| (name.trim_start_matches('\\').to_string(), body.clone()))
+            .collect();
+        for (name, body) in overrides.into_iter().flatten() {
+            macros.insert(name.trim_start_matches('\\').to_string(), body.clone());
+        }
+        macros
+    }
+}
+
+/// Where LaTeX math is typeset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MathRender {
+    #[default]
+    Mathjax,
+    Mathml,
+}
+
+/// A `note_types` entry; settings left out keep the type's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoteTypeConfig {
+    /// Show notes of this type in the `{{directory_tree}}` index listing
+    #[serde(default)]
+    pub listed: Option<bool>,
+
+    /// Include notes of this type in rss.xml
+    #[serde(default)]
+    pub rss: Option<bool>,
+
+    /// Include notes of this type in the search index
+    #[serde(default)]
+    pub search: Option<bool>,
+
+    /// Page template; defaults to `post`, or `api` for docs with a `doc_kind`
+    #[serde(default)]
+    pub template: Option<PageTemplate>,
+}
+
+/// Per-type publishing settings for notes
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteTypeSettings {
+    pub listed: bool,
+    pub rss: bool,
+    pub search: bool,
+    pub template: Option<PageTemplate>,
+}
+
+impl Default for NoteTypeSettings {
+    fn default() -> Self {
+        Self {
+            listed: true,
+            rss: true,
+            search: true,
+            template: None,
+        }
+    }
+}
+
+/// Built-in page templates a note type can render with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageTemplate {
+    Post,
+    Api,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdapterConfig {
     pub name: String,
@@ -148,6 +397,14 @@ impl Config {
         self.resolve_path(&self.paths.output)
     }
 
+    /// Get the build cache directory, resolved relative to config file
+    pub fn cache_dir(&self) -> PathBuf {
+        match &self.paths.cache {
+            Some(cache) => self.resolve_path(cache),
+            None => self.resolve_path(Path::new(".monowiki-cache")),
+        }
+    }
+
     /// Resolve an arbitrary path relative to the config file location
     pub fn resolve_relative(&self, path: &Path) -> PathBuf {
         self.resolve_path(path)
@@ -161,6 +418,27 @@ impl Config {
             .collect()
     }
 
+    /// Get the citation style, with `.csl` paths resolved relative to the
+    /// config file
+    pub fn citation_style(&self) -> Option<String> {
+        self.resolve_citation_style(self.citation_style.as_deref()?)
+    }
+
+    /// Resolve a citation style name, or a `.csl` path relative to the config
+    /// file; `None` when blank
+    pub fn resolve_citation_style(&self, style: &str) -> Option<String> {
+        let style = style.trim();
+        if style.ends_with(".csl") {
+            Some(
+                self.resolve_path(Path::new(style))
+                    .to_string_lossy()
+                    .to_string(),
+            )
+        } else {
+            Some(style.to_string()).filter(|style| !style.is_empty())
+        }
+    }
+
     /// Get the templates directory (None means use built-in)
     pub fn templates_dir(&self) -> Option<PathBuf> {
         self.paths.templates.as_ref().map(|p| self.resolve_path(p))
@@ -205,6 +483,42 @@ impl Config {
         }
     }
 
+    /// Resolve a frontmatter `type:` value to a built-in or declared type.
+    /// Returns `None` for types that are neither.
+    pub fn resolve_note_type(&self, name: &str) -> Option<NoteType> {
+        let note_type = NoteType::named(name);
+        let declared = self
+            .note_types
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case(note_type.as_str()));
+        (note_type.is_builtin() || declared).then_some(note_type)
+    }
+
+    /// Settings for a note type: the built-in defaults, overridden by
+    /// whatever its `note_types` entry sets
+    pub fn note_type_settings(&self, note_type: &NoteType) -> NoteTypeSettings {
+        let mut settings = match note_type {
+            // Comments render as pages but stay out of search
+            NoteType::Comment => NoteTypeSettings {
+                search: false,
+                ..NoteTypeSettings::default()
+            },
+            _ => NoteTypeSettings::default(),
+        };
+        if let Some(entry) = self
+            .note_types
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(note_type.as_str()))
+            .map(|(_, entry)| entry)
+        {
+            settings.listed = entry.listed.unwrap_or(settings.listed);
+            settings.rss = entry.rss.unwrap_or(settings.rss);
+            settings.search = entry.search.unwrap_or(settings.search);
+            settings.template = entry.template.or(settings.template);
+        }
+        settings
+    }
+
     /// Normalized base URL with leading and trailing slash ("/foo/" or "/")
     pub fn normalized_base_url(&self) -> String {
         normalize_base_url(&self.base_url)
@@ -267,17 +581,28 @@ mod tests {
                 output: PathBuf::from("docs"),
                 templates: None,
                 theme: None,
+                attachments: None,
+                cache: None,
             },
             orcid: None,
             server: ServerConfig::default(),
             base_url: default_base_url(),
             ignore_patterns: vec![],
             bibliography: vec![],
+            citation_style: None,
             theme_overrides: None,
             enable_rss: true,
             enable_sitemap: true,
             enable_backlinks: true,
+            enable_mentions: false,
+            recover_collisions: false,
+            git_dates: false,
+            images: ImageConfig::default(),
+            toc: TocConfig::default(),
+            nota: NotaConfig::default(),
+            math: MathConfig::default(),
             adapters: vec![],
+            note_types: BTreeMap::new(),
             config_path: None,
         };
 
@@ -301,17 +626,28 @@ mod tests {
                 output: PathBuf::from("docs"),
                 templates: None,
                 theme: None,
+                attachments: None,
+                cache: None,
             },
             orcid: None,
             server: ServerConfig::default(),
             base_url: default_base_url(),
             ignore_patterns: vec![],
             bibliography: vec![],
+            citation_style: None,
             theme_overrides: None,
             enable_rss: true,
             enable_sitemap: true,
             enable_backlinks: true,
+            enable_mentions: false,
+            recover_collisions: false,
+            git_dates: false,
+            images: ImageConfig::default(),
+            toc: TocConfig::default(),
+            nota: NotaConfig::default(),
+            math: MathConfig::default(),
             adapters: vec![],
+            note_types: BTreeMap::new(),
             config_path: None,
         };
 
@@ -321,4 +657,37 @@ mod tests {
         assert_eq!(config.get("server.port"), Some("8000".into()));
         assert_eq!(config.get("nonexistent.key"), None);
     }
+
+    #[test]
+    fn note_types_are_declared_in_config() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+site: { title: T, author: A, description: D, url: "https://example.com" }
+paths: { vault: vault, output: docs }
+note_types:
+  paper: { template: post }
+  meeting: { listed: false, rss: false, search: false }
+  thought: { rss: false }
+  comment: { listed: false }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.resolve_note_type("Paper"),
+            Some(NoteType::Custom("paper".into()))
+        );
+        assert_eq!(config.resolve_note_type("essay"), Some(NoteType::Essay));
+        assert_eq!(config.resolve_note_type("rfc"), None);
+
+        let meeting = config.note_type_settings(&NoteType::Custom("meeting".into()));
+        assert!(!meeting.listed && !meeting.rss && !meeting.search);
+        let paper = config.note_type_settings(&NoteType::Custom("paper".into()));
+        assert_eq!(paper.template, Some(PageTemplate::Post));
+        assert!(paper.listed && paper.rss && paper.search);
+        assert!(!config.note_type_settings(&NoteType::Thought).rss);
+        // Declaring a built-in type only overrides the settings it names
+        let comment = config.note_type_settings(&NoteType::Comment);
+        assert!(!comment.listed && comment.rss && !comment.search);
+    }
 }
@@ -0,0 +1,135 @@
+//! Parsing of frontmatter dates and timestamps.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+/// A parsed `date` / `updated` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsedDate {
+    /// Calendar date, in the timestamp's own offset
+    pub date: NaiveDate,
+    /// Full timestamp, if the value included a time of day
+    pub time: Option<DateTime<FixedOffset>>,
+}
+
+impl ParsedDate {
+    /// The current time
+    pub fn now() -> Self {
+        Utc::now().fixed_offset().into()
+    }
+
+    /// Whether this moment is at or after `other`. When either side has no
+    /// time of day the calendar dates are compared.
+    pub fn reached(&self, other: &ParsedDate) -> bool {
+        match (self.time, other.time) {
+            (Some(time), Some(other_time)) => time >= other_time,
+            _ => self.date >= other.date,
+        }
+    }
+}
+
+impl From<DateTime<FixedOffset>> for ParsedDate {
+    fn from(time: DateTime<FixedOffset>) -> Self {
+        Self {
+            date: time.date_naive(),
+            time: Some(time),
+        }
+    }
+}
+
+/// Timestamp formats carrying an explicit offset
+const OFFSET_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%d %H:%M:%S %z",
+    "%Y-%m-%d %H:%M %z",
+    "%Y-%m-%dT%H:%M%:z",
+    "%Y-%m-%dT%H:%M%z",
+];
+
+/// Timestamp formats without an offset (interpreted as UTC)
+const NAIVE_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d"];
+
+/// Parse a frontmatter date.
+///
+/// Accepts plain dates (`2024-08-19`, `2024/08/19`), RFC 3339 timestamps
+/// (`2024-08-19T10:00:00+02:00`) and space-separated times with an optional
+/// offset (`2024-08-19 10:00`, `2024-08-19 10:00 +0200`). Times without an
+/// offset are taken as UTC.
+pub fn parse_date(input: &str) -> Option<ParsedDate> {
+    let input = input.trim();
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Some(time.into());
+    }
+    for format in OFFSET_FORMATS {
+        if let Ok(time) = DateTime::parse_from_str(input, format) {
+            return Some(time.into());
+        }
+    }
+    for format in NAIVE_FORMATS {
+        if let Ok(time) = NaiveDateTime::parse_from_str(input, format) {
+            return Some(time.and_utc().fixed_offset().into());
+        }
+    }
+    DATE_FORMATS.iter().find_map(|format| {
+        NaiveDate::parse_from_str(input, format)
+            .ok()
+            .map(|date| ParsedDate { date, time: None })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_dates_have_no_time() {
+        let parsed = parse_date("2024-08-19").unwrap();
+        assert_eq!(parsed.date, NaiveDate::from_ymd_opt(2024, 8, 19).unwrap());
+        assert_eq!(parsed.time, None);
+        assert_eq!(parse_date("2024/08/19").unwrap().date, parsed.date);
+    }
+
+    #[test]
+    fn timestamps_keep_their_offset() {
+        let parsed = parse_date("2024-08-19T23:30:00-05:00").unwrap();
+        // The calendar date is the one in the timestamp's own offset
+        assert_eq!(parsed.date, NaiveDate::from_ymd_opt(2024, 8, 19).unwrap());
+        assert_eq!(parsed.time.unwrap().offset().local_minus_utc(), -5 * 3600);
+
+        let parsed = parse_date("2024-08-19 10:00 +0200").unwrap();
+        assert_eq!(
+            parsed.time.unwrap().to_rfc3339(),
+            "2024-08-19T10:00:00+02:00"
+        );
+
+        let parsed = parse_date("2024-08-19 10:00").unwrap();
+        assert_eq!(
+            parsed.time.unwrap().to_rfc3339(),
+            "2024-08-19T10:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn reached_compares_times_or_days() {
+        let day = parse_date("2024-08-19").unwrap();
+        let morning = parse_date("2024-08-19T09:00:00Z").unwrap();
+        let evening = parse_date("2024-08-19T18:00:00Z").unwrap();
+        assert!(evening.reached(&morning));
+        assert!(!morning.reached(&evening));
+        assert!(morning.reached(&day) && day.reached(&evening));
+        assert!(!day.reached(&parse_date("2024-08-20").unwrap()));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_eq!(parse_date("last tuesday"), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+    }
+}
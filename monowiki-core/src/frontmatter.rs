@@ -14,6 +14,132 @@ pub enum FrontmatterError {
     MissingField(String),
 }
 
+impl FrontmatterError {
+    /// Line and column (1-based) of the error within the full source file.
+    ///
+    /// YAML errors are mapped from their position inside the frontmatter
+    /// block; other errors point at the opening `---`.
+    pub fn source_position(&self, content: &str) -> Option<(usize, usize)> {
+        let captures = frontmatter_regex().captures(content)?;
+        match self {
+            FrontmatterError::YamlError(e) => {
+                let location = e.location()?;
+                let yaml_start = captures.get(1)?.start();
+                let line_offset = content[..yaml_start].matches('\n').count();
+                Some((location.line() + line_offset, location.column()))
+            }
+            FrontmatterError::MissingField(_) => Some((1, 1)),
+        }
+    }
+}
+
+/// Line and column (1-based) of a top-level frontmatter field's value
+/// within the full source file
+pub fn field_position(content: &str, key: &str) -> Option<(usize, usize)> {
+    let yaml = frontmatter_regex().captures(content)?.get(1)?;
+    let line_offset = content[..yaml.start()].matches('\n').count();
+    yaml.as_str().lines().enumerate().find_map(|(idx, line)| {
+        let rest = line.strip_prefix(key)?.strip_prefix(':')?;
+        let value_start = line.len() - rest.trim_start().len();
+        Some((
+            idx + 1 + line_offset,
+            line[..value_start].chars().count() + 1,
+        ))
+    })
+}
+
+/// Set a top-level scalar field, replacing its value or appending the field.
+/// A frontmatter block is created if the file has none.
+pub fn set_field(content: &str, key: &str, value: &str) -> String {
+    let value = yaml_scalar(value);
+    edit_fields(content, |lines| {
+        match lines.iter().position(|line| is_field(line, key)) {
+            Some(idx) => lines[idx] = format!("{key}: {value}"),
+            None => lines.push(format!("{key}: {value}")),
+        }
+    })
+}
+
+/// Append an entry to the top-level `aliases` list, in whichever style
+/// (flow `[a, b]` or block `- a`) the file already uses
+pub fn add_alias(content: &str, alias: &str) -> String {
+    let value = yaml_scalar(alias);
+    edit_fields(content, |lines| {
+        let Some(idx) = lines.iter().position(|line| is_field(line, "aliases")) else {
+            lines.push(format!("aliases: [{value}]"));
+            return;
+        };
+        let rest = lines[idx]["aliases:".len()..].trim().to_string();
+        if rest.is_empty() {
+            let items = lines[idx + 1..]
+                .iter()
+                .take_while(|line| line.starts_with([' ', '\t', '-']))
+                .count();
+            let indent = match lines.get(idx + 1).filter(|_| items > 0) {
+                Some(item) => item[..item.len() - item.trim_start().len()].to_string(),
+                None => "  ".to_string(),
+            };
+            lines.insert(idx + 1 + items, format!("{indent}- {value}"));
+        } else {
+            let inner = rest
+                .strip_prefix('[')
+                .and_then(|r| r.strip_suffix(']'))
+                .unwrap_or(&rest)
+                .trim();
+            lines[idx] = if inner.is_empty() {
+                format!("aliases: [{value}]")
+            } else {
+                format!("aliases: [{inner}, {value}]")
+            };
+        }
+    })
+}
+
+/// Apply `edit` to the frontmatter lines, leaving the body untouched
+fn edit_fields(content: &str, edit: impl FnOnce(&mut Vec<String>)) -> String {
+    let yaml = frontmatter_regex()
+        .captures(content)
+        .and_then(|captures| captures.get(1));
+    let mut lines: Vec<String> = yaml
+        .map(|yaml| yaml.as_str().lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    edit(&mut lines);
+    match yaml {
+        Some(yaml) => format!(
+            "{}{}{}",
+            &content[..yaml.start()],
+            lines.join("\n"),
+            &content[yaml.end()..]
+        ),
+        None => format!("---\n{}\n---\n{}", lines.join("\n"), content),
+    }
+}
+
+fn is_field(line: &str, key: &str) -> bool {
+    line.strip_prefix(key)
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// A string as a YAML scalar, quoted unless it reads back as the same plain
+/// string (safe in both block and flow context)
+fn yaml_scalar(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ' '))
+        && !value.starts_with(['-', '.', ' '])
+        && !value.ends_with(' ')
+        && matches!(
+            serde_yaml::from_str::<serde_yaml::Value>(value),
+            Ok(serde_yaml::Value::String(ref s)) if s == value
+        );
+    if plain {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
 static FRONTMATTER_REGEX: OnceLock<Regex> = OnceLock::new();
 
 fn frontmatter_regex() -> &'static Regex {
@@ -68,6 +194,18 @@ pub fn parse_frontmatter(content: &str) -> Result<(Frontmatter, String), Frontma
     }
 }
 
+/// Return the markdown body with any frontmatter block removed.
+///
+/// Unlike `parse_frontmatter`, this never fails, so notes with broken
+/// frontmatter can still be rendered.
+pub fn strip_frontmatter(content: &str) -> &str {
+    frontmatter_regex()
+        .captures(content)
+        .and_then(|captures| captures.get(2))
+        .map(|body| body.as_str())
+        .unwrap_or(content)
+}
+
 /// Extract just the frontmatter without the body
 pub fn extract_frontmatter(content: &str) -> Option<Frontmatter> {
     parse_frontmatter(content).ok().map(|(fm, _)| fm)
@@ -174,6 +312,43 @@ Content."#;
         assert!(parse_frontmatter(content).is_err());
     }
 
+    #[test]
+    fn test_yaml_error_position_maps_to_source_line() {
+        let content = "---\ntitle: Test\ndraft: maybe\n---\n\nContent.";
+        let err = parse_frontmatter(content).unwrap_err();
+        assert_eq!(err.source_position(content), Some((3, 8)));
+        assert_eq!(strip_frontmatter(content), "Content.");
+    }
+
+    #[test]
+    fn set_field_replaces_or_appends() {
+        let content = "---\ntitle: Post\nslug: old\n---\n\nBody.\n";
+        let updated = set_field(content, "slug", "new-name");
+        assert_eq!(updated, "---\ntitle: Post\nslug: new-name\n---\n\nBody.\n");
+        let updated = set_field(content, "target_slug", "2024");
+        assert!(updated.contains("slug: old\ntarget_slug: \"2024\"\n---"));
+        assert_eq!(
+            set_field("Body.\n", "title", "Post"),
+            "---\ntitle: Post\n---\nBody.\n"
+        );
+    }
+
+    #[test]
+    fn add_alias_keeps_list_style() {
+        let flow = "---\ntitle: T\naliases: [a, b]\n---\nBody";
+        assert!(add_alias(flow, "old").contains("aliases: [a, b, old]\n"));
+
+        let block = "---\ntitle: T\naliases:\n    - a\ntags: [x]\n---\nBody";
+        let updated = add_alias(block, "old");
+        assert!(updated.contains("aliases:\n    - a\n    - old\ntags: [x]"));
+        let (fm, _) = parse_frontmatter(&updated).unwrap();
+        assert_eq!(fm.aliases, vec!["a", "old"]);
+
+        let missing = add_alias("---\ntitle: T\n---\nBody", "old: name");
+        let (fm, _) = parse_frontmatter(&missing).unwrap();
+        assert_eq!(fm.aliases, vec!["old: name"]);
+    }
+
     #[test]
     fn test_missing_title() {
         let content = r#"---
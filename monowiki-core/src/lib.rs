@@ -5,26 +5,32 @@
 //! This crate provides the fundamental building blocks for parsing markdown,
 //! managing site configuration, and building the content model.
 
+pub mod assets;
 pub mod bibliography;
 pub mod builder;
 pub mod config;
+pub mod dates;
 pub mod frontmatter;
+pub mod manifest;
 pub mod markdown;
+pub mod mentions;
 pub mod models;
 pub mod search;
 pub mod slug;
 // pub mod artifacts;
-// pub mod assets;
 // pub mod cleanup;
 
+pub use assets::AssetIndex;
 pub use bibliography::{Bibliography, BibliographyStore};
-pub use builder::SiteBuilder;
+pub use builder::{IncrementalBuild, PreviousBuild, SiteBuilder};
 pub use config::Config;
+pub use manifest::BuildManifest;
+pub use mentions::{Mention, MentionIndex};
 pub use models::{
-    Comment, CommentStatus, Diagnostic, DiagnosticSeverity, Frontmatter, LinkGraph, Note, NoteType,
-    SiteIndex,
+    tag_hierarchy, Comment, CommentStatus, Diagnostic, DiagnosticSeverity, Frontmatter, LinkGraph,
+    Note, NoteType, Redirect, SiteIndex, SourceSpan,
 };
 pub use search::SectionDigest;
 pub use search::{build_search_index, SearchEntry};
-pub use slug::slugify;
+pub use slug::{slugify, tag_slug};
 pub use markdown::highlight_code;
@@ -0,0 +1,252 @@
+//! Persistent build manifest used for incremental rebuilds.
+//!
+//! The manifest records, for every note, the hash of its source file, the
+//! slug map entries it contributes, and everything its rendered HTML depends
+//! on. On the next build, notes whose source and dependencies are unchanged
+//! are reused from the previous site index instead of being re-rendered.
+
+use crate::markdown::NotaLabel;
+use crate::models::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// Bump when the manifest layout or rendering semantics change.
+pub const MANIFEST_VERSION: u32 = 5;
+
+/// Build manifest persisted next to the build output.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BuildManifest {
+    pub version: u32,
+
+    /// monowiki version that produced the manifest
+    pub generator: String,
+
+    /// Hash of the configuration used for the build
+    pub config_hash: String,
+
+    /// Resolved slug map (slug or alias slug -> href)
+    #[serde(default)]
+    pub slug_map: BTreeMap<String, String>,
+
+    /// Per-note records keyed by source path relative to the vault
+    #[serde(default)]
+    pub notes: BTreeMap<String, NoteManifest>,
+
+    /// Hashes of written output files keyed by path relative to the output dir
+    #[serde(default)]
+    pub outputs: BTreeMap<String, String>,
+}
+
+/// Manifest record for a single note.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NoteManifest {
+    /// blake3 hash of the raw source file
+    pub source_hash: String,
+
+    pub slug: String,
+
+    /// Slug map keys contributed by this note (slug plus alias slugs)
+    #[serde(default)]
+    pub slug_keys: Vec<String>,
+
+    /// Href the slug map keys resolve to
+    pub href: String,
+
+    /// Wikilink target slugs referenced by the note (resolved or not)
+    #[serde(default)]
+    pub links: Vec<String>,
+
+    /// Bibliography files used by the note, with their content hashes
+    #[serde(default)]
+    pub bibliography: BTreeMap<String, String>,
+
+    /// Block ids (`^block-id`) the note defines
+    #[serde(default)]
+    pub block_ids: Vec<String>,
+
+    /// Labelled nota blocks the note defines, as references render them
+    #[serde(default)]
+    pub nota_labels: Vec<NotaLabel>,
+
+    /// Slug map keys transcluded by the note (directly or through nested
+    /// embeds), with the source hash of the note each resolved to
+    #[serde(default)]
+    pub embeds: BTreeMap<String, String>,
+
+    /// Asset references as written, with the vault-relative path each
+    /// resolved to (empty when missing)
+    #[serde(default)]
+    pub assets: BTreeMap<String, String>,
+
+    /// Markdown links to `.md` files as written, with the slug of the note
+    /// each resolved to (empty when missing)
+    #[serde(default)]
+    pub note_links: BTreeMap<String, String>,
+
+    /// Diagnostics emitted while rendering the note
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BuildManifest {
+    pub fn new(config_hash: String) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            generator: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash,
+            ..Default::default()
+        }
+    }
+
+    /// Whether notes recorded in this manifest can be reused for a build
+    /// with the given configuration hash.
+    pub fn is_compatible(&self, config_hash: &str) -> bool {
+        self.version == MANIFEST_VERSION
+            && self.generator == env!("CARGO_PKG_VERSION")
+            && self.config_hash == config_hash
+    }
+}
+
+impl NoteManifest {
+    /// Check whether any recorded dependency resolves differently now.
+    ///
+    /// `old_slug_map` is the slug map from the previous build; `bib_hashes`
+    /// holds the current hashes of bibliography files and `embed_hashes` the
+    /// current source hash of the note each slug map key resolves to.
+    /// `changed_blocks` holds slug map keys whose notes gained or lost block
+    /// ids, since `[[note#^block-id]]` links are checked against them.
+    pub fn dependencies_changed(
+        &self,
+        old_slug_map: &BTreeMap<String, String>,
+        new_slug_map: &HashMap<String, String>,
+        bib_hashes: &BTreeMap<String, String>,
+        embed_hashes: &HashMap<String, String>,
+        changed_blocks: &HashSet<String>,
+    ) -> bool {
+        let link_changed = self.links.iter().any(|target| {
+            old_slug_map.get(target) != new_slug_map.get(target) || changed_blocks.contains(target)
+        });
+
+        let embed_changed = self
+            .embeds
+            .iter()
+            .any(|(key, hash)| embed_hashes.get(key) != Some(hash));
+
+        link_changed || embed_changed || &self.bibliography != bib_hashes
+    }
+}
+
+/// Hash arbitrary bytes into a hex digest.
+pub fn content_hash(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+/// Hash a file on disk, returning an empty string if it cannot be read.
+pub fn file_hash(path: &Path) -> String {
+    std::fs::read(path)
+        .map(|bytes| content_hash(&bytes))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(links: &[&str]) -> NoteManifest {
+        NoteManifest {
+            source_hash: "abc".into(),
+            slug: "note".into(),
+            slug_keys: vec!["note".into()],
+            href: "/note.html".into(),
+            links: links.iter().map(|s| s.to_string()).collect(),
+            bibliography: BTreeMap::new(),
+            block_ids: Vec::new(),
+            nota_labels: Vec::new(),
+            embeds: BTreeMap::new(),
+            assets: BTreeMap::new(),
+            note_links: BTreeMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn unchanged_links_are_clean() {
+        let old = BTreeMap::from([("target".to_string(), "/target.html".to_string())]);
+        let new = HashMap::from([("target".to_string(), "/target.html".to_string())]);
+        assert!(!entry(&["target"]).dependencies_changed(
+            &old,
+            &new,
+            &BTreeMap::new(),
+            &HashMap::new(),
+            &HashSet::new()
+        ));
+    }
+
+    #[test]
+    fn link_target_appearing_or_moving_is_dirty() {
+        let old = BTreeMap::new();
+        let new = HashMap::from([("target".to_string(), "/target.html".to_string())]);
+        assert!(entry(&["target"]).dependencies_changed(
+            &old,
+            &new,
+            &BTreeMap::new(),
+            &HashMap::new(),
+            &HashSet::new()
+        ));
+
+        let old = BTreeMap::from([("target".to_string(), "/target.html".to_string())]);
+        let new = HashMap::from([("target".to_string(), "/moved/target.html".to_string())]);
+        assert!(entry(&["target"]).dependencies_changed(
+            &old,
+            &new,
+            &BTreeMap::new(),
+            &HashMap::new(),
+            &HashSet::new()
+        ));
+    }
+
+    #[test]
+    fn block_ids_changing_on_link_target_is_dirty() {
+        let slug_map = BTreeMap::from([("target".to_string(), "/target.html".to_string())]);
+        let new: HashMap<_, _> = slug_map.clone().into_iter().collect();
+        let changed = HashSet::from(["target".to_string()]);
+        let (bib, embeds) = (BTreeMap::new(), HashMap::new());
+        let e = entry(&["target"]);
+        assert!(!e.dependencies_changed(&slug_map, &new, &bib, &embeds, &HashSet::new()));
+        assert!(e.dependencies_changed(&slug_map, &new, &bib, &embeds, &changed));
+    }
+
+    #[test]
+    fn bibliography_change_is_dirty() {
+        let mut e = entry(&[]);
+        e.bibliography.insert("refs.bib".into(), "h1".into());
+        let now = BTreeMap::from([("refs.bib".to_string(), "h2".to_string())]);
+        assert!(e.dependencies_changed(
+            &BTreeMap::new(),
+            &HashMap::new(),
+            &now,
+            &HashMap::new(),
+            &HashSet::new()
+        ));
+    }
+
+    #[test]
+    fn embedded_source_change_is_dirty() {
+        let mut e = entry(&[]);
+        e.embeds.insert("shared".into(), "h1".into());
+        let same = HashMap::from([("shared".to_string(), "h1".to_string())]);
+        let changed = HashMap::from([("shared".to_string(), "h2".to_string())]);
+        let (old, new, bib) = (BTreeMap::new(), HashMap::new(), BTreeMap::new());
+        assert!(!e.dependencies_changed(&old, &new, &bib, &same, &HashSet::new()));
+        assert!(e.dependencies_changed(&old, &new, &bib, &changed, &HashSet::new()));
+        assert!(e.dependencies_changed(&old, &new, &bib, &HashMap::new(), &HashSet::new()));
+    }
+
+    #[test]
+    fn manifest_compatibility_checks_config_hash() {
+        let manifest = BuildManifest::new("cfg".into());
+        assert!(manifest.is_compatible("cfg"));
+        assert!(!manifest.is_compatible("other"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Obsidian-style block references.
+//!
+//! A paragraph or list item ending in `^block-id` gets that id as its DOM id
+//! (rendered as `id="^block-id"`), so `[[note#^block-id]]` links, embeds and
+//! comment anchors can point at a single block.
+
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+static BLOCK_ID_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap());
+
+/// Block ids defined by each note, keyed by slug map key (slug or alias)
+pub type BlockIndex = HashMap<String, HashSet<String>>;
+
+/// DOM id / URL fragment for a block id
+pub fn block_anchor(id: &str) -> String {
+    format!("^{id}")
+}
+
+/// Split a trailing ` ^block-id` off block text, returning the remaining
+/// text and the id
+fn split_block_id(text: &str) -> Option<(&str, &str)> {
+    let caps = BLOCK_ID_RE.captures(text)?;
+    let id = caps.get(1)?.as_str();
+    let rest = &text[..caps.get(0)?.start()];
+    Some((rest.trim_end(), id))
+}
+
+/// Block ids in `markdown` with the byte range of the block defining each
+pub fn scan_block_ids(markdown: &str, options: Options) -> Vec<(String, Range<usize>)> {
+    let mut ids = Vec::new();
+    // (block range, is list item, id already found)
+    let mut open: Vec<(Range<usize>, bool, bool)> = Vec::new();
+    let mut last_text: Option<CowStr> = None;
+
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        let closes_block = match &event {
+            Event::End(TagEnd::Paragraph) | Event::End(TagEnd::Item) => true,
+            // A nested list ends the item's own text
+            Event::Start(Tag::List(_)) => matches!(open.last(), Some((_, true, false))),
+            _ => false,
+        };
+
+        if closes_block {
+            if let (Some(text), Some(block)) = (&last_text, open.last_mut()) {
+                if let Some((_, id)) = split_block_id(text).filter(|_| !block.2) {
+                    ids.push((id.to_string(), block.0.clone()));
+                    block.2 = true;
+                }
+            }
+        }
+
+        match event {
+            Event::Start(Tag::Paragraph) => open.push((range, false, false)),
+            Event::Start(Tag::Item) => open.push((range, true, false)),
+            Event::End(TagEnd::Paragraph) | Event::End(TagEnd::Item) => {
+                open.pop();
+            }
+            _ => {}
+        }
+        last_text = None;
+        if let Event::Text(text) = event {
+            last_text = Some(text);
+        }
+    }
+
+    ids
+}
+
+/// Transformer that turns trailing `^block-id` markers into DOM ids
+#[derive(Debug, Default)]
+pub struct BlockIdTransformer;
+
+impl BlockIdTransformer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn transform(&self, events: Vec<Event<'static>>) -> Vec<Event<'static>> {
+        let mut out: Vec<Event<'static>> = Vec::with_capacity(events.len());
+        // Index in `out` of each open paragraph / list item start
+        let mut open: Vec<usize> = Vec::new();
+
+        for event in events {
+            match &event {
+                Event::Start(Tag::Paragraph) | Event::Start(Tag::Item) => {
+                    open.push(out.len());
+                }
+                Event::Start(Tag::List(_)) => {
+                    if let Some(&start) = open.last() {
+                        if matches!(out[start], Event::Start(Tag::Item)) {
+                            assign_block_id(&mut out, start);
+                        }
+                    }
+                }
+                Event::End(TagEnd::Paragraph) | Event::End(TagEnd::Item) => {
+                    if let Some(start) = open.pop() {
+                        assign_block_id(&mut out, start);
+                    }
+                }
+                _ => {}
+            }
+            out.push(event);
+        }
+
+        out
+    }
+}
+
+/// If the text just emitted ends in `^block-id`, strip it and give the block
+/// starting at `start` that id
+fn assign_block_id(out: &mut Vec<Event<'static>>, start: usize) {
+    let tag = match &out[start] {
+        Event::Start(Tag::Paragraph) => "p",
+        Event::Start(Tag::Item) => "li",
+        _ => return,
+    };
+    let Some(Event::Text(text)) = out.last() else {
+        return;
+    };
+    let Some((rest, id)) = split_block_id(text) else {
+        return;
+    };
+
+    let rest = rest.to_string();
+    let anchor = block_anchor(id);
+    out.pop();
+    if rest.is_empty() {
+        // `^id` on its own line: drop the line break before it too
+        if matches!(out.last(), Some(Event::SoftBreak | Event::HardBreak)) {
+            out.pop();
+        }
+    } else {
+        out.push(Event::Text(CowStr::Boxed(rest.into_boxed_str())));
+    }
+    out[start] = Event::Html(CowStr::Boxed(
+        format!("<{tag} id=\"{anchor}\">").into_boxed_str(),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::html;
+
+    fn render(markdown: &str) -> String {
+        let events: Vec<Event<'static>> = Parser::new_ext(markdown, Options::ENABLE_TASKLISTS)
+            .map(|e| e.into_static())
+            .collect();
+        let mut out = String::new();
+        html::push_html(
+            &mut out,
+            BlockIdTransformer::new().transform(events).into_iter(),
+        );
+        out
+    }
+
+    #[test]
+    fn paragraphs_and_list_items_get_ids() {
+        let html =
+            render("A claim worth citing. ^claim\n\n- first ^one\n- second\n  - nested ^two\n");
+        assert!(html.contains("<p id=\"^claim\">A claim worth citing.</p>"));
+        assert!(html.contains("<li id=\"^one\">first</li>"));
+        assert!(html.contains("<li id=\"^two\">nested</li>"));
+        assert!(!html.contains("^one<"));
+    }
+
+    #[test]
+    fn id_on_its_own_line_and_plain_carets() {
+        let html = render("Some text\n^own-line\n\n2^10 is not a block id\n");
+        assert!(html.contains("<p id=\"^own-line\">Some text</p>"));
+        assert!(html.contains("<p>2^10 is not a block id</p>"));
+    }
+
+    #[test]
+    fn scan_reports_block_ranges() {
+        let markdown = "Intro.\n\n- item ^a\n  - child\n\nPara ^b\n";
+        let ids = scan_block_ids(markdown, Options::empty());
+        let found: Vec<_> = ids
+            .iter()
+            .map(|(id, range)| (id.as_str(), &markdown[range.clone()]))
+            .collect();
+        assert_eq!(found[0].0, "a");
+        assert!(found[0].1.starts_with("- item ^a") && found[0].1.contains("child"));
+        assert_eq!(found[1], ("b", "Para ^b\n"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Obsidian-style callout blocks.
+//!
+//! Recognizes blockquotes whose first line is `[!type] Optional title`,
+//! optionally followed by `-` (folded) or `+` (foldable, open), and wraps
+//! them in styled callout markup. Only the marker line is rewritten; the
+//! title and body stay as regular events so downstream transformations
+//! (math, wikilinks, sidenotes, nested callouts) still apply.
+
+use super::html_escape;
+use super::source_map::{strip_spans, unspanned, MergedText, SpannedEvent};
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use regex::Regex;
+use std::ops::Range;
+
+static CALLOUT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[!([A-Za-z][A-Za-z0-9_-]*)\]([+-]?)[ \t]*").unwrap());
+
+#[derive(Debug, Default)]
+pub struct CalloutTransformer;
+
+/// Parsed `[!type]` marker line of a callout
+struct CalloutHeader {
+    kind: String,
+    /// `None` if not foldable, otherwise whether it starts open
+    fold: Option<bool>,
+    title: Vec<SpannedEvent>,
+    /// Events consumed from the blockquote (paragraph start through the
+    /// marker line)
+    consumed: usize,
+    /// Whether the first paragraph continues after the marker line
+    continues_paragraph: bool,
+}
+
+impl CalloutTransformer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn transform(&self, events: Vec<Event<'static>>) -> Vec<Event<'static>> {
+        strip_spans(self.transform_spanned(unspanned(events)))
+    }
+
+    /// Like `transform`, but keeps source ranges; generated markup takes the
+    /// range of the blockquote it replaces
+    pub fn transform_spanned(&self, events: Vec<SpannedEvent>) -> Vec<SpannedEvent> {
+        let mut out = Vec::with_capacity(events.len());
+        // Closing markup for each open blockquote that became a callout
+        let mut open: Vec<Option<&'static str>> = Vec::new();
+        let mut i = 0;
+
+        while i < events.len() {
+            let (event, range) = &events[i];
+            match event {
+                Event::Start(Tag::BlockQuote(_)) => {
+                    if let Some(header) = parse_header(&events[i + 1..]) {
+                        open.push(Some(if header.fold.is_some() {
+                            "</div></details>\n"
+                        } else {
+                            "</div></div>\n"
+                        }));
+                        i += 1 + header.consumed;
+                        emit_callout(&mut out, header, range);
+                        continue;
+                    }
+                    open.push(None);
+                    out.push(events[i].clone());
+                }
+                Event::End(TagEnd::BlockQuote(_)) => match open.pop() {
+                    Some(Some(close)) => out.push((html(close.to_string()), range.clone())),
+                    _ => out.push(events[i].clone()),
+                },
+                _ => out.push(events[i].clone()),
+            }
+            i += 1;
+        }
+
+        out
+    }
+}
+
+/// Recognize a `[!type]` marker at the start of a blockquote's contents
+fn parse_header(events: &[SpannedEvent]) -> Option<CalloutHeader> {
+    if !matches!(events.first(), Some((Event::Start(Tag::Paragraph), _))) {
+        return None;
+    }
+
+    let mut merged = MergedText::new();
+    let mut idx = 1;
+    while let Some((Event::Text(text), range)) = events.get(idx) {
+        merged.push(text, range.clone());
+        idx += 1;
+    }
+
+    let caps = CALLOUT_RE.captures(&merged.text)?;
+    let kind = caps[1].to_lowercase();
+    let fold = match &caps[2] {
+        "-" => Some(false),
+        "+" => Some(true),
+        _ => None,
+    };
+
+    let mut title = Vec::new();
+    let marker_end = caps.get(0)?.end();
+    if marker_end < merged.text.len() {
+        title.push((
+            Event::Text(CowStr::Boxed(
+                merged.text[marker_end..].to_string().into_boxed_str(),
+            )),
+            merged.source_range(marker_end, merged.text.len()),
+        ));
+    }
+
+    // The rest of the marker line (inline markup) is part of the title
+    while let Some((event, range)) = events.get(idx) {
+        match event {
+            Event::SoftBreak | Event::HardBreak | Event::End(TagEnd::Paragraph) => break,
+            _ => title.push((event.clone(), range.clone())),
+        }
+        idx += 1;
+    }
+
+    let continues_paragraph = !matches!(
+        events.get(idx),
+        Some((Event::End(TagEnd::Paragraph), _)) | None
+    );
+    Some(CalloutHeader {
+        kind,
+        fold,
+        title,
+        consumed: idx + 1,
+        continues_paragraph,
+    })
+}
+
+fn emit_callout(out: &mut Vec<SpannedEvent>, header: CalloutHeader, range: &Range<usize>) {
+    let kind = html_escape(&header.kind);
+    let class = canonical_kind(&header.kind).unwrap_or(&kind);
+    let (open, title_close) = match header.fold {
+        Some(is_open) => (
+            format!(
+                "<details class=\"callout callout-{class}\" data-callout=\"{kind}\"{}><summary class=\"callout-title\">",
+                if is_open { " open" } else { "" }
+            ),
+            "</summary><div class=\"callout-content\">\n",
+        ),
+        None => (
+            format!(
+                "<div class=\"callout callout-{class}\" data-callout=\"{kind}\"><div class=\"callout-title\">"
+            ),
+            "</div><div class=\"callout-content\">\n",
+        ),
+    };
+
+    out.push((html(open), range.clone()));
+    if header.title.is_empty() {
+        let title = capitalize(&header.kind);
+        out.push((
+            Event::Text(CowStr::Boxed(title.into_boxed_str())),
+            range.clone(),
+        ));
+    } else {
+        out.extend(header.title);
+    }
+    out.push((html(title_close.to_string()), range.clone()));
+    if header.continues_paragraph {
+        out.push((Event::Start(Tag::Paragraph), range.clone()));
+    }
+}
+
+/// Style class for Obsidian's built-in callout types and their aliases
+fn canonical_kind(kind: &str) -> Option<&'static str> {
+    Some(match kind {
+        "note" => "note",
+        "abstract" | "summary" | "tldr" => "abstract",
+        "info" => "info",
+        "todo" => "todo",
+        "tip" | "hint" | "important" => "tip",
+        "success" | "check" | "done" => "success",
+        "question" | "help" | "faq" => "question",
+        "warning" | "caution" | "attention" => "warning",
+        "failure" | "fail" | "missing" => "failure",
+        "danger" | "error" => "danger",
+        "bug" => "bug",
+        "example" => "example",
+        "quote" | "cite" => "quote",
+        _ => return None,
+    })
+}
+
+fn html(markup: String) -> Event<'static> {
+    Event::Html(CowStr::Boxed(markup.into_boxed_str()))
+}
+
+fn capitalize(input: &str) -> String {
+    let mut chars = input.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{html, Options, Parser};
+
+    fn render(markdown: &str) -> String {
+        let events: Vec<Event<'static>> = Parser::new_ext(markdown, Options::empty())
+            .map(|e| e.into_static())
+            .collect();
+        let mut out = String::new();
+        html::push_html(
+            &mut out,
+            CalloutTransformer::new().transform(events).into_iter(),
+        );
+        out
+    }
+
+    #[test]
+    fn callout_with_custom_title_and_body() {
+        let html = render("> [!warning] Mind the *gap*\n> Body text.\n>\n> Second paragraph.\n");
+        assert!(html.contains(
+            "<div class=\"callout callout-warning\" data-callout=\"warning\"><div class=\"callout-title\">Mind the <em>gap</em></div>"
+        ));
+        assert!(html.contains("<p>Body text.</p>"));
+        assert!(html.contains("<p>Second paragraph.</p>"));
+        assert!(!html.contains("[!warning]"));
+        assert!(!html.contains("<blockquote>"));
+    }
+
+    #[test]
+    fn default_title_aliases_and_fold_state() {
+        let html = render("> [!faq]-\n> Hidden answer.\n");
+        assert!(html.contains(
+            "<details class=\"callout callout-question\" data-callout=\"faq\"><summary class=\"callout-title\">Faq</summary>"
+        ));
+        assert!(html.trim_end().ends_with("</div></details>"));
+
+        let html = render("> [!Custom]+ Open\n> Shown.\n");
+        assert!(html
+            .contains("<details class=\"callout callout-custom\" data-callout=\"custom\" open>"));
+    }
+
+    #[test]
+    fn nested_callouts_and_plain_blockquotes() {
+        let html = render(
+            "> [!note] Outer\n> > [!tip] Inner\n> > Deep.\n>\n> After.\n\n> Just a quote.\n",
+        );
+        let outer = html.find("callout-note").unwrap();
+        let inner = html.find("callout-tip").unwrap();
+        assert!(outer < inner);
+        assert!(html.contains("<p>Deep.</p>\n</div></div>"));
+        assert!(html.contains("<blockquote>\n<p>Just a quote.</p>\n</blockquote>"));
+    }
+}
@@ -1,26 +1,49 @@
 //! Inline citation handling and reference list rendering.
+//!
+//! Citation labels and reference entries are formatted by a CSL style
+//! through hayagriva's citation engine.
 
-use crate::bibliography::Bibliography;
+use super::source_map::{sub_range, SourceMap, SpannedEvent};
+use crate::bibliography::{Bibliography, CitationStyle};
 use crate::models::{Diagnostic, DiagnosticSeverity};
-use hayagriva::{types::Person, Entry};
+use hayagriva::archive::locales;
+use hayagriva::citationberg::Locale;
+use hayagriva::{
+    BibliographyDriver, BibliographyRequest, BufWriteFormat, CitationItem, CitationRequest,
+    ElemChild, ElemChildren,
+};
 use once_cell::sync::Lazy;
 use pulldown_cmark::{CowStr, Event};
 use regex::Regex;
 use std::collections::HashMap;
-use std::fmt::Write;
+use std::ops::Range;
 use tracing::warn;
 
+/// Locale files providing CSL terms ("et al.", "and", ...)
+static LOCALES: Lazy<Vec<Locale>> = Lazy::new(locales);
+
 /// Context required to resolve citation keys.
 pub struct CitationContext<'a> {
     pub bibliography: &'a Bibliography,
+    /// Style formatting citation labels and the reference list
+    pub style: &'a CitationStyle,
 }
 
 /// A single reference entry in the rendered bibliography.
 #[derive(Debug, Clone)]
 pub struct CitationRef {
     pub key: String,
+    /// Order of the key's first citation, used for anchors
     pub number: usize,
-    pub entry: Option<Entry>,
+    /// The entry as formatted by the style, or `None` when the key has no
+    /// bibliography entry
+    pub html: Option<String>,
+}
+
+/// A `[@...]` marker awaiting its label, by index into the output events
+struct PendingCitation {
+    keys: Vec<String>,
+    event: usize,
 }
 
 /// Transform markdown events by replacing `[@key]` markers with inline citations.
@@ -28,8 +51,12 @@ pub struct CitationTransformer<'a> {
     ctx: &'a CitationContext<'a>,
     order: Vec<String>,
     index: HashMap<String, usize>,
+    citations: Vec<PendingCitation>,
     note_slug: Option<String>,
     source_path: Option<String>,
+    source_map: Option<&'a SourceMap<'a>>,
+    /// Source range of the first citation of each key
+    first_use: HashMap<String, Range<usize>>,
 }
 
 impl<'a> CitationTransformer<'a> {
@@ -42,19 +69,28 @@ impl<'a> CitationTransformer<'a> {
             ctx,
             order: Vec::new(),
             index: HashMap::new(),
+            citations: Vec::new(),
             note_slug,
             source_path,
+            source_map: None,
+            first_use: HashMap::new(),
         }
     }
 
+    /// Attach a source map so diagnostics carry line/column spans
+    pub fn with_source_map(mut self, source_map: &'a SourceMap<'a>) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
     pub fn transform(
         mut self,
-        events: Vec<Event<'static>>,
-    ) -> (Vec<Event<'static>>, Vec<CitationRef>, Vec<Diagnostic>) {
+        events: Vec<SpannedEvent>,
+    ) -> (Vec<SpannedEvent>, Vec<CitationRef>, Vec<Diagnostic>) {
         let mut out = Vec::with_capacity(events.len());
         let mut diagnostics = Vec::new();
 
-        for event in events {
+        for (event, range) in events {
             match event {
                 Event::Text(text) => {
                     let mut last_end = 0;
@@ -64,64 +100,71 @@ impl<'a> CitationTransformer<'a> {
                             let start = full.start();
                             let end = full.end();
                             if start > last_end {
-                                out.push(Event::Text(CowStr::Boxed(
-                                    text[last_end..start].to_string().into_boxed_str(),
-                                )));
+                                out.push((
+                                    Event::Text(CowStr::Boxed(
+                                        text[last_end..start].to_string().into_boxed_str(),
+                                    )),
+                                    sub_range(&range, text.len(), last_end, start),
+                                ));
                             }
+                            let cite_range = sub_range(&range, text.len(), start, end);
                             let inner = caps.get(1).map(|m| m.as_str()).unwrap_or_default();
-                            let rendered = self.render_citation(inner);
-                            out.push(Event::Html(CowStr::Boxed(rendered.into_boxed_str())));
+                            let rendered = self.register_citation(inner, &cite_range, out.len());
+                            out.push((
+                                Event::Html(CowStr::Boxed(rendered.into_boxed_str())),
+                                cite_range,
+                            ));
                             last_end = end;
                             replaced = true;
                         }
                     }
 
                     if replaced && last_end < text.len() {
-                        out.push(Event::Text(CowStr::Boxed(
-                            text[last_end..].to_string().into_boxed_str(),
-                        )));
+                        out.push((
+                            Event::Text(CowStr::Boxed(
+                                text[last_end..].to_string().into_boxed_str(),
+                            )),
+                            sub_range(&range, text.len(), last_end, text.len()),
+                        ));
                     }
 
                     if !replaced {
-                        out.push(Event::Text(text));
+                        out.push((Event::Text(text), range));
                     }
                 }
-                other => out.push(other),
+                other => out.push((other, range)),
             }
         }
 
-        let references = self
-            .order
-            .iter()
-            .enumerate()
-            .map(|(idx, key)| {
-                let entry = self.ctx.bibliography.get(key).cloned();
-                if entry.is_none() {
-                    warn!("Missing bibliography entry for key '{}'", key);
-                    diagnostics.push(Diagnostic {
-                        code: "citation.missing_entry".to_string(),
-                        message: format!("Missing bibliography entry for key '{}'", key),
-                        severity: DiagnosticSeverity::Warning,
-                        note_slug: self.note_slug.clone(),
-                        source_path: self.source_path.clone(),
-                        context: Some(key.clone()),
-                        anchor: None,
-                    });
-                }
-                CitationRef {
-                    key: key.clone(),
-                    number: idx + 1,
-                    entry,
-                }
-            })
-            .collect();
+        for key in &self.order {
+            if self.ctx.bibliography.get(key).is_none() {
+                warn!("Missing bibliography entry for key '{}'", key);
+                diagnostics.push(Diagnostic {
+                    code: "citation.missing_entry".to_string(),
+                    message: format!("Missing bibliography entry for key '{}'", key),
+                    severity: DiagnosticSeverity::Warning,
+                    note_slug: self.note_slug.clone(),
+                    source_path: self.source_path.clone(),
+                    context: Some(key.clone()),
+                    anchor: None,
+                    span: self
+                        .source_map
+                        .zip(self.first_use.get(key))
+                        .map(|(map, range)| map.span(range)),
+                });
+            }
+        }
 
+        let references = self.format(&mut out);
         (out, references, diagnostics)
     }
 
-    fn render_citation(&mut self, inner: &str) -> String {
+    /// Record the keys cited by `inner`; the marker's HTML is filled in by
+    /// `format` once every citation is known. Returns the HTML for markers
+    /// without keys.
+    fn register_citation(&mut self, inner: &str, range: &Range<usize>, event: usize) -> String {
         let keys: Vec<String> = inner
-            .split(|c| c == ';' || c == ',')
+            .split([';', ','])
             .map(|s| s.trim().trim_start_matches('@'))
             .filter(|s| !s.is_empty())
             .map(|s| s.to_string())
@@ -131,21 +174,88 @@ impl<'a> CitationTransformer<'a> {
             return format!("[{}]", html_escape(inner));
         }
 
-        let numbers: Vec<usize> = keys.iter().map(|k| self.register(k)).collect();
-        let label = numbers
-            .iter()
-            .map(|n| n.to_string())
-            .collect::<Vec<_>>()
-            .join(", ");
+        for key in &keys {
+            self.first_use
+                .entry(key.clone())
+                .or_insert_with(|| range.clone());
+            self.register(key);
+        }
+        self.citations.push(PendingCitation { keys, event });
+        String::new()
+    }
+
+    /// Format citation labels into `out` and the reference list with the
+    /// style. Styles see every citation at once, so numbering, "ibid." and
+    /// author-date disambiguation work across the note.
+    fn format(&self, out: &mut [SpannedEvent]) -> Vec<CitationRef> {
+        let style = self.ctx.style.csl();
+        let mut driver = BibliographyDriver::new();
+        for citation in &self.citations {
+            let items: Vec<_> = citation
+                .keys
+                .iter()
+                .filter_map(|key| self.ctx.bibliography.get(key))
+                .map(CitationItem::with_entry)
+                .collect();
+            if !items.is_empty() {
+                driver.citation(CitationRequest::from_items(items, style, &LOCALES));
+            }
+        }
+        let rendered = driver.finish(BibliographyRequest::new(style, None, &LOCALES));
+
+        let mut labels = rendered.citations.into_iter();
+        for citation in &self.citations {
+            let resolved = citation
+                .keys
+                .iter()
+                .any(|key| self.ctx.bibliography.get(key).is_some());
+            let label = match resolved.then(|| labels.next()).flatten() {
+                Some(label) => to_html(&label.citation),
+                None => format!("[{}]", html_escape(&citation.keys.join(", "))),
+            };
+            let first = self.index[&citation.keys[0]];
+            let html = format!(
+                "<span class=\"citation\" id=\"cite-{first}\" data-cites=\"{data_cites}\"><a href=\"#ref-{first}\">{label}</a></span>",
+                first = first,
+                data_cites = html_escape(&citation.keys.join(",")),
+                label = label,
+            );
+            out[citation.event].0 = Event::Html(CowStr::Boxed(html.into_boxed_str()));
+        }
 
-        let first = numbers[0];
-        let data_cites = keys.join(",");
-        format!(
-            "<sup class=\"citation\" id=\"cite-{first}\" data-cites=\"{data_cites}\"><a href=\"#ref-{first}\">[{label}]</a></sup>",
-            first = first,
-            data_cites = html_escape(&data_cites),
-            label = label,
-        )
+        // Entries in the style's order, then keys missing from the bibliography
+        let mut references: Vec<CitationRef> = rendered
+            .bibliography
+            .map(|bibliography| bibliography.items)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|item| {
+                let number = *self.index.get(&item.key)?;
+                let mut html = String::new();
+                if let Some(field) = item.first_field {
+                    html.push_str(&format!(
+                        r#"<span class="ref-label">{}</span> "#,
+                        to_html(&ElemChildren(vec![field]))
+                    ));
+                }
+                html.push_str(&to_html(&item.content));
+                Some(CitationRef {
+                    key: item.key,
+                    number,
+                    html: Some(html),
+                })
+            })
+            .collect();
+        for key in &self.order {
+            if self.ctx.bibliography.get(key).is_none() {
+                references.push(CitationRef {
+                    key: key.clone(),
+                    number: self.index[key],
+                    html: None,
+                });
+            }
+        }
+        references
     }
 
     fn register(&mut self, key: &str) -> usize {
@@ -167,14 +277,13 @@ pub fn render_references(references: &[CitationRef]) -> Option<String> {
     }
 
     let mut html = String::from(
-        r#"<section class="references"><h3>References</h3><ol class="reference-list">"#,
+        r#"<section class="references"><h3>References</h3><ul class="reference-list">"#,
     );
     for cite in references {
         html.push_str(&format!(r#"<li id="ref-{}">"#, cite.number));
         let body = cite
-            .entry
-            .as_ref()
-            .map(|entry| format_entry(entry))
+            .html
+            .clone()
             .unwrap_or_else(|| format!("Missing entry: {}", html_escape(&cite.key)));
         html.push_str(&body);
         html.push_str(&format!(
@@ -183,84 +292,35 @@ pub fn render_references(references: &[CitationRef]) -> Option<String> {
         ));
         html.push_str("</li>");
     }
-    html.push_str("</ol></section>");
+    html.push_str("</ul></section>");
 
     Some(html)
 }
 
-fn format_entry(entry: &Entry) -> String {
-    let mut parts: Vec<String> = Vec::new();
-
-    if let Some(authors) = entry.authors() {
-        if !authors.is_empty() {
-            parts.push(format_authors(authors));
-        }
-    } else if let Some(editors) = entry.editors() {
-        if !editors.is_empty() {
-            parts.push(format_authors(editors));
-        }
-    }
-
-    if let Some(date) = entry.date() {
-        parts.push(format!("({})", date));
-    }
-
-    if let Some(title) = entry.title() {
-        parts.push(format!(
-            r#"<span class="ref-title">{}</span>"#,
-            html_escape(&title.to_string())
-        ));
-    }
-
-    if let Some(parent) = entry.parents().first().and_then(|p| p.title()) {
-        parts.push(format!(r#"<em>{}</em>"#, html_escape(&parent.to_string())));
-    }
-
-    if let Some(publisher) = entry.publisher() {
-        if let Some(name) = publisher.name() {
-            parts.push(html_escape(&name.to_string()));
-        }
-        if let Some(location) = publisher.location() {
-            parts.push(html_escape(&location.to_string()));
-        }
-    }
-
-    if let Some(url) = entry.url() {
-        let escaped = html_escape(&url.to_string());
-        parts.push(format!(r#"<a href="{0}">{0}</a>"#, escaped));
-    } else if let Some(serial) = entry.serial_number() {
-        if let Some(doi) = serial.0.get("doi") {
-            let escaped = html_escape(doi);
-            parts.push(format!(
-                r#"doi: <a href="https://doi.org/{0}">{0}</a>"#,
-                escaped
-            ));
+/// Style output as HTML. hayagriva writes text verbatim, so entry fields
+/// are escaped first.
+fn to_html(children: &ElemChildren) -> String {
+    fn escape(children: &mut ElemChildren) {
+        for child in &mut children.0 {
+            match child {
+                ElemChild::Text(formatted) => formatted.text = html_escape(&formatted.text),
+                ElemChild::Elem(elem) => escape(&mut elem.children),
+                ElemChild::Markup(markup) => *markup = html_escape(markup),
+                ElemChild::Link { text, url } => {
+                    text.text = html_escape(&text.text);
+                    *url = html_escape(url);
+                }
+                ElemChild::Transparent { .. } => {}
+            }
         }
     }
 
-    if parts.is_empty() {
-        html_escape(entry.key())
-    } else {
-        parts.join(". ")
-    }
-}
-
-fn format_authors(authors: &[Person]) -> String {
-    let names: Vec<String> = authors
-        .iter()
-        .map(|p| html_escape(&p.name_first(true, false)))
-        .collect();
-
-    match names.len() {
-        0 => String::new(),
-        1 => names[0].clone(),
-        2 => format!("{} & {}", names[0], names[1]),
-        _ => {
-            let mut out = names[..names.len() - 1].join(", ");
-            let _ = write!(&mut out, ", & {}", names.last().unwrap());
-            out
-        }
-    }
+    let mut escaped = children.clone();
+    escape(&mut escaped);
+    let mut html = String::new();
+    // Writing to a String can't fail
+    let _ = escaped.write_buf(&mut html, BufWriteFormat::Html);
+    html
 }
 
 fn html_escape(text: &str) -> String {
@@ -1,6 +1,15 @@
 //! Math delimiter transformer to normalize math blocks before rendering.
 
+use super::nota_blocks::NotaLabel;
+use super::source_map::{strip_spans, sub_range, SpannedEvent};
+use once_cell::sync::Lazy;
 use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use regex::Regex;
+use std::ops::Range;
+
+static LABEL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\label\{([^}]*)\}").unwrap());
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\tag\*?\{([^}]*)\}").unwrap());
+static NOTAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\(?:notag|nonumber)\b").unwrap());
 
 /// Transformer to improve math rendering
 pub struct MathTransformer;
@@ -14,13 +23,20 @@ impl MathTransformer {
     ///
     /// Detects $$ blocks and ensures they're in their own paragraphs
     pub fn transform(&self, events: Vec<Event<'_>>) -> Vec<Event<'static>> {
+        let events = events.into_iter().map(|event| (event, 0..0)).collect();
+        strip_spans(self.transform_spanned(events))
+    }
+
+    /// Like `transform`, but keeps each event's source range; math events
+    /// span from their opening to their closing delimiter
+    pub fn transform_spanned(&self, events: Vec<(Event<'_>, Range<usize>)>) -> Vec<SpannedEvent> {
         // First pass: convert events to static and find math delimiters
-        let mut static_events: Vec<Event<'static>> = Vec::new();
+        let mut static_events: Vec<SpannedEvent> = Vec::new();
         let mut open: Option<OpenMath> = None;
         let mut in_code_block = false;
         let mut in_inline_code = false;
 
-        for event in events {
+        for (event, range) in events {
             let owned = self.event_into_static(event);
 
             // Track code blocks and inline code to skip math processing
@@ -33,7 +49,7 @@ impl MathTransformer {
 
             // Skip math processing inside code blocks or inline code
             if in_code_block || in_inline_code {
-                static_events.push(owned);
+                static_events.push((owned, range));
                 continue;
             }
 
@@ -44,21 +60,20 @@ impl MathTransformer {
                         let text_str = text.into_string();
                         if let Some(close_idx) = text_str.find(state.close) {
                             // Found the closer!
+                            let close_end = close_idx + state.close.len();
+                            let end = sub_range(&range, text_str.len(), 0, close_end).end;
                             state.content.push_str(&text_str[..close_idx]);
-                            static_events.push(match state.kind {
-                                DelimKind::Display => Event::DisplayMath(CowStr::Boxed(
-                                    state.content.into_boxed_str(),
-                                )),
-                                DelimKind::Inline => {
-                                    Event::InlineMath(CowStr::Boxed(state.content.into_boxed_str()))
-                                }
-                            });
+                            static_events
+                                .push((math_event(state.kind, state.content), state.start..end));
 
                             // Process any remaining text after the closer
-                            let remaining = text_str[close_idx + state.close.len()..].to_string();
+                            let remaining = text_str[close_end..].to_string();
                             if !remaining.is_empty() {
+                                let rest_range =
+                                    sub_range(&range, text_str.len(), close_end, text_str.len());
                                 self.process_text_for_math(
                                     remaining,
+                                    rest_range,
                                     &mut static_events,
                                     &mut open,
                                 );
@@ -66,23 +81,29 @@ impl MathTransformer {
                         } else {
                             // Closer not found, accumulate content
                             state.content.push_str(&text_str);
+                            state.end = range.end;
                             open = Some(state);
                         }
                     }
                     Event::SoftBreak => {
                         state.content.push('\n');
+                        state.end = range.end;
                         open = Some(state);
                     }
                     Event::HardBreak => {
                         state.content.push_str("\n\n");
+                        state.end = range.end;
                         open = Some(state);
                     }
                     other => {
                         // Unexpected event while in math mode - emit accumulated content as literal and the event
-                        static_events.push(Event::Text(CowStr::Boxed(
-                            format!("{}{}", state.open, state.content).into_boxed_str(),
-                        )));
-                        static_events.push(other);
+                        static_events.push((
+                            Event::Text(CowStr::Boxed(
+                                format!("{}{}", state.open, state.content).into_boxed_str(),
+                            )),
+                            state.start..state.end,
+                        ));
+                        static_events.push((other, range));
                     }
                 }
             } else {
@@ -91,20 +112,24 @@ impl MathTransformer {
                     Event::Text(text) => {
                         self.process_text_for_math(
                             text.into_string(),
+                            range,
                             &mut static_events,
                             &mut open,
                         );
                     }
-                    other => static_events.push(other),
+                    other => static_events.push((other, range)),
                 }
             }
         }
 
         // If we still have an open math delimiter at the end, emit it as literal text
         if let Some(state) = open {
-            static_events.push(Event::Text(CowStr::Boxed(
-                format!("{}{}", state.open, state.content).into_boxed_str(),
-            )));
+            static_events.push((
+                Event::Text(CowStr::Boxed(
+                    format!("{}{}", state.open, state.content).into_boxed_str(),
+                )),
+                state.start..state.end,
+            ));
         }
 
         static_events
@@ -112,20 +137,18 @@ impl MathTransformer {
 
     /// Second-pass transform to unwrap paragraphs containing display math
     /// This must run AFTER math rendering (which converts DisplayMath to Html)
-    pub fn unwrap_display_math_paragraphs(
-        &self,
-        events: Vec<Event<'static>>,
-    ) -> Vec<Event<'static>> {
+    pub fn unwrap_display_math_paragraphs(&self, events: Vec<SpannedEvent>) -> Vec<SpannedEvent> {
         let mut result = Vec::new();
         let mut i = 0;
 
         while i < events.len() {
             match &events[i] {
-                Event::Start(Tag::Paragraph) => {
+                (Event::Start(Tag::Paragraph), paragraph_range) => {
+                    let paragraph_range = paragraph_range.clone();
                     i += 1;
-                    let mut paragraph: Vec<Event<'static>> = Vec::new();
+                    let mut paragraph: Vec<SpannedEvent> = Vec::new();
                     while i < events.len() {
-                        if matches!(events[i], Event::End(TagEnd::Paragraph)) {
+                        if matches!(events[i].0, Event::End(TagEnd::Paragraph)) {
                             break;
                         }
                         paragraph.push(events[i].clone());
@@ -137,7 +160,7 @@ impl MathTransformer {
                         let mut buffer = Vec::new();
                         for ev in paragraph {
                             // Check if this is rendered display math HTML
-                            let is_display_math = match &ev {
+                            let is_display_math = match &ev.0 {
                                 Event::Html(html) => html.contains("math-display"),
                                 Event::InlineHtml(html) => html.contains("math-display"),
                                 _ => false,
@@ -145,9 +168,15 @@ impl MathTransformer {
 
                             if is_display_math {
                                 if !buffer.is_empty() {
-                                    result.push(Event::Start(Tag::Paragraph));
+                                    result.push((
+                                        Event::Start(Tag::Paragraph),
+                                        paragraph_range.clone(),
+                                    ));
                                     result.append(&mut buffer);
-                                    result.push(Event::End(TagEnd::Paragraph));
+                                    result.push((
+                                        Event::End(TagEnd::Paragraph),
+                                        paragraph_range.clone(),
+                                    ));
                                 }
                                 result.push(ev);
                             } else {
@@ -155,12 +184,12 @@ impl MathTransformer {
                             }
                         }
                         if !buffer.is_empty() {
-                            result.push(Event::Start(Tag::Paragraph));
+                            result.push((Event::Start(Tag::Paragraph), paragraph_range.clone()));
                             result.append(&mut buffer);
-                            result.push(Event::End(TagEnd::Paragraph));
+                            result.push((Event::End(TagEnd::Paragraph), paragraph_range.clone()));
                         }
                     } else {
-                        result.push(Event::Start(Tag::Paragraph));
+                        result.push((Event::Start(Tag::Paragraph), paragraph_range));
                         result.extend(paragraph);
                         if i < events.len() {
                             result.push(events[i].clone()); // End paragraph
@@ -182,8 +211,8 @@ impl MathTransformer {
         result
     }
 
-    fn paragraph_has_rendered_display_math(&self, events: &[Event<'static>]) -> bool {
-        events.iter().any(|event| {
+    fn paragraph_has_rendered_display_math(&self, events: &[SpannedEvent]) -> bool {
+        events.iter().any(|(event, _)| {
             matches!(event, Event::Html(html) if html.contains("math-display"))
                 || matches!(event, Event::InlineHtml(html) if html.contains("math-display"))
         })
@@ -191,32 +220,37 @@ impl MathTransformer {
 
     fn process_text_for_math(
         &self,
-        mut text: String,
-        out: &mut Vec<Event<'static>>,
+        text: String,
+        range: Range<usize>,
+        out: &mut Vec<SpannedEvent>,
         open: &mut Option<OpenMath>,
     ) {
-        while !text.is_empty() {
-            if let Some((kind, start_idx, open_pat, close_pat)) = find_next_delimiter(&text) {
+        let len = text.len();
+        let mut pos = 0;
+
+        while pos < len {
+            let rest = &text[pos..];
+            if let Some((kind, start_idx, open_pat, close_pat)) = find_next_delimiter(rest) {
                 // Emit any text before the delimiter
                 if start_idx > 0 {
-                    out.push(Event::Text(CowStr::Boxed(
-                        text[..start_idx].to_string().into_boxed_str(),
-                    )));
+                    out.push((
+                        Event::Text(CowStr::Boxed(
+                            rest[..start_idx].to_string().into_boxed_str(),
+                        )),
+                        sub_range(&range, len, pos, pos + start_idx),
+                    ));
                 }
 
-                let after_start = &text[start_idx + open_pat.len()..];
+                let content_start = pos + start_idx + open_pat.len();
+                let after_start = &text[content_start..];
                 if let Some(end_idx) = after_start.find(close_pat) {
                     // Found the closer in the same text - emit math event
-                    let math_content = &after_start[..end_idx];
-                    out.push(match kind {
-                        DelimKind::Display => Event::DisplayMath(CowStr::Boxed(
-                            math_content.to_string().into_boxed_str(),
-                        )),
-                        DelimKind::Inline => Event::InlineMath(CowStr::Boxed(
-                            math_content.to_string().into_boxed_str(),
-                        )),
-                    });
-                    text = after_start[end_idx + close_pat.len()..].to_string();
+                    let math_end = content_start + end_idx + close_pat.len();
+                    out.push((
+                        math_event(kind, after_start[..end_idx].to_string()),
+                        sub_range(&range, len, pos + start_idx, math_end),
+                    ));
+                    pos = math_end;
                 } else {
                     // No closer found in this text - set open state and return
                     *open = Some(OpenMath {
@@ -224,12 +258,17 @@ impl MathTransformer {
                         close: close_pat,
                         open: open_pat,
                         content: after_start.to_string(),
+                        start: sub_range(&range, len, pos + start_idx, len).start,
+                        end: range.end,
                     });
                     return;
                 }
             } else {
                 // No delimiter found - emit remaining text
-                out.push(Event::Text(CowStr::Boxed(text.into_boxed_str())));
+                out.push((
+                    Event::Text(CowStr::Boxed(rest.to_string().into_boxed_str())),
+                    sub_range(&range, len, pos, len),
+                ));
                 break;
             }
         }
@@ -353,6 +392,16 @@ struct OpenMath {
     close: &'static str,
     open: &'static str,
     content: String,
+    /// Source offsets of the opening delimiter and the last consumed event
+    start: usize,
+    end: usize,
+}
+
+fn math_event(kind: DelimKind, content: String) -> Event<'static> {
+    match kind {
+        DelimKind::Display => Event::DisplayMath(CowStr::Boxed(content.into_boxed_str())),
+        DelimKind::Inline => Event::InlineMath(CowStr::Boxed(content.into_boxed_str())),
+    }
 }
 
 fn find_next_delimiter(input: &str) -> Option<(DelimKind, usize, &'static str, &'static str)> {
@@ -398,6 +447,77 @@ fn find_next_delimiter(input: &str) -> Option<(DelimKind, usize, &'static str, &
     best
 }
 
+/// A display equation carrying a `\label{...}`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LabelledEquation {
+    pub label: String,
+    /// Number given explicitly with `\tag{...}`
+    pub tag: Option<String>,
+    /// `false` when marked `\notag` / `\nonumber`
+    pub numbered: bool,
+    /// Further `\label`s (say, on other rows of an `align`), which are not
+    /// numbered
+    pub extra_labels: Vec<String>,
+    /// The LaTeX with the labels, tag and `\notag` removed
+    pub latex: String,
+}
+
+/// The label of a display equation, if it has one
+pub(crate) fn labelled_equation(math: &str) -> Option<LabelledEquation> {
+    let mut labels = LABEL_RE
+        .captures_iter(math)
+        .map(|caps| caps[1].trim().to_string());
+    let label = labels.next()?;
+    if label.is_empty() {
+        return None;
+    }
+    let extra_labels = labels.collect();
+    let tag = TAG_RE.captures(math).map(|caps| caps[1].trim().to_string());
+    let numbered = !NOTAG_RE.is_match(math);
+
+    let latex = LABEL_RE.replace_all(math, "");
+    let latex = TAG_RE.replace(&latex, "");
+    let latex = NOTAG_RE.replace_all(&latex, "");
+    Some(LabelledEquation {
+        label,
+        tag,
+        numbered,
+        extra_labels,
+        latex: latex.into_owned(),
+    })
+}
+
+/// Number labelled display equations in document order. `\tag{...}` sets
+/// a number explicitly and `\notag` leaves an equation unnumbered; neither
+/// advances the counter.
+pub fn number_equations(events: &[SpannedEvent]) -> Vec<NotaLabel> {
+    let mut count = 0;
+    events
+        .iter()
+        .filter_map(|(event, _)| match event {
+            Event::DisplayMath(math) => labelled_equation(math),
+            _ => None,
+        })
+        .map(|equation| {
+            let number = match (equation.tag, equation.numbered) {
+                (Some(tag), _) => Some(tag),
+                (None, true) => {
+                    count += 1;
+                    Some(count.to_string())
+                }
+                (None, false) => None,
+            };
+            NotaLabel {
+                label: equation.label,
+                kind: "equation".to_string(),
+                number,
+                title: String::new(),
+                text: equation.latex.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
 impl Default for MathTransformer {
     fn default() -> Self {
         Self::new()
@@ -441,4 +561,46 @@ mod tests {
             .any(|e| matches!(e, Event::InlineMath(_)));
         assert!(has_inline_math, "Should contain InlineMath event");
     }
+
+    #[test]
+    fn test_equations_are_numbered_by_label() {
+        let markdown = "$$ E = mc^2 \\label{eq:energy} $$\n\n$$ a = b $$\n\n\
+                        $$ x \\label{eq:x} \\notag $$\n\n$$ y \\tag{*} \\label{eq:y} $$\n\n\
+                        $$ z \\label{eq:z} $$";
+        let events: Vec<_> = Parser::new_ext(markdown, Options::empty())
+            .into_offset_iter()
+            .collect();
+        let events = MathTransformer::new().transform_spanned(events);
+
+        let equations = number_equations(&events);
+        let numbers: Vec<_> = equations
+            .iter()
+            .map(|eq| (eq.label.as_str(), eq.number.as_deref()))
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![
+                ("eq:energy", Some("1")),
+                ("eq:x", None),
+                ("eq:y", Some("*")),
+                ("eq:z", Some("2")),
+            ]
+        );
+        assert_eq!(equations[0].text, "E = mc^2");
+    }
+
+    #[test]
+    fn test_math_spans_cover_delimiters() {
+        let markdown = "Test $x + y$ here";
+        let events: Vec<_> = Parser::new_ext(markdown, Options::empty())
+            .into_offset_iter()
+            .collect();
+
+        let transformed = MathTransformer::new().transform_spanned(events);
+        let (_, range) = transformed
+            .iter()
+            .find(|(e, _)| matches!(e, Event::InlineMath(_)))
+            .expect("inline math");
+        assert_eq!(&markdown[range.clone()], "$x + y$");
+    }
 }
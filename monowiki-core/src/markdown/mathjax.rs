@@ -1,31 +1,471 @@
-//! MathJax-compatible math rendering.
+//! Math rendering.
 //!
-//! Converts InlineMath and DisplayMath events to HTML that MathJax can process client-side.
+//! Converts InlineMath and DisplayMath events to HTML: either LaTeX wrapped
+//! for MathJax to typeset client-side, or MathML produced at build time
+//! (see `math.render`), falling back to MathJax for unsupported constructs.
+//! Macros from `math.macros` are expanded for MathML, and math is checked
+//! for unbalanced braces and environments and macros missing arguments.
 
-use crate::models::Diagnostic;
+use super::math::{labelled_equation, LabelledEquation};
+use super::mathml::{latex_to_mathml, Unsupported};
+use super::nota_blocks::NotaLabel;
+use super::source_map::{SourceMap, SpannedEvent};
+use crate::config::{MathMacros, MathRender};
+use crate::models::{Diagnostic, DiagnosticSeverity};
 use pulldown_cmark::{CowStr, Event};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
-/// Render math events into MathJax-compatible HTML.
-///
-/// Wraps LaTeX in delimiters that MathJax will process client-side.
-pub fn render_math_for_mathjax(events: Vec<Event<'static>>) -> (Vec<Event<'static>>, Vec<Diagnostic>) {
-    let events = events
-        .into_iter()
-        .map(|event| match event {
-            Event::InlineMath(math) => {
-                let html = wrap_inline_math(&math);
-                Event::InlineHtml(CowStr::Boxed(html.into_boxed_str()))
-            }
-            Event::DisplayMath(math) => {
-                let html = wrap_display_math(&math);
-                Event::Html(CowStr::Boxed(html.into_boxed_str()))
+/// Expansion rounds before macros are assumed to recurse forever
+const MAX_EXPANSIONS: usize = 32;
+
+/// Renders math events into HTML
+pub struct MathRenderer<'a> {
+    render: MathRender,
+    note_slug: Option<String>,
+    source_path: Option<String>,
+    source_map: Option<&'a SourceMap<'a>>,
+    macros: HashMap<&'a str, Macro<'a>>,
+    /// Ids already taken by nota blocks
+    block_labels: &'a [NotaLabel],
+}
+
+impl<'a> MathRenderer<'a> {
+    pub fn new(render: MathRender, note_slug: Option<String>, source_path: Option<String>) -> Self {
+        Self {
+            render,
+            note_slug,
+            source_path,
+            source_map: None,
+            macros: HashMap::new(),
+            block_labels: &[],
+        }
+    }
+
+    /// Labelled nota blocks of the note, whose ids equations must not reuse
+    pub fn with_block_labels(mut self, labels: &'a [NotaLabel]) -> Self {
+        self.block_labels = labels;
+        self
+    }
+
+    /// Expand and check uses of `macros` (names without the leading `\`, as
+    /// `MathConfig::macros_for` returns them)
+    pub fn with_macros(mut self, macros: &'a MathMacros) -> Self {
+        self.macros = macros
+            .iter()
+            .map(|(name, body)| (name.as_str(), Macro::new(body)))
+            .collect();
+        self
+    }
+
+    /// Resolve event ranges to file positions for diagnostics
+    pub fn with_source_map(mut self, source_map: &'a SourceMap<'a>) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// Render math events, reporting math that falls back to MathJax.
+    ///
+    /// Labelled display equations get an anchored container and the number
+    /// `equations` (from `number_equations`) gives them. Labels already
+    /// taken, and labels after the first in one equation, are reported.
+    pub fn render(
+        &self,
+        events: Vec<SpannedEvent>,
+        equations: &[NotaLabel],
+    ) -> (Vec<SpannedEvent>, Vec<Diagnostic>) {
+        let mut equations = equations.iter();
+        let mut diagnostics = Vec::new();
+        let mut taken: HashSet<&str> = self
+            .block_labels
+            .iter()
+            .map(|label| label.label.as_str())
+            .collect();
+        let events = events
+            .into_iter()
+            .map(|(event, range)| {
+                let event = match event {
+                    Event::InlineMath(math) => {
+                        let html = self.render_math(&math, false, &range, &mut diagnostics);
+                        Event::InlineHtml(CowStr::Boxed(html.into_boxed_str()))
+                    }
+                    Event::DisplayMath(math) => {
+                        let html = match labelled_equation(&math)
+                            .and_then(|eq| Some((eq, equations.next()?)))
+                        {
+                            Some((equation, label)) => {
+                                let id = self.equation_id(
+                                    &equation,
+                                    label,
+                                    &mut taken,
+                                    &range,
+                                    &mut diagnostics,
+                                );
+                                wrap_equation(
+                                    &self.render_math(
+                                        &equation.latex,
+                                        true,
+                                        &range,
+                                        &mut diagnostics,
+                                    ),
+                                    id,
+                                    label.number.as_deref(),
+                                )
+                            }
+                            None => self.render_math(&math, true, &range, &mut diagnostics),
+                        };
+                        Event::Html(CowStr::Boxed(html.into_boxed_str()))
+                    }
+                    other => other,
+                };
+                (event, range)
+            })
+            .collect();
+
+        (events, diagnostics)
+    }
+
+    /// The id of a labelled equation, `None` when another block or equation
+    /// already has it. Reports that and any labels after the first.
+    fn equation_id<'l>(
+        &self,
+        equation: &LabelledEquation,
+        label: &'l NotaLabel,
+        taken: &mut HashSet<&'l str>,
+        range: &Range<usize>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<&'l str> {
+        for extra in &equation.extra_labels {
+            diagnostics.push(self.diagnostic(
+                "math.extra_label",
+                format!(
+                    "Only the first \\label of an equation is numbered; '{}' is ignored",
+                    extra
+                ),
+                DiagnosticSeverity::Warning,
+                &equation.latex,
+                range,
+            ));
+        }
+        if taken.insert(&label.label) {
+            return Some(&label.label);
+        }
+        diagnostics.push(self.diagnostic(
+            "math.duplicate_label",
+            format!("Label '{}' is already used in this note", label.label),
+            DiagnosticSeverity::Warning,
+            &equation.latex,
+            range,
+        ));
+        None
+    }
+
+    fn render_math(
+        &self,
+        math: &str,
+        display: bool,
+        range: &Range<usize>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> String {
+        let problems = check_math(math, &self.macros);
+        let valid = problems.is_empty();
+        for (code, message) in problems {
+            diagnostics.push(self.diagnostic(
+                code,
+                message,
+                DiagnosticSeverity::Warning,
+                math,
+                range,
+            ));
+        }
+
+        // Malformed math is already reported, so it goes to MathJax quietly
+        if self.render == MathRender::Mathml && valid {
+            match expand_macros(math, &self.macros)
+                .and_then(|latex| latex_to_mathml(&latex, display))
+            {
+                Ok(mathml) if display => {
+                    return format!(r#"<div class="math math-display">{}</div>"#, mathml)
+                }
+                Ok(mathml) => {
+                    return format!(r#"<span class="math math-inline">{}</span>"#, mathml)
+                }
+                Err(unsupported) => diagnostics.push(self.diagnostic(
+                    "math.fallback",
+                    format!("Math left to MathJax: unsupported {}", unsupported),
+                    DiagnosticSeverity::Info,
+                    math,
+                    range,
+                )),
             }
-            other => other,
+        }
+        if display {
+            wrap_display_math(math)
+        } else {
+            wrap_inline_math(math)
+        }
+    }
+
+    fn diagnostic(
+        &self,
+        code: &str,
+        message: String,
+        severity: DiagnosticSeverity,
+        math: &str,
+        range: &Range<usize>,
+    ) -> Diagnostic {
+        Diagnostic {
+            code: code.to_string(),
+            message,
+            severity,
+            note_slug: self.note_slug.clone(),
+            source_path: self.source_path.clone(),
+            context: Some(math.trim().to_string()),
+            anchor: None,
+            span: self.source_map.map(|map| map.span(range)),
+        }
+    }
+}
+
+/// Macro definitions as MathJax's `tex.macros` option expects them: a body,
+/// or `[body, arity]` for macros with arguments
+pub fn mathjax_macros(macros: &MathMacros) -> String {
+    let object = macros
+        .iter()
+        .map(|(name, body)| {
+            let value = match Macro::new(body).arity {
+                0 => json!(body),
+                arity => json!([body, arity]),
+            };
+            (name.clone(), value)
         })
         .collect();
+    // Safe to inline in a <script> element
+    Value::Object(object).to_string().replace("</", "<\\/")
+}
 
-    // MathJax handles rendering client-side, so we don't generate diagnostics here
-    (events, Vec::new())
+/// A macro body and the number of arguments (`#1`..`#9`) it takes
+#[derive(Debug, Clone, Copy)]
+struct Macro<'a> {
+    body: &'a str,
+    arity: usize,
+}
+
+impl<'a> Macro<'a> {
+    fn new(body: &'a str) -> Self {
+        let arity = body
+            .as_bytes()
+            .windows(2)
+            .filter(|pair| pair[0] == b'#' && pair[1].is_ascii_digit())
+            .map(|pair| usize::from(pair[1] - b'0'))
+            .max()
+            .unwrap_or(0);
+        Self { body, arity }
+    }
+
+    /// The body with `#n` replaced by the n-th argument
+    fn apply(&self, args: &[&str]) -> String {
+        let mut out = String::with_capacity(self.body.len());
+        let mut chars = self.body.chars().peekable();
+        while let Some(c) = chars.next() {
+            let arg = chars
+                .peek()
+                .and_then(|d| d.to_digit(10))
+                .filter(|_| c == '#')
+                .and_then(|n| args.get((n as usize).checked_sub(1)?));
+            match arg {
+                Some(arg) => {
+                    chars.next();
+                    out.push_str(arg);
+                }
+                None => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+/// A TeX token: a control sequence (`\alpha`, `\{`) or a character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Command(&'a str),
+    Char(char),
+}
+
+/// The token starting at byte `pos` of `tex` and the offset just after it
+fn token_at(tex: &str, pos: usize) -> Option<(Token<'_>, usize)> {
+    let rest = &tex[pos..];
+    let c = rest.chars().next()?;
+    if c != '\\' {
+        return Some((Token::Char(c), pos + c.len_utf8()));
+    }
+    let name = &rest[1..];
+    let len = match name.find(|c: char| !c.is_ascii_alphabetic()) {
+        Some(0) => name.chars().next().map_or(0, char::len_utf8),
+        Some(len) => len,
+        None => name.len(),
+    };
+    Some((Token::Command(&name[..len]), pos + 1 + len))
+}
+
+/// The argument starting at `pos` (a braced group's contents or a single
+/// token) and the offset after it; `None` when the argument is missing
+fn argument(tex: &str, pos: usize) -> Option<(&str, usize)> {
+    let start = pos + (tex[pos..].len() - tex[pos..].trim_start().len());
+    let (token, end) = token_at(tex, start)?;
+    match token {
+        Token::Char('{') => {
+            let mut depth = 1;
+            let mut pos = end;
+            while let Some((token, end)) = token_at(tex, pos) {
+                match token {
+                    Token::Char('{') => depth += 1,
+                    Token::Char('}') => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some((&tex[start + 1..pos], end));
+                        }
+                    }
+                    _ => {}
+                }
+                pos = end;
+            }
+            None
+        }
+        Token::Char('}' | '&' | '^' | '_') | Token::Command("\\" | "end" | "right") => None,
+        _ => Some((&tex[start..end], end)),
+    }
+}
+
+/// Problems MathJax would choke on, as (diagnostic code, message) pairs
+fn check_math(math: &str, macros: &HashMap<&str, Macro<'_>>) -> Vec<(&'static str, String)> {
+    let mut problems = Vec::new();
+    let mut depth = 0usize;
+    let mut stray_close = false;
+    let mut environments: Vec<&str> = Vec::new();
+
+    let mut pos = 0;
+    while let Some((token, end)) = token_at(math, pos) {
+        pos = end;
+        match token {
+            Token::Char('{') => depth += 1,
+            Token::Char('}') => match depth.checked_sub(1) {
+                Some(outer) => depth = outer,
+                None => stray_close = true,
+            },
+            Token::Command(command @ ("begin" | "end")) => {
+                let Some((name, after)) = argument(math, end) else {
+                    continue;
+                };
+                pos = after;
+                if command == "begin" {
+                    environments.push(name);
+                } else {
+                    match environments.pop() {
+                        Some(open) if open == name => {}
+                        Some(open) => problems.push((
+                            "math.unbalanced_environment",
+                            format!("\\begin{{{}}} is closed by \\end{{{}}}", open, name),
+                        )),
+                        None => problems.push((
+                            "math.unbalanced_environment",
+                            format!("\\end{{{}}} has no matching \\begin", name),
+                        )),
+                    }
+                }
+            }
+            Token::Command(name) => {
+                let Some(definition) = macros.get(name).filter(|m| m.arity > 0) else {
+                    continue;
+                };
+                let mut found = 0;
+                let mut next = end;
+                while found < definition.arity {
+                    let Some((_, after)) = argument(math, next) else {
+                        break;
+                    };
+                    found += 1;
+                    next = after;
+                }
+                if found < definition.arity {
+                    problems.push((
+                        "math.macro_arity",
+                        format!(
+                            "\\{} takes {} argument{} but is given {}",
+                            name,
+                            definition.arity,
+                            if definition.arity == 1 { "" } else { "s" },
+                            found
+                        ),
+                    ));
+                }
+            }
+            Token::Char(_) => {}
+        }
+    }
+
+    for open in environments {
+        problems.push((
+            "math.unbalanced_environment",
+            format!("\\begin{{{}}} has no matching \\end", open),
+        ));
+    }
+    if depth > 0 || stray_close {
+        let message = if stray_close {
+            "Unbalanced braces: '}' without a matching '{'".to_string()
+        } else {
+            format!("Unbalanced braces: {} '{{' never closed", depth)
+        };
+        problems.push(("math.unbalanced_braces", message));
+    }
+    problems
+}
+
+/// Replace uses of `macros` with their bodies, repeatedly, so macros can use
+/// other macros
+fn expand_macros(math: &str, macros: &HashMap<&str, Macro<'_>>) -> Result<String, Unsupported> {
+    let mut tex = math.to_string();
+    for _ in 0..MAX_EXPANSIONS {
+        let mut expanded = String::with_capacity(tex.len());
+        let mut changed = false;
+        let mut pos = 0;
+        while let Some((token, end)) = token_at(&tex, pos) {
+            let definition = match token {
+                Token::Command(name) => macros.get(name),
+                Token::Char(_) => None,
+            };
+            let Some(definition) = definition else {
+                expanded.push_str(&tex[pos..end]);
+                pos = end;
+                continue;
+            };
+
+            let mut args = Vec::with_capacity(definition.arity);
+            pos = end;
+            for _ in 0..definition.arity {
+                let (arg, after) = argument(&tex, pos)
+                    .ok_or_else(|| Unsupported("macro without its arguments".to_string()))?;
+                args.push(arg);
+                pos = after;
+            }
+            expanded.push_str(&definition.apply(&args));
+            // Keep a trailing `\cmd` from merging with letters that follow
+            expanded.push(' ');
+            changed = true;
+        }
+        if !changed {
+            return Ok(expanded);
+        }
+        tex = expanded;
+    }
+    Err(Unsupported("recursive macro definitions".to_string()))
+}
+
+/// Whether `html` contains math left for MathJax to typeset
+pub fn needs_mathjax(html: &str) -> bool {
+    html.contains(r#"class="math math-inline" aria-label="#)
+        || html.contains(r#"class="math math-display" aria-label="#)
 }
 
 fn wrap_inline_math(math: &str) -> String {
@@ -44,6 +484,25 @@ fn wrap_display_math(math: &str) -> String {
     )
 }
 
+/// Anchor rendered display math at `id`, with its number alongside
+fn wrap_equation(math_html: &str, id: Option<&str>, number: Option<&str>) -> String {
+    let number = number
+        .map(|n| {
+            format!(
+                r#"<span class="equation-number">({})</span>"#,
+                html_escape(n)
+            )
+        })
+        .unwrap_or_default();
+    let id = id
+        .map(|id| format!(r#" id="{}""#, html_escape(id)))
+        .unwrap_or_default();
+    format!(
+        r#"<div class="math-equation"{}>{}{}</div>"#,
+        id, math_html, number
+    )
+}
+
 fn html_escape(input: &str) -> String {
     input
         .replace('&', "&amp;")
@@ -76,4 +535,71 @@ mod tests {
         let html = wrap_inline_math("x < y & z > w");
         assert!(html.contains("x &lt; y &amp; z &gt; w"));
     }
+
+    fn macros() -> MathMacros {
+        MathMacros::from([
+            ("R".to_string(), r"\mathbb{R}".to_string()),
+            (
+                "norm".to_string(),
+                r"\left\lVert #1 \right\rVert".to_string(),
+            ),
+            ("ip".to_string(), r"\langle #1, #2 \rangle".to_string()),
+        ])
+    }
+
+    fn check(math: &str) -> Vec<(&'static str, String)> {
+        let macros = macros();
+        let renderer = MathRenderer::new(MathRender::Mathjax, None, None).with_macros(&macros);
+        check_math(math, &renderer.macros)
+    }
+
+    #[test]
+    fn test_checks_braces_environments_and_macro_arity() {
+        assert!(check(r"\frac{1}{2} \norm{x} \ip{x}{y} \R^n \{ a \}").is_empty());
+        assert!(check(r"\begin{pmatrix} a \\ b \end{pmatrix}").is_empty());
+
+        assert_eq!(check(r"\frac{1}{2")[0].0, "math.unbalanced_braces");
+        assert_eq!(check(r"x}")[0].0, "math.unbalanced_braces");
+        assert_eq!(
+            check(r"\begin{pmatrix} a \end{bmatrix}"),
+            vec![(
+                "math.unbalanced_environment",
+                r"\begin{pmatrix} is closed by \end{bmatrix}".to_string()
+            )]
+        );
+        assert_eq!(
+            check(r"\begin{cases} a")[0].0,
+            "math.unbalanced_environment"
+        );
+        assert_eq!(
+            check(r"\ip{x}"),
+            vec![(
+                "math.macro_arity",
+                r"\ip takes 2 arguments but is given 1".to_string()
+            )]
+        );
+        assert_eq!(check(r"{\norm}")[0].0, "math.macro_arity");
+    }
+
+    #[test]
+    fn test_expands_macros_for_mathml() {
+        let macros = macros();
+        let renderer = MathRenderer::new(MathRender::Mathml, None, None).with_macros(&macros);
+        assert_eq!(
+            expand_macros(r"\norm{v}^2 \in \R", &renderer.macros).unwrap(),
+            r"\left\lVert v \right\rVert ^2 \in \mathbb{R} "
+        );
+
+        let looping = MathMacros::from([("a".to_string(), r"\a".to_string())]);
+        let renderer = MathRenderer::new(MathRender::Mathml, None, None).with_macros(&looping);
+        assert!(expand_macros(r"\a", &renderer.macros).is_err());
+    }
+
+    #[test]
+    fn test_mathjax_macros_config() {
+        assert_eq!(
+            mathjax_macros(&macros()),
+            r#"{"R":"\\mathbb{R}","ip":["\\langle #1, #2 \\rangle",2],"norm":["\\left\\lVert #1 \\right\\rVert",1]}"#
+        );
+    }
 }
@@ -0,0 +1,1056 @@
+//! LaTeX to MathML conversion for build-time math rendering.
+//!
+//! Covers the everyday subset of TeX math: symbols and Greek letters,
+//! sub/superscripts, fractions, roots, accents, font commands, `\text`,
+//! `\left`/`\right` delimiters, big operators with limits and the matrix,
+//! `cases` and alignment environments. Anything else is reported as
+//! `Unsupported` so callers can fall back to client-side MathJax.
+
+use std::fmt;
+
+/// A construct the converter does not handle (e.g. `\newcommand`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported(pub String);
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Convert LaTeX math to a `<math>` element, keeping the source as a TeX
+/// annotation
+pub fn latex_to_mathml(latex: &str, display: bool) -> Result<String, Unsupported> {
+    let mut parser = Parser {
+        src: latex,
+        pos: 0,
+        display,
+        font: None,
+    };
+    let items = parser.parse_row()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(Unsupported(match c {
+            '}' => "unbalanced braces".to_string(),
+            '&' => "'&' outside an environment".to_string(),
+            _ => parser.rest().chars().take(12).collect(),
+        }));
+    }
+
+    Ok(format!(
+        r#"<math display="{}"><semantics>{}<annotation encoding="application/x-tex">{}</annotation></semantics></math>"#,
+        if display { "block" } else { "inline" },
+        mrow(items),
+        escape(latex.trim())
+    ))
+}
+
+/// Alphabets selected by `\mathbb`, `\mathbf`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Roman,
+    Bold,
+    Italic,
+    BoldItalic,
+    Script,
+    Fraktur,
+    DoubleStruck,
+    SansSerif,
+    Monospace,
+}
+
+/// How a command renders on its own
+enum Symbol {
+    /// Identifier (`<mi>`); upright when `true`
+    Ident(&'static str, bool),
+    Op(&'static str),
+    /// Large operator; `true` when its scripts go above/below in display
+    LargeOp(&'static str, bool),
+    /// Function name like `sin`; `true` when it takes limits (`lim`)
+    Func(&'static str, bool),
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    display: bool,
+    font: Option<Font>,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Name of the command at the cursor (which is just past a `\`)
+    fn read_command(&mut self) -> Result<String, Unsupported> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        if len > 0 {
+            self.pos += len;
+            return Ok(rest[..len].to_string());
+        }
+        match self.bump() {
+            Some(c) => Ok(c.to_string()),
+            None => Err(Unsupported("trailing '\\'".to_string())),
+        }
+    }
+
+    /// Whether the input continues with `\name` (not followed by a letter)
+    fn at_command(&self, name: &str) -> bool {
+        self.rest()
+            .strip_prefix('\\')
+            .and_then(|rest| rest.strip_prefix(name))
+            .is_some_and(|after| !after.starts_with(|c: char| c.is_ascii_alphabetic()))
+    }
+
+    /// Whether the row being parsed ends here
+    fn at_row_end(&self) -> bool {
+        match self.peek() {
+            None | Some('}') | Some('&') => true,
+            Some('\\') => {
+                self.rest().starts_with("\\\\")
+                    || self.at_command("end")
+                    || self.at_command("right")
+            }
+            _ => false,
+        }
+    }
+
+    /// Atoms (with their scripts) up to the end of the row
+    fn parse_row(&mut self) -> Result<Vec<String>, Unsupported> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.at_row_end() {
+                return Ok(items);
+            }
+            if let Some(item) = self.parse_scripted()? {
+                items.push(item);
+            }
+        }
+    }
+
+    /// An atom followed by any `^`, `_` and `'` scripts
+    fn parse_scripted(&mut self) -> Result<Option<String>, Unsupported> {
+        let start = self.pos;
+        let Some((base, mut limits)) = self.parse_atom()? else {
+            return Ok(None);
+        };
+
+        let mut sub = None;
+        let mut sup = None;
+        let mut primes = 0;
+        loop {
+            self.skip_whitespace();
+            if self.at_command("limits") {
+                self.pos += "\\limits".len();
+                limits = true;
+                continue;
+            }
+            if self.at_command("nolimits") {
+                self.pos += "\\nolimits".len();
+                limits = false;
+                continue;
+            }
+            match self.peek() {
+                Some('_') if sub.is_none() => {
+                    self.bump();
+                    sub = Some(self.parse_argument()?);
+                }
+                Some('^') if sup.is_none() => {
+                    self.bump();
+                    sup = Some(self.parse_argument()?);
+                }
+                Some('\'') if sup.is_none() => {
+                    self.bump();
+                    primes += 1;
+                }
+                Some('_' | '^') => {
+                    let snippet = &self.src[start..self.pos + 1];
+                    return Err(Unsupported(format!("double script in '{}'", snippet)));
+                }
+                _ => break,
+            }
+        }
+        if primes > 0 {
+            let mark = ["′", "″", "‴"][primes.min(3) - 1];
+            sup = Some(format!("<mo>{mark}</mo>"));
+        }
+
+        let under = limits && self.display;
+        Ok(Some(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) if under => format!("<munder>{base}{sub}</munder>"),
+            (None, Some(sup)) if under => format!("<mover>{base}{sup}</mover>"),
+            (Some(sub), Some(sup)) if under => {
+                format!("<munderover>{base}{sub}{sup}</munderover>")
+            }
+            (Some(sub), None) => format!("<msub>{base}{sub}</msub>"),
+            (None, Some(sup)) => format!("<msup>{base}{sup}</msup>"),
+            (Some(sub), Some(sup)) => format!("<msubsup>{base}{sub}{sup}</msubsup>"),
+        }))
+    }
+
+    /// A script or command argument: a braced group or a single token
+    fn parse_argument(&mut self) -> Result<String, Unsupported> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(Unsupported("missing argument".to_string())),
+            Some('{') => self.parse_group(),
+            _ => match self.parse_atom()? {
+                Some((atom, _)) => Ok(atom),
+                None => Ok("<mrow></mrow>".to_string()),
+            },
+        }
+    }
+
+    /// `{...}` as a single `<mrow>`
+    fn parse_group(&mut self) -> Result<String, Unsupported> {
+        self.skip_whitespace();
+        if self.bump() != Some('{') {
+            return Err(Unsupported("expected '{'".to_string()));
+        }
+        let items = self.parse_row()?;
+        if self.bump() != Some('}') {
+            return Err(Unsupported("unbalanced braces".to_string()));
+        }
+        Ok(mrow(items))
+    }
+
+    /// Raw text of a braced argument (for `\text`, `\begin`, ...)
+    fn parse_raw_group(&mut self) -> Result<String, Unsupported> {
+        self.skip_whitespace();
+        if self.bump() != Some('{') {
+            return Err(Unsupported("expected '{'".to_string()));
+        }
+        let start = self.pos;
+        let mut depth = 1;
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    self.bump();
+                }
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(self.src[start..self.pos - 1].to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(Unsupported("unbalanced braces".to_string()))
+    }
+
+    /// One atom, with whether it takes limits in display mode. `None` for
+    /// input that renders as nothing (`\label`, `\displaystyle`, ...).
+    fn parse_atom(&mut self) -> Result<Option<(String, bool)>, Unsupported> {
+        self.skip_whitespace();
+        let Some(c) = self.peek() else {
+            return Err(Unsupported("missing argument".to_string()));
+        };
+        let atom = match c {
+            '{' => self.parse_group()?,
+            '\\' => {
+                self.bump();
+                return self.parse_command();
+            }
+            '0'..='9' | '.'
+                if c != '.' || self.rest()[1..].starts_with(|d: char| d.is_ascii_digit()) =>
+            {
+                let rest = self.rest();
+                let mut len = rest
+                    .find(|d: char| !d.is_ascii_digit() && d != '.')
+                    .unwrap_or(rest.len());
+                // A trailing dot ends the sentence, not the number
+                if rest[..len].ends_with('.') {
+                    len -= 1;
+                }
+                self.pos += len;
+                let digits: String = rest[..len].chars().map(|d| self.styled(d)).collect();
+                format!("<mn>{}</mn>", digits)
+            }
+            c if c.is_alphabetic() => {
+                self.bump();
+                self.ident(c)
+            }
+            '~' => {
+                self.bump();
+                r#"<mspace width="0.3333em"></mspace>"#.to_string()
+            }
+            '$' | '#' | '%' => {
+                return Err(Unsupported(format!("'{}' in math", c)));
+            }
+            '}' | '&' | '^' | '_' => {
+                return Err(Unsupported(format!("unexpected '{}'", c)));
+            }
+            _ => {
+                self.bump();
+                let op = match c {
+                    '-' => "−",
+                    '*' => "∗",
+                    '\'' => "′",
+                    _ => return Ok(Some((format!("<mo>{}</mo>", escape_char(c)), false))),
+                };
+                format!("<mo>{}</mo>", op)
+            }
+        };
+        Ok(Some((atom, false)))
+    }
+
+    /// An identifier in the current font
+    fn ident(&self, c: char) -> String {
+        match self.font {
+            Some(Font::Roman) => format!(r#"<mi mathvariant="normal">{}</mi>"#, escape_char(c)),
+            _ => format!("<mi>{}</mi>", escape_char(self.styled(c))),
+        }
+    }
+
+    fn styled(&self, c: char) -> char {
+        self.font.map(|font| styled_char(c, font)).unwrap_or(c)
+    }
+
+    /// The command whose name follows the cursor
+    fn parse_command(&mut self) -> Result<Option<(String, bool)>, Unsupported> {
+        let name = self.read_command()?;
+        if let Some(symbol) = symbol(&name) {
+            return Ok(Some(match symbol {
+                Symbol::Ident(text, true) => {
+                    (format!(r#"<mi mathvariant="normal">{}</mi>"#, text), false)
+                }
+                Symbol::Ident(text, false) => (format!("<mi>{}</mi>", text), false),
+                Symbol::Op(text) => (format!("<mo>{}</mo>", text), false),
+                Symbol::LargeOp(text, limits) => (
+                    format!(r#"<mo largeop="true" movablelimits="true">{}</mo>"#, text),
+                    limits,
+                ),
+                Symbol::Func(text, limits) => (format!("<mi>{}</mi>", text), limits),
+            }));
+        }
+        if let Some(font) = font(&name) {
+            let saved = self.font.replace(font);
+            let argument = self.parse_argument();
+            self.font = saved;
+            return Ok(Some((argument?, false)));
+        }
+        if let Some(width) = space(&name) {
+            return Ok(Some((
+                format!(r#"<mspace width="{}"></mspace>"#, width),
+                false,
+            )));
+        }
+        if let Some((accent, over)) = accent(&name) {
+            let base = self.parse_argument()?;
+            let stretchy = if name.starts_with("wide")
+                || name.starts_with("over")
+                || name.starts_with("under")
+            {
+                ""
+            } else {
+                r#" stretchy="false""#
+            };
+            return Ok(Some((
+                if over {
+                    format!(r#"<mover accent="true">{base}<mo{stretchy}>{accent}</mo></mover>"#)
+                } else {
+                    format!(
+                        r#"<munder accentunder="true">{base}<mo{stretchy}>{accent}</mo></munder>"#
+                    )
+                },
+                false,
+            )));
+        }
+
+        let atom = match name.as_str() {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let num = self.parse_argument()?;
+                let den = self.parse_argument()?;
+                format!("<mfrac>{num}{den}</mfrac>")
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let top = self.parse_argument()?;
+                let bottom = self.parse_argument()?;
+                format!(
+                    r#"<mrow><mo>(</mo><mfrac linethickness="0">{top}{bottom}</mfrac><mo>)</mo></mrow>"#
+                )
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.peek() == Some('[') {
+                    self.bump();
+                    let start = self.pos;
+                    let Some(end) = self.rest().find(']') else {
+                        return Err(Unsupported("unclosed '[' in \\sqrt".to_string()));
+                    };
+                    let index = &self.src[start..start + end];
+                    let index = Parser {
+                        src: index,
+                        pos: 0,
+                        display: false,
+                        font: self.font,
+                    }
+                    .parse_row()
+                    .map(mrow)?;
+                    self.pos = start + end + 1;
+                    let radicand = self.parse_argument()?;
+                    format!("<mroot>{radicand}{index}</mroot>")
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument()?)
+                }
+            }
+            "overset" | "stackrel" => {
+                let over = self.parse_argument()?;
+                let base = self.parse_argument()?;
+                format!("<mover>{base}{over}</mover>")
+            }
+            "underset" => {
+                let under = self.parse_argument()?;
+                let base = self.parse_argument()?;
+                format!("<munder>{base}{under}</munder>")
+            }
+            "text" | "textrm" | "textnormal" | "mbox" | "textit" | "textbf" | "texttt"
+            | "textsf" => {
+                let text = self.parse_raw_group()?;
+                if text.contains('$') || text.contains('\\') {
+                    return Err(Unsupported(format!("\\{} with markup", name)));
+                }
+                let variant = match name.as_str() {
+                    "textit" => r#" mathvariant="italic""#,
+                    "textbf" => r#" mathvariant="bold""#,
+                    "texttt" => r#" mathvariant="monospace""#,
+                    "textsf" => r#" mathvariant="sans-serif""#,
+                    _ => "",
+                };
+                format!(
+                    "<mtext{}>{}</mtext>",
+                    variant,
+                    escape(&text.replace(' ', "\u{a0}"))
+                )
+            }
+            "operatorname" => {
+                if self.peek() == Some('*') {
+                    self.bump();
+                }
+                let text = self.parse_raw_group()?;
+                if !text.chars().all(|c| c.is_alphanumeric() || c == ' ') {
+                    return Err(Unsupported("\\operatorname with markup".to_string()));
+                }
+                format!("<mi>{}</mi>", escape(&text.replace(' ', "")))
+            }
+            "left" => return self.parse_fenced().map(|fenced| Some((fenced, false))),
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl"
+            | "biggr" | "Biggl" | "Biggr" | "bigm" | "Bigm" => {
+                let delimiter = self.parse_delimiter()?;
+                format!(r#"<mo fence="true" stretchy="false">{}</mo>"#, delimiter)
+            }
+            "not" => {
+                self.skip_whitespace();
+                let negated = if self.peek() == Some('=') {
+                    self.bump();
+                    "≠"
+                } else if self.at_command("in") {
+                    self.pos += "\\in".len();
+                    "∉"
+                } else {
+                    return Err(Unsupported("\\not".to_string()));
+                };
+                format!("<mo>{}</mo>", negated)
+            }
+            "begin" => self.parse_environment()?,
+            "label" => {
+                self.parse_raw_group()?;
+                return Ok(None);
+            }
+            "notag" | "nonumber" | "displaystyle" | "textstyle" | "limits" | "nolimits" => {
+                return Ok(None)
+            }
+            "\\" => return Err(Unsupported("line break outside an environment".to_string())),
+            "{" => "<mo>{</mo>".to_string(),
+            "}" => "<mo>}</mo>".to_string(),
+            "|" => "<mo>‖</mo>".to_string(),
+            "_" | "%" | "$" | "#" => format!("<mi>{}</mi>", name),
+            "&" => "<mo>&amp;</mo>".to_string(),
+            _ => return Err(Unsupported(format!("\\{}", name))),
+        };
+        Ok(Some((atom, false)))
+    }
+
+    /// A delimiter after `\left`, `\right` or `\big`; empty for `.`
+    fn parse_delimiter(&mut self) -> Result<String, Unsupported> {
+        self.skip_whitespace();
+        let delimiter = match self.bump() {
+            Some('.') => "",
+            Some('\\') => match self.read_command()?.as_str() {
+                "{" | "lbrace" => "{",
+                "}" | "rbrace" => "}",
+                "|" | "Vert" | "lVert" | "rVert" => "‖",
+                "vert" | "lvert" | "rvert" | "mid" => "|",
+                "langle" => "⟨",
+                "rangle" => "⟩",
+                "lfloor" => "⌊",
+                "rfloor" => "⌋",
+                "lceil" => "⌈",
+                "rceil" => "⌉",
+                "uparrow" => "↑",
+                "downarrow" => "↓",
+                "backslash" => "\\",
+                other => return Err(Unsupported(format!("delimiter \\{}", other))),
+            },
+            Some(c @ ('(' | ')' | '[' | ']' | '|' | '/')) => return Ok(c.to_string()),
+            Some('<') => "⟨",
+            Some('>') => "⟩",
+            _ => return Err(Unsupported("missing delimiter".to_string())),
+        };
+        Ok(delimiter.to_string())
+    }
+
+    /// `\left( ... \right)`, the cursor just past `\left`
+    fn parse_fenced(&mut self) -> Result<String, Unsupported> {
+        let open = self.parse_delimiter()?;
+        let items = self.parse_row()?;
+        if !self.at_command("right") {
+            return Err(Unsupported("\\left without \\right".to_string()));
+        }
+        self.pos += "\\right".len();
+        let close = self.parse_delimiter()?;
+
+        let fence = |delimiter: &str| {
+            if delimiter.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    r#"<mo fence="true" stretchy="true">{}</mo>"#,
+                    escape(delimiter)
+                )
+            }
+        };
+        Ok(format!(
+            "<mrow>{}{}{}</mrow>",
+            fence(&open),
+            items.concat(),
+            fence(&close)
+        ))
+    }
+
+    /// `\begin{name} ... \end{name}`, the cursor just past `\begin`
+    fn parse_environment(&mut self) -> Result<String, Unsupported> {
+        let name = self.parse_raw_group()?;
+        let (open, close, align) = match name.as_str() {
+            "matrix" | "smallmatrix" | "array" => ("", "", "center"),
+            "pmatrix" => ("(", ")", "center"),
+            "bmatrix" => ("[", "]", "center"),
+            "Bmatrix" => ("{", "}", "center"),
+            "vmatrix" => ("|", "|", "center"),
+            "Vmatrix" => ("‖", "‖", "center"),
+            "cases" => ("{", "", "left"),
+            "aligned" | "align" | "align*" | "split" | "alignat" | "alignat*" => ("", "", "align"),
+            "gathered" | "gather" | "gather*" | "equation" | "equation*" => ("", "", "center"),
+            _ => return Err(Unsupported(format!("\\begin{{{}}}", name))),
+        };
+        if name == "array" || name.starts_with("alignat") {
+            // Column spec / column count
+            self.parse_raw_group()?;
+        }
+
+        let mut rows: Vec<Vec<String>> = vec![Vec::new()];
+        loop {
+            let cell = self.parse_row()?;
+            rows.last_mut().expect("a row").push(mrow(cell));
+            if self.rest().starts_with("\\\\") {
+                self.pos += 2;
+                // Optional spacing like `\\[2pt]`
+                self.skip_whitespace();
+                if self.peek() == Some('[') {
+                    if let Some(end) = self.rest().find(']') {
+                        self.pos += end + 1;
+                    }
+                }
+                rows.push(Vec::new());
+            } else if self.peek() == Some('&') {
+                self.bump();
+            } else if self.at_command("end") {
+                self.pos += "\\end".len();
+                let end = self.parse_raw_group()?;
+                if end != name {
+                    return Err(Unsupported(format!(
+                        "\\begin{{{}}} closed by \\end{{{}}}",
+                        name, end
+                    )));
+                }
+                break;
+            } else {
+                return Err(Unsupported(format!("unterminated \\begin{{{}}}", name)));
+            }
+        }
+        // `\\` before `\end` leaves an empty row
+        if rows.len() > 1
+            && rows
+                .last()
+                .is_some_and(|row| row.len() == 1 && row[0] == "<mrow></mrow>")
+        {
+            rows.pop();
+        }
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(1);
+        let column_align = match align {
+            // Alternate right / left, like amsmath
+            "align" => (0..columns)
+                .map(|i| if i % 2 == 0 { "right" } else { "left" })
+                .collect::<Vec<_>>()
+                .join(" "),
+            other => other.to_string(),
+        };
+        let body: String = rows
+            .into_iter()
+            .map(|row| {
+                let cells: String = row
+                    .into_iter()
+                    .map(|cell| format!("<mtd>{}</mtd>", cell))
+                    .collect();
+                format!("<mtr>{}</mtr>", cells)
+            })
+            .collect();
+        let display = if align == "align" || name.starts_with("gather") {
+            r#" displaystyle="true""#
+        } else {
+            ""
+        };
+        let table = format!(
+            r#"<mtable columnalign="{}"{}>{}</mtable>"#,
+            column_align, display, body
+        );
+
+        let fence = |delimiter: &str| {
+            if delimiter.is_empty() {
+                String::new()
+            } else {
+                format!("<mo>{}</mo>", escape(delimiter))
+            }
+        };
+        if open.is_empty() && close.is_empty() {
+            Ok(table)
+        } else {
+            Ok(format!(
+                "<mrow>{}{}{}</mrow>",
+                fence(open),
+                table,
+                fence(close)
+            ))
+        }
+    }
+}
+
+fn mrow(items: Vec<String>) -> String {
+    if items.len() == 1 {
+        items.into_iter().next().unwrap_or_default()
+    } else {
+        format!("<mrow>{}</mrow>", items.concat())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_char(c: char) -> String {
+    escape(c.encode_utf8(&mut [0; 4]))
+}
+
+fn font(name: &str) -> Option<Font> {
+    Some(match name {
+        "mathrm" | "rm" | "mathup" => Font::Roman,
+        "mathbf" | "bf" => Font::Bold,
+        "mathit" | "it" => Font::Italic,
+        "boldsymbol" | "bm" => Font::BoldItalic,
+        "mathcal" | "mathscr" | "cal" => Font::Script,
+        "mathfrak" => Font::Fraktur,
+        "mathbb" => Font::DoubleStruck,
+        "mathsf" => Font::SansSerif,
+        "mathtt" => Font::Monospace,
+        _ => return None,
+    })
+}
+
+/// `c` from the Unicode mathematical alphanumeric block for `font`
+fn styled_char(c: char, font: Font) -> char {
+    // Letters that predate the block live in Letterlike Symbols
+    let exception = match (font, c) {
+        (Font::Italic, 'h') => Some('ℎ'),
+        (Font::Script, 'B') => Some('ℬ'),
+        (Font::Script, 'E') => Some('ℰ'),
+        (Font::Script, 'F') => Some('ℱ'),
+        (Font::Script, 'H') => Some('ℋ'),
+        (Font::Script, 'I') => Some('ℐ'),
+        (Font::Script, 'L') => Some('ℒ'),
+        (Font::Script, 'M') => Some('ℳ'),
+        (Font::Script, 'R') => Some('ℛ'),
+        (Font::Script, 'e') => Some('ℯ'),
+        (Font::Script, 'g') => Some('ℊ'),
+        (Font::Script, 'o') => Some('ℴ'),
+        (Font::Fraktur, 'C') => Some('ℭ'),
+        (Font::Fraktur, 'H') => Some('ℌ'),
+        (Font::Fraktur, 'I') => Some('ℑ'),
+        (Font::Fraktur, 'R') => Some('ℜ'),
+        (Font::Fraktur, 'Z') => Some('ℨ'),
+        (Font::DoubleStruck, 'C') => Some('ℂ'),
+        (Font::DoubleStruck, 'H') => Some('ℍ'),
+        (Font::DoubleStruck, 'N') => Some('ℕ'),
+        (Font::DoubleStruck, 'P') => Some('ℙ'),
+        (Font::DoubleStruck, 'Q') => Some('ℚ'),
+        (Font::DoubleStruck, 'R') => Some('ℝ'),
+        (Font::DoubleStruck, 'Z') => Some('ℤ'),
+        _ => None,
+    };
+    if let Some(styled) = exception {
+        return styled;
+    }
+
+    // (capital A, small a, digit 0) code points, where the font has them
+    let (upper, lower, digit) = match font {
+        Font::Roman => return c,
+        Font::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+        Font::Italic => (0x1D434, 0x1D44E, None),
+        Font::BoldItalic => (0x1D468, 0x1D482, None),
+        Font::Script => (0x1D49C, 0x1D4B6, None),
+        Font::Fraktur => (0x1D504, 0x1D51E, None),
+        Font::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8)),
+        Font::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2)),
+        Font::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+    };
+    let code = match c {
+        'A'..='Z' => upper + (c as u32 - 'A' as u32),
+        'a'..='z' => lower + (c as u32 - 'a' as u32),
+        '0'..='9' => match digit {
+            Some(zero) => zero + (c as u32 - '0' as u32),
+            None => return c,
+        },
+        _ => return c,
+    };
+    char::from_u32(code).unwrap_or(c)
+}
+
+fn space(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "," | "thinspace" => "0.1667em",
+        ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em",
+        "!" | "negthinspace" => "-0.1667em",
+        " " => "0.3333em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        _ => return None,
+    })
+}
+
+/// Accent mark and whether it goes over (`true`) or under the base
+fn accent(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "hat" | "widehat" => ("^", true),
+        "bar" | "overline" => ("¯", true),
+        "tilde" | "widetilde" => ("~", true),
+        "vec" | "overrightarrow" => ("→", true),
+        "overleftarrow" => ("←", true),
+        "dot" => ("˙", true),
+        "ddot" => ("¨", true),
+        "check" => ("ˇ", true),
+        "breve" => ("˘", true),
+        "acute" => ("´", true),
+        "grave" => ("`", true),
+        "overbrace" => ("⏞", true),
+        "underline" => ("_", false),
+        "underbrace" => ("⏟", false),
+        _ => return None,
+    })
+}
+
+fn symbol(name: &str) -> Option<Symbol> {
+    use Symbol::*;
+    Some(match name {
+        // Greek
+        "alpha" => Ident("α", false),
+        "beta" => Ident("β", false),
+        "gamma" => Ident("γ", false),
+        "delta" => Ident("δ", false),
+        "epsilon" => Ident("ϵ", false),
+        "varepsilon" => Ident("ε", false),
+        "zeta" => Ident("ζ", false),
+        "eta" => Ident("η", false),
+        "theta" => Ident("θ", false),
+        "vartheta" => Ident("ϑ", false),
+        "iota" => Ident("ι", false),
+        "kappa" => Ident("κ", false),
+        "lambda" => Ident("λ", false),
+        "mu" => Ident("μ", false),
+        "nu" => Ident("ν", false),
+        "xi" => Ident("ξ", false),
+        "pi" => Ident("π", false),
+        "varpi" => Ident("ϖ", false),
+        "rho" => Ident("ρ", false),
+        "varrho" => Ident("ϱ", false),
+        "sigma" => Ident("σ", false),
+        "varsigma" => Ident("ς", false),
+        "tau" => Ident("τ", false),
+        "upsilon" => Ident("υ", false),
+        "phi" => Ident("ϕ", false),
+        "varphi" => Ident("φ", false),
+        "chi" => Ident("χ", false),
+        "psi" => Ident("ψ", false),
+        "omega" => Ident("ω", false),
+        "Gamma" => Ident("Γ", true),
+        "Delta" => Ident("Δ", true),
+        "Theta" => Ident("Θ", true),
+        "Lambda" => Ident("Λ", true),
+        "Xi" => Ident("Ξ", true),
+        "Pi" => Ident("Π", true),
+        "Sigma" => Ident("Σ", true),
+        "Upsilon" => Ident("Υ", true),
+        "Phi" => Ident("Φ", true),
+        "Psi" => Ident("Ψ", true),
+        "Omega" => Ident("Ω", true),
+
+        // Letter-like
+        "infty" => Ident("∞", true),
+        "partial" => Ident("∂", true),
+        "nabla" => Ident("∇", true),
+        "emptyset" | "varnothing" => Ident("∅", true),
+        "ell" => Ident("ℓ", false),
+        "hbar" => Ident("ℏ", false),
+        "Re" => Ident("ℜ", true),
+        "Im" => Ident("ℑ", true),
+        "aleph" => Ident("ℵ", true),
+        "top" => Ident("⊤", true),
+        "bot" => Ident("⊥", true),
+        "prime" => Op("′"),
+
+        // Binary operators and relations
+        "cdot" => Op("⋅"),
+        "times" => Op("×"),
+        "div" => Op("÷"),
+        "pm" => Op("±"),
+        "mp" => Op("∓"),
+        "ast" => Op("∗"),
+        "star" => Op("⋆"),
+        "circ" => Op("∘"),
+        "bullet" => Op("∙"),
+        "oplus" => Op("⊕"),
+        "otimes" => Op("⊗"),
+        "cup" => Op("∪"),
+        "cap" => Op("∩"),
+        "setminus" => Op("∖"),
+        "wedge" | "land" => Op("∧"),
+        "vee" | "lor" => Op("∨"),
+        "neg" | "lnot" => Op("¬"),
+        "leq" | "le" => Op("≤"),
+        "geq" | "ge" => Op("≥"),
+        "neq" | "ne" => Op("≠"),
+        "ll" => Op("≪"),
+        "gg" => Op("≫"),
+        "approx" => Op("≈"),
+        "equiv" => Op("≡"),
+        "sim" => Op("∼"),
+        "simeq" => Op("≃"),
+        "cong" => Op("≅"),
+        "propto" => Op("∝"),
+        "triangleq" => Op("≜"),
+        "coloneqq" => Op("≔"),
+        "prec" => Op("≺"),
+        "succ" => Op("≻"),
+        "preceq" => Op("⪯"),
+        "succeq" => Op("⪰"),
+        "in" => Op("∈"),
+        "notin" => Op("∉"),
+        "ni" => Op("∋"),
+        "subset" => Op("⊂"),
+        "subseteq" => Op("⊆"),
+        "supset" => Op("⊃"),
+        "supseteq" => Op("⊇"),
+        "sqsubseteq" => Op("⊑"),
+        "mid" => Op("∣"),
+        "parallel" => Op("∥"),
+        "perp" => Op("⊥"),
+        "vdash" => Op("⊢"),
+        "dashv" => Op("⊣"),
+        "models" => Op("⊨"),
+        "forall" => Op("∀"),
+        "exists" => Op("∃"),
+        "colon" => Op(":"),
+        "vert" => Op("|"),
+        "Vert" => Op("‖"),
+
+        // Arrows
+        "to" | "rightarrow" => Op("→"),
+        "gets" | "leftarrow" => Op("←"),
+        "leftrightarrow" => Op("↔"),
+        "Rightarrow" => Op("⇒"),
+        "Leftarrow" => Op("⇐"),
+        "Leftrightarrow" => Op("⇔"),
+        "implies" => Op("⟹"),
+        "impliedby" => Op("⟸"),
+        "iff" => Op("⟺"),
+        "mapsto" => Op("↦"),
+        "longrightarrow" => Op("⟶"),
+        "longleftarrow" => Op("⟵"),
+        "longmapsto" => Op("⟼"),
+        "hookrightarrow" => Op("↪"),
+        "leadsto" => Op("⇝"),
+        "uparrow" => Op("↑"),
+        "downarrow" => Op("↓"),
+
+        // Delimiters and dots
+        "langle" => Op("⟨"),
+        "rangle" => Op("⟩"),
+        "lfloor" => Op("⌊"),
+        "rfloor" => Op("⌋"),
+        "lceil" => Op("⌈"),
+        "rceil" => Op("⌉"),
+        "lbrace" => Op("{"),
+        "rbrace" => Op("}"),
+        "lvert" | "rvert" => Op("|"),
+        "lVert" | "rVert" => Op("‖"),
+        "backslash" => Op("\\"),
+        "ldots" | "dots" | "dotsc" => Op("…"),
+        "cdots" | "dotsb" => Op("⋯"),
+        "vdots" => Op("⋮"),
+        "ddots" => Op("⋱"),
+
+        // Big operators
+        "sum" => LargeOp("∑", true),
+        "prod" => LargeOp("∏", true),
+        "coprod" => LargeOp("∐", true),
+        "bigcup" => LargeOp("⋃", true),
+        "bigcap" => LargeOp("⋂", true),
+        "bigoplus" => LargeOp("⨁", true),
+        "bigotimes" => LargeOp("⨂", true),
+        "bigvee" => LargeOp("⋁", true),
+        "bigwedge" => LargeOp("⋀", true),
+        "int" => LargeOp("∫", false),
+        "iint" => LargeOp("∬", false),
+        "iiint" => LargeOp("∭", false),
+        "oint" => LargeOp("∮", false),
+
+        // Function names
+        "lim" => Func("lim", true),
+        "liminf" => Func("lim inf", true),
+        "limsup" => Func("lim sup", true),
+        "max" => Func("max", true),
+        "min" => Func("min", true),
+        "sup" => Func("sup", true),
+        "inf" => Func("inf", true),
+        "det" => Func("det", true),
+        "gcd" => Func("gcd", true),
+        "Pr" => Func("Pr", true),
+        "argmax" => Func("arg max", true),
+        "argmin" => Func("arg min", true),
+        "sin" => Func("sin", false),
+        "cos" => Func("cos", false),
+        "tan" => Func("tan", false),
+        "cot" => Func("cot", false),
+        "sec" => Func("sec", false),
+        "csc" => Func("csc", false),
+        "arcsin" => Func("arcsin", false),
+        "arccos" => Func("arccos", false),
+        "arctan" => Func("arctan", false),
+        "sinh" => Func("sinh", false),
+        "cosh" => Func("cosh", false),
+        "tanh" => Func("tanh", false),
+        "log" => Func("log", false),
+        "lg" => Func("lg", false),
+        "ln" => Func("ln", false),
+        "exp" => Func("exp", false),
+        "arg" => Func("arg", false),
+        "deg" => Func("deg", false),
+        "dim" => Func("dim", false),
+        "ker" => Func("ker", false),
+        "hom" => Func("hom", false),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(latex: &str) -> String {
+        let math = latex_to_mathml(latex, false).expect("convertible");
+        let start = math.find("<semantics>").unwrap() + "<semantics>".len();
+        let end = math.find("<annotation").unwrap();
+        math[start..end].to_string()
+    }
+
+    #[test]
+    fn converts_scripts_fractions_and_symbols() {
+        assert_eq!(
+            body("x^2 + y_i"),
+            "<mrow><msup><mi>x</mi><mn>2</mn></msup><mo>+</mo><msub><mi>y</mi><mi>i</mi></msub></mrow>"
+        );
+        assert_eq!(
+            body(r"\frac{a}{b} \leq \sqrt{2}"),
+            "<mrow><mfrac><mi>a</mi><mi>b</mi></mfrac><mo>≤</mo><msqrt><mn>2</mn></msqrt></mrow>"
+        );
+        assert_eq!(
+            body(r"\Gamma \vdash e : \tau"),
+            "<mrow><mi mathvariant=\"normal\">Γ</mi><mo>⊢</mo><mi>e</mi><mo>:</mo><mi>τ</mi></mrow>"
+        );
+        assert_eq!(body(r"\mathbb{R}^n"), "<msup><mi>ℝ</mi><mi>n</mi></msup>");
+        assert_eq!(body("f'"), "<msup><mi>f</mi><mo>′</mo></msup>");
+    }
+
+    #[test]
+    fn display_limits_and_environments() {
+        let sum = latex_to_mathml(r"\sum_{i=0}^n i", true).unwrap();
+        assert!(sum.starts_with("<math display=\"block\">"));
+        assert!(sum.contains("<munderover><mo largeop=\"true\" movablelimits=\"true\">∑</mo>"));
+        assert!(
+            sum.contains("<annotation encoding=\"application/x-tex\">\\sum_{i=0}^n i</annotation>")
+        );
+
+        let inline = latex_to_mathml(r"\sum_{i=0}^n i", false).unwrap();
+        assert!(inline.contains("<msubsup>"));
+
+        let matrix = body(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}");
+        assert!(matrix.starts_with("<mrow><mo>(</mo><mtable columnalign=\"center\">"));
+        assert!(matrix.contains("<mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>"));
+
+        let fenced = body(r"\left( \frac{1}{2} \right)");
+        assert!(fenced.starts_with("<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mfrac>"));
+    }
+
+    #[test]
+    fn unsupported_constructs_are_reported() {
+        for (latex, construct) in [
+            (r"\newcommand{\R}{\mathbb{R}}", r"\newcommand"),
+            (r"\begin{tikzcd} A \end{tikzcd}", r"\begin{tikzcd}"),
+            ("{x", "unbalanced braces"),
+            ("x}", "unbalanced braces"),
+            (r"\left( x", r"\left without \right"),
+        ] {
+            assert_eq!(
+                latex_to_mathml(latex, true),
+                Err(Unsupported(construct.to_string())),
+                "{latex}"
+            );
+        }
+    }
+}
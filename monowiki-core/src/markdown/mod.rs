@@ -1,11 +1,16 @@
 //! Markdown processing pipeline with custom extensions.
 
+pub mod block_ids;
+pub mod callouts;
 pub mod citations;
 pub mod highlight;
 pub mod math;
 pub mod mathjax;
+pub mod mathml;
 pub mod nota_blocks;
 pub mod sidenotes;
+pub mod source_map;
+pub mod transclusion;
 pub mod wikilinks;
 
 #[cfg(test)]
@@ -14,20 +19,39 @@ mod test_integration;
 #[cfg(test)]
 mod debug_events;
 
+use crate::assets::{is_asset_path, AssetIndex};
+use crate::config::{MathConfig, MathMacros, NotaConfig};
 use crate::slug::slugify;
 use citations::{render_references, CitationContext, CitationTransformer};
-use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
-use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 
 use crate::models::Diagnostic;
 
+use block_ids::scan_block_ids;
+pub use block_ids::{BlockIdTransformer, BlockIndex};
+pub use callouts::CalloutTransformer;
 pub use highlight::{highlight_code, HighlightTransformer};
-pub use math::MathTransformer;
-use mathjax::render_math_for_mathjax;
-pub use nota_blocks::NotaBlockTransformer;
+pub use math::{number_equations, MathTransformer};
+use mathjax::MathRenderer;
+pub use mathjax::{mathjax_macros, needs_mathjax};
+pub use nota_blocks::{LabelIndex, NotaBlockTransformer, NotaLabel};
 pub use sidenotes::SidenoteTransformer;
+use source_map::{strip_spans, SourceMap, SpannedEvent};
 pub use wikilinks::WikilinkTransformer;
 
+/// Targets of `[[...]]` and `![[...]]`, without size hints or labels
+static ASSET_WIKILINK_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[\[([^\[\]|#]+)(?:[#|][^\[\]]*)?\]\]").unwrap());
+
+/// `src`, `href` and `poster` attribute values in raw HTML
+static HTML_ASSET_ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b(?:src|href|poster)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'<>`=]+))"#).unwrap()
+});
+
 #[derive(Debug, Clone)]
 struct TocItem {
     level: u32,
@@ -35,9 +59,64 @@ struct TocItem {
     id: String,
 }
 
+/// Inputs to `MarkdownProcessor::convert` beyond the markdown itself
+///
+/// `new` gives a context with only a slug map and base URL; the builder
+/// fills in the site-wide indexes once and the per-note fields for each note.
+#[derive(Clone, Copy)]
+pub struct RenderContext<'a> {
+    pub slug_map: &'a HashMap<String, String>,
+    pub base_url: &'a str,
+    /// Slug of the note being rendered
+    pub note_slug: Option<&'a str>,
+    /// Vault-relative source path of the note being rendered
+    pub source_path: Option<&'a str>,
+    /// Source lines preceding the markdown in its file (e.g. frontmatter),
+    /// so diagnostic spans point at file lines
+    pub line_offset: usize,
+    /// The note's `macros` frontmatter, extending and overriding the site's
+    /// `math.macros`
+    pub note_macros: Option<&'a MathMacros>,
+    pub citations: Option<&'a CitationContext<'a>>,
+    pub block_index: Option<&'a BlockIndex>,
+    /// Slugs of scheduled or expired notes; links to them render as plain text
+    pub unpublished: Option<&'a HashSet<String>>,
+    /// Vault-relative source path -> slug, for relative `[text](other.md)` links
+    pub note_paths: Option<&'a HashMap<String, String>>,
+    /// Attachments and local images
+    pub assets: Option<&'a AssetIndex>,
+    /// Nota block and equation labels of every note
+    pub labels: Option<&'a LabelIndex>,
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn new(slug_map: &'a HashMap<String, String>, base_url: &'a str) -> Self {
+        Self {
+            slug_map,
+            base_url,
+            note_slug: None,
+            source_path: None,
+            line_offset: 0,
+            note_macros: None,
+            citations: None,
+            block_index: None,
+            unpublished: None,
+            note_paths: None,
+            assets: None,
+            labels: None,
+        }
+    }
+}
+
 /// Markdown processor with custom extensions
 pub struct MarkdownProcessor {
     options: Options,
+    /// Heading levels listed in the table of contents
+    toc_depth: RangeInclusive<u32>,
+    /// Numbering of nota blocks
+    nota: NotaConfig,
+    /// How math is typeset
+    math: MathConfig,
 }
 
 impl MarkdownProcessor {
@@ -53,48 +132,103 @@ impl MarkdownProcessor {
         // Note: ENABLE_MATH is NOT enabled - we handle all math delimiters
         // ourselves in MathTransformer to support \[...\], \(...\), $$, and $
 
-        Self { options }
+        Self {
+            options,
+            toc_depth: 1..=6,
+            nota: NotaConfig::default(),
+            math: MathConfig::default(),
+        }
+    }
+
+    /// Only list headings from level `min` to `max` in the table of contents
+    pub fn with_toc_depth(mut self, min: u8, max: u8) -> Self {
+        self.toc_depth = u32::from(min)..=u32::from(max);
+        self
+    }
+
+    /// Number nota blocks according to `nota`
+    pub fn with_nota(mut self, nota: NotaConfig) -> Self {
+        self.nota = nota;
+        self
+    }
+
+    /// Typeset math according to `math`
+    pub fn with_math(mut self, math: MathConfig) -> Self {
+        self.math = math;
+        self
     }
 
     /// Convert markdown to HTML with all custom transforms
     ///
-    /// Returns a tuple of (html, outgoing_links, toc_html)
+    /// Returns a tuple of (html, outgoing_links, toc_html, diagnostics)
     pub fn convert(
         &self,
         markdown: &str,
-        slug_map: &HashMap<String, String>,
-        base_url: &str,
-        _typst_preamble: Option<&str>, // Kept for API compatibility, unused with MathJax
-        citation_context: Option<&CitationContext>,
-        note_slug: Option<&str>,
-        source_path: Option<&str>,
+        ctx: &RenderContext,
     ) -> (String, Vec<String>, Option<String>, Vec<Diagnostic>) {
-        // Parse markdown into events
-        let parser = Parser::new_ext(markdown, self.options);
-        let events: Vec<Event> = parser.collect();
+        let RenderContext {
+            slug_map,
+            base_url,
+            note_slug,
+            source_path,
+            line_offset,
+            note_macros,
+            citations: citation_context,
+            block_index,
+            unpublished,
+            note_paths,
+            assets,
+            labels,
+        } = *ctx;
+
+        // Parse markdown into events, keeping source ranges for diagnostics
+        let source_map = SourceMap::new(markdown, line_offset);
+        let events = self.parse_spanned(markdown);
 
         // Collect headings for TOC and later ID injection
         let headings = collect_headings(&events);
         let mut diagnostics: Vec<Diagnostic> = Vec::new();
 
-        // Transform math delimiters first ($$, $, etc.)
+        // Rewrite callout blockquotes while the `[!type]` marker text is intact
+        let events = CalloutTransformer::new().transform_spanned(events);
+
+        // Transform math delimiters ($$, $, etc.)
         let math_transformer = MathTransformer::new();
-        let events = math_transformer.transform(events);
+        let events = math_transformer.transform_spanned(events);
 
         // Apply nota blocks (needs paragraph structure intact)
-        let nota_transformer = NotaBlockTransformer::new();
-        let events = nota_transformer.transform(events);
-
-        // Render math for MathJax (client-side rendering)
-        let (events, mut math_diags) = render_math_for_mathjax(events);
+        let nota_transformer = NotaBlockTransformer::new()
+            .with_config(self.nota.clone())
+            .with_note(
+                note_slug.map(|s| s.to_string()),
+                source_path.map(|s| s.to_string()),
+            )
+            .with_source_map(&source_map);
+        let (events, mut note_labels, mut nota_diags) = nota_transformer.transform_spanned(events);
+        diagnostics.append(&mut nota_diags);
+
+        // Render math (MathML or MathJax markup), anchoring and numbering
+        // labelled equations
+        let equations = number_equations(&events);
+        let macros = self.math.macros_for(note_macros);
+        let math_renderer = MathRenderer::new(
+            self.math.render,
+            note_slug.map(|s| s.to_string()),
+            source_path.map(|s| s.to_string()),
+        )
+        .with_source_map(&source_map)
+        .with_macros(&macros)
+        .with_block_labels(&note_labels);
+        let (events, mut math_diags) = math_renderer.render(events, &equations);
         diagnostics.append(&mut math_diags);
+        note_labels.extend(equations);
 
         // Unwrap paragraphs with display math (must be after nota blocks)
         let events = math_transformer.unwrap_display_math_paragraphs(events);
 
         // Apply sidenote transform
         let sidenote_transformer = SidenoteTransformer::new();
-        let events = sidenote_transformer.transform(events);
+        let events = sidenote_transformer.transform_spanned(events);
 
         // Apply wikilink transform
         let wikilink_transformer = WikilinkTransformer::new(
@@ -102,8 +236,31 @@ impl MarkdownProcessor {
             base_url,
             note_slug.map(|s| s.to_string()),
             source_path.map(|s| s.to_string()),
-        );
-        let (events, outgoing_links, mut link_diags) = wikilink_transformer.transform(events);
+        )
+        .with_source_map(&source_map)
+        .with_note_labels(&note_labels);
+        let wikilink_transformer = match block_index {
+            Some(block_index) => wikilink_transformer.with_block_index(block_index),
+            None => wikilink_transformer,
+        };
+        let wikilink_transformer = match unpublished {
+            Some(unpublished) => wikilink_transformer.with_unpublished(unpublished),
+            None => wikilink_transformer,
+        };
+        let wikilink_transformer = match note_paths {
+            Some(note_paths) => wikilink_transformer.with_note_paths(note_paths),
+            None => wikilink_transformer,
+        };
+        let wikilink_transformer = match assets {
+            Some(assets) => wikilink_transformer.with_assets(assets),
+            None => wikilink_transformer,
+        };
+        let wikilink_transformer = match labels {
+            Some(labels) => wikilink_transformer.with_label_index(labels),
+            None => wikilink_transformer,
+        };
+        let (events, outgoing_links, mut link_diags) =
+            wikilink_transformer.transform_spanned(events);
         diagnostics.append(&mut link_diags);
 
         // Apply citation transform
@@ -113,7 +270,8 @@ impl MarkdownProcessor {
                 ctx,
                 note_slug.map(|s| s.to_string()),
                 source_path.map(|s| s.to_string()),
-            );
+            )
+            .with_source_map(&source_map);
             let (events, refs, mut cite_diags) = transformer.transform(events);
             citation_references = refs;
             diagnostics.append(&mut cite_diags);
@@ -123,6 +281,11 @@ impl MarkdownProcessor {
         };
 
         // Inject heading ids to match TOC anchors
+        let events = strip_spans(events);
+
+        // Turn trailing `^block-id` markers into paragraph / list item ids
+        let events = BlockIdTransformer::new().transform(events);
+
         let events = attach_heading_ids(events, &headings);
         let events = add_heading_anchors(events);
 
@@ -139,11 +302,7 @@ impl MarkdownProcessor {
             html_output.push_str(&refs_html);
         }
 
-        let toc_html = if headings.is_empty() {
-            None
-        } else {
-            Some(render_toc(&headings))
-        };
+        let toc_html = render_toc(&headings, &self.toc_depth);
 
         (html_output, outgoing_links, toc_html, diagnostics)
     }
@@ -151,38 +310,203 @@ impl MarkdownProcessor {
     /// Convert markdown to HTML without link tracking
     pub fn convert_simple(&self, markdown: &str) -> String {
         let slug_map = HashMap::new();
-        let (html, _, _, _) = self.convert(markdown, &slug_map, "/", None, None, None, None);
+        let (html, _, _, _) = self.convert(markdown, &RenderContext::new(&slug_map, "/"));
         html
     }
 }
 
+impl MarkdownProcessor {
+    /// Parser options used for every note
+    pub(crate) fn options(&self) -> Options {
+        self.options
+    }
+
+    /// Parse markdown into events paired with their source ranges, keeping
+    /// `:::` fences on lines of their own
+    fn parse_spanned(&self, markdown: &str) -> Vec<SpannedEvent> {
+        let (prepared, inserted) = nota_blocks::isolate_fences(markdown);
+        // Offsets in `prepared` map back by the newlines inserted before them
+        let original = |offset: usize| offset - inserted.partition_point(|&at| at < offset);
+        Parser::new_ext(&prepared, self.options)
+            .into_offset_iter()
+            .map(|(event, range)| {
+                (
+                    event.into_static(),
+                    original(range.start)..original(range.end),
+                )
+            })
+            .collect()
+    }
+
+    /// Labelled nota blocks and equations defined in a note's markdown,
+    /// numbered as `convert` numbers them
+    pub fn nota_labels(&self, markdown: &str) -> Vec<NotaLabel> {
+        let events = self.parse_spanned(markdown);
+        let events = CalloutTransformer::new().transform_spanned(events);
+        let events = MathTransformer::new().transform_spanned(events);
+        let (events, mut labels, _) = NotaBlockTransformer::new()
+            .with_config(self.nota.clone())
+            .transform_spanned(events);
+        labels.extend(number_equations(&events));
+        labels
+    }
+
+    /// Block ids (`^block-id`) defined in a note's markdown
+    pub fn block_ids(&self, markdown: &str) -> Vec<String> {
+        scan_block_ids(markdown, self.options)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Asset references in a note's markdown, as written: local image and
+    /// link destinations, `[[file.ext]]` / `![[file.ext]]` targets and the
+    /// `src` / `href` / `poster` attributes of raw HTML
+    pub fn asset_refs(&self, markdown: &str) -> Vec<String> {
+        let mut refs = Vec::new();
+        let mut text = String::new();
+        let mut in_code_block = false;
+        let flush = |text: &mut String, refs: &mut Vec<String>| {
+            for caps in ASSET_WIKILINK_RE.captures_iter(text) {
+                let target = caps[1].trim();
+                if is_asset_path(target) {
+                    refs.push(target.to_string());
+                }
+            }
+            text.clear();
+        };
+
+        for event in Parser::new_ext(markdown, self.options) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(TagEnd::CodeBlock) => in_code_block = false,
+                Event::Text(t) if !in_code_block => {
+                    text.push_str(&t);
+                    continue;
+                }
+                Event::Start(Tag::Image { dest_url, .. } | Tag::Link { dest_url, .. })
+                    if wikilinks::is_asset_reference(&dest_url) =>
+                {
+                    refs.push(dest_url.to_string());
+                }
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    for caps in HTML_ASSET_ATTR_RE.captures_iter(&html) {
+                        let value = caps.get(1).or(caps.get(2)).or(caps.get(3));
+                        if let Some(value) = value
+                            .map(|m| m.as_str().trim())
+                            .filter(|v| wikilinks::is_asset_reference(v))
+                        {
+                            refs.push(value.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+            flush(&mut text, &mut refs);
+        }
+        flush(&mut text, &mut refs);
+
+        refs.dedup();
+        refs
+    }
+
+    /// Destinations of the Markdown links to `.md` files in a note, as written
+    pub fn note_link_refs(&self, markdown: &str) -> Vec<String> {
+        let mut refs: Vec<String> = Parser::new_ext(markdown, self.options)
+            .filter_map(|event| match event {
+                Event::Start(Tag::Link { dest_url, .. })
+                    if wikilinks::markdown_link_target(&dest_url).is_some() =>
+                {
+                    Some(dest_url.to_string())
+                }
+                _ => None,
+            })
+            .collect();
+        refs.sort();
+        refs.dedup();
+        refs
+    }
+}
+
 impl Default for MarkdownProcessor {
     fn default() -> Self {
         Self::new()
     }
 }
 
-fn collect_headings(events: &[Event]) -> Vec<TocItem> {
+/// Heading ids for a note: slugified heading text, with repeats suffixed
+/// (`examples`, `examples-1`) so every anchor is unique
+#[derive(Debug, Default)]
+pub(crate) struct HeadingIds {
+    used: HashSet<String>,
+}
+
+impl HeadingIds {
+    /// Claim an id given explicitly with `{#id}`
+    pub(crate) fn reserve(&mut self, id: &str) {
+        self.used.insert(id.to_string());
+    }
+
+    /// Unused id for a heading titled `title`
+    pub(crate) fn unique(&mut self, title: &str) -> String {
+        self.claim(&slugify(title))
+    }
+
+    /// `id` itself if unused, otherwise the first unused `id-N`
+    pub(crate) fn claim(&mut self, base: &str) -> String {
+        let mut id = base.to_string();
+        let mut n = 0;
+        while !self.used.insert(id.clone()) {
+            n += 1;
+            id = format!("{base}-{n}");
+        }
+        id
+    }
+}
+
+/// Text of a heading, from every inline event that renders as text
+pub(crate) fn heading_text<'a>(event: &'a Event) -> Option<&'a str> {
+    match event {
+        Event::Text(text)
+        | Event::Code(text)
+        | Event::InlineMath(text)
+        | Event::DisplayMath(text) => Some(text),
+        _ => None,
+    }
+}
+
+fn collect_headings(events: &[SpannedEvent]) -> Vec<TocItem> {
+    // Explicit `{#id}`s win over generated ones wherever they appear
+    let mut ids = HeadingIds::default();
+    for (event, _) in events {
+        if let Event::Start(Tag::Heading { id: Some(id), .. }) = event {
+            ids.reserve(id);
+        }
+    }
+
     let mut toc = Vec::new();
-    let mut current: Option<(u32, String)> = None;
+    let mut current: Option<(u32, Option<String>, String)> = None;
 
-    for event in events {
+    for (event, _) in events {
         match event {
-            Event::Start(Tag::Heading { level, .. }) => {
-                current = Some((*level as u32, String::new()));
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                current = Some((
+                    *level as u32,
+                    id.as_ref().map(|id| id.to_string()),
+                    String::new(),
+                ));
             }
-            Event::Text(text) => {
-                if let Some((_level, ref mut title)) = current {
-                    title.push_str(text.as_ref());
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, id, title)) = current.take() {
+                    let id = id.unwrap_or_else(|| ids.unique(&title));
+                    toc.push(TocItem { level, title, id });
                 }
             }
-            Event::End(pulldown_cmark::TagEnd::Heading(_)) => {
-                if let Some((level, title)) = current.take() {
-                    let id = slugify(&title);
-                    toc.push(TocItem { level, title, id });
+            event => {
+                if let (Some((_, _, title)), Some(text)) = (current.as_mut(), heading_text(event)) {
+                    title.push_str(text);
                 }
             }
-            _ => {}
         }
     }
 
@@ -204,11 +528,10 @@ fn attach_heading_ids(
                 classes,
                 attrs,
             }) => {
-                if id.is_none() {
-                    if let Some(next) = heading_iter.next() {
-                        id = Some(pulldown_cmark::CowStr::Boxed(
-                            next.id.clone().into_boxed_str(),
-                        ));
+                // Every heading has an entry, explicit ids included
+                if let Some(next) = heading_iter.next() {
+                    if id.is_none() {
+                        id = Some(CowStr::Boxed(next.id.clone().into_boxed_str()));
                     }
                 }
                 result.push(Event::Start(Tag::Heading {
@@ -262,18 +585,47 @@ fn add_heading_anchors(events: Vec<Event<'static>>) -> Vec<Event<'static>> {
     result
 }
 
-fn render_toc(headings: &[TocItem]) -> String {
-    let mut html = String::from(r#"<nav class="toc-nav"><h3>Contents</h3><ul class="toc-list">"#);
-    for h in headings {
+/// Nested `<ul>` of the headings within `depth`; a heading deeper than the
+/// one before it opens a list inside that one's item
+fn render_toc(headings: &[TocItem], depth: &RangeInclusive<u32>) -> Option<String> {
+    let mut html = String::from(r#"<nav class="toc-nav"><h3>Contents</h3>"#);
+    // Levels of the open lists, outermost first
+    let mut open: Vec<u32> = Vec::new();
+
+    for h in headings.iter().filter(|h| depth.contains(&h.level)) {
+        match open.last().copied() {
+            None => {
+                html.push_str(r#"<ul class="toc-list">"#);
+                open.push(h.level);
+            }
+            Some(_) => {
+                while open.len() > 1 && open.last().is_some_and(|&top| h.level < top) {
+                    html.push_str("</li></ul>");
+                    open.pop();
+                }
+                if open.last().is_some_and(|&top| h.level > top) {
+                    html.push_str("<ul>");
+                    open.push(h.level);
+                } else {
+                    html.push_str("</li>");
+                }
+            }
+        }
         html.push_str(&format!(
-            r##"<li class="toc-level-{}"><a href="#{}">{}</a></li>"##,
+            r##"<li class="toc-level-{}"><a href="#{}">{}</a>"##,
             h.level,
             h.id,
             html_escape(&h.title)
         ));
     }
+
+    if open.is_empty() {
+        return None;
+    }
+    html.push_str("</li>");
+    html.push_str(&"</ul></li>".repeat(open.len() - 1));
     html.push_str("</ul></nav>");
-    html
+    Some(html)
 }
 
 fn html_escape(text: &str) -> String {
@@ -310,6 +662,24 @@ mod tests {
         assert!(html.contains("<th>Header 1</th>"));
     }
 
+    #[test]
+    fn asset_refs_include_raw_html_attributes() {
+        let processor = MarkdownProcessor::new();
+        let md = "![[diagram.png]]\n\n<video src=\"clip.mp4\" poster='poster.jpg'></video>\n\n\
+                  See <a href=\"docs/manual%20v2.pdf\">the manual</a> or \
+                  <a href=\"https://example.com/x.pdf\">this</a>.\n\n\
+                  ```\n<img src=\"code.png\">\n```\n";
+        assert_eq!(
+            processor.asset_refs(md),
+            vec![
+                "diagram.png",
+                "clip.mp4",
+                "poster.jpg",
+                "docs/manual%20v2.pdf"
+            ]
+        );
+    }
+
     #[test]
     fn test_code_blocks() {
         let processor = MarkdownProcessor::new();
@@ -2,42 +2,227 @@
 //!
 //! Recognizes paragraphs that start with `@Definition[label]{Title}: ...`
 //! and wraps their content in a styled block while preserving downstream
-//! transformations (math, wikilinks, etc.).
+//! transformations (math, wikilinks, etc.). Blocks spanning several
+//! paragraphs, lists or display math use a fenced container:
+//!
+//! ```text
+//! ::: proof [main] {Of the main theorem}
+//! Any block content, including nested `:::` blocks.
+//! :::
+//! ```
+//!
+//! Blocks are numbered per kind or
+//! with a shared counter (see `NotaConfig`), and their labels can be
+//! referenced with `@ref[label]`, `[[#label]]` or `[[note#label]]`. A label
+//! used twice in a note is reported, and only its first block gets the id.
 
+use super::source_map::{strip_spans, sub_range, unspanned, SourceMap, SpannedEvent};
+use crate::config::{NotaConfig, NotaCounter};
+use crate::models::{Diagnostic, DiagnosticSeverity};
 use crate::slug::slugify;
-use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
-use regex::Regex;
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Tag, TagEnd};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::OnceLock;
 
+/// Characters of block text kept for hover previews
+const PREVIEW_CHARS: usize = 280;
+
+/// Closes a block's heading and opens its body
+const HEADING_END: &str = r#"</div><div class="nota-block-body">"#;
+
+/// A labelled nota block, as references to it see it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotaLabel {
+    /// DOM id of the block
+    pub label: String,
+    /// Kind as written (`Theorem`)
+    pub kind: String,
+    /// Display number (`3`, or `2.1` when numbered by section); `None` for
+    /// unnumbered kinds
+    pub number: Option<String>,
+    pub title: String,
+    /// Start of the block's text, for hover previews
+    pub text: String,
+}
+
+impl NotaLabel {
+    /// How references render: "Theorem 3" ("Equation (3)" for equations),
+    /// or the kind and title when the block is unnumbered
+    pub fn reference_text(&self) -> String {
+        let kind = capitalize(&self.kind);
+        match (&self.number, self.title.is_empty()) {
+            (Some(number), _) if self.kind == "equation" => format!("{kind} ({number})"),
+            (Some(number), _) => format!("{kind} {number}"),
+            (None, false) => format!("{kind} ({})", self.title),
+            (None, true) => kind,
+        }
+    }
+}
+
+/// Labelled nota blocks defined by each note, keyed by slug map key
+pub type LabelIndex = HashMap<String, Vec<NotaLabel>>;
+
+/// Assigns numbers to nota blocks in document order
+#[derive(Debug)]
+pub(crate) struct NotaNumbering<'a> {
+    config: &'a NotaConfig,
+    section: u32,
+    counts: HashMap<String, u32>,
+}
+
+impl<'a> NotaNumbering<'a> {
+    pub(crate) fn new(config: &'a NotaConfig) -> Self {
+        Self {
+            config,
+            section: 0,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Note a heading; `##` starts a new section
+    pub(crate) fn heading(&mut self, level: HeadingLevel) {
+        if self.config.by_section && level == HeadingLevel::H2 {
+            self.section += 1;
+            self.counts.clear();
+        }
+    }
+
+    /// Number of the next block of `kind`, if that kind is numbered
+    pub(crate) fn next(&mut self, kind: &str) -> Option<String> {
+        if self
+            .config
+            .unnumbered
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(kind))
+        {
+            return None;
+        }
+        let key = match self.config.counter {
+            NotaCounter::PerKind => kind.to_lowercase(),
+            NotaCounter::Shared => String::new(),
+        };
+        let count = self.counts.entry(key).or_insert(0);
+        *count += 1;
+        Some(if self.config.by_section {
+            format!("{}.{}", self.section, count)
+        } else {
+            count.to_string()
+        })
+    }
+}
+
 #[derive(Debug, Default)]
-pub struct NotaBlockTransformer;
+pub struct NotaBlockTransformer<'a> {
+    config: NotaConfig,
+    note_slug: Option<String>,
+    source_path: Option<String>,
+    source_map: Option<&'a SourceMap<'a>>,
+}
 
-impl NotaBlockTransformer {
+impl<'a> NotaBlockTransformer<'a> {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Number blocks according to `config`
+    pub fn with_config(mut self, config: NotaConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Attribute diagnostics to a note and its source file
+    pub fn with_note(mut self, note_slug: Option<String>, source_path: Option<String>) -> Self {
+        self.note_slug = note_slug;
+        self.source_path = source_path;
+        self
+    }
+
+    /// Resolve event ranges to file positions for diagnostics
+    pub fn with_source_map(mut self, source_map: &'a SourceMap<'a>) -> Self {
+        self.source_map = Some(source_map);
+        self
     }
 
     pub fn transform(&self, events: Vec<Event<'static>>) -> Vec<Event<'static>> {
+        strip_spans(self.transform_spanned(unspanned(events)).0)
+    }
+
+    /// Like `transform`, but keeps source ranges; generated block wrappers
+    /// take the range of the paragraph they replace. Also returns the
+    /// labelled blocks in document order and diagnostics.
+    pub fn transform_spanned(
+        &self,
+        events: Vec<SpannedEvent>,
+    ) -> (Vec<SpannedEvent>, Vec<NotaLabel>, Vec<Diagnostic>) {
         let mut out = Vec::new();
+        let mut labels = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut seen = HashSet::new();
+        let mut numbering = NotaNumbering::new(&self.config);
+        // Open fenced blocks, where their content starts in `out` and the
+        // range of their opening fence
+        let mut open: Vec<(BlockHeader, usize, Range<usize>)> = Vec::new();
         let mut i = 0;
 
         while i < events.len() {
-            match &events[i] {
+            match &events[i].0 {
+                Event::Start(Tag::Heading { level, .. }) => {
+                    numbering.heading(*level);
+                    out.push(events[i].clone());
+                    i += 1;
+                }
                 Event::Start(Tag::Paragraph) => {
                     let mut paragraph = Vec::new();
                     let start_idx = i;
                     i += 1;
                     while i < events.len() {
-                        if matches!(events[i], Event::End(TagEnd::Paragraph)) {
+                        if matches!(events[i].0, Event::End(TagEnd::Paragraph)) {
                             break;
                         }
                         paragraph.push(events[i].clone());
                         i += 1;
                     }
+                    let range = &events[start_idx].1;
 
-                    let rewritten = self.rewrite_paragraph(&paragraph);
-                    if let Some(mut block_events) = rewritten {
+                    let (text, starts) = flatten(&paragraph);
+                    let fence = if starts.len() < paragraph.len() {
+                        None
+                    } else if close_fence_regex().is_match(&text) && !open.is_empty() {
+                        Some(None)
+                    } else {
+                        open_fence_regex().captures(&text).map(|caps| {
+                            let title = title_events(&paragraph, &starts, &text, &caps);
+                            Some(BlockHeader::new(&caps, title, &mut numbering))
+                        })
+                    };
+                    if let Some(fence) = fence {
+                        match fence {
+                            Some(mut header) => {
+                                self.claim_label(&mut header, &mut seen, range, &mut diagnostics);
+                                out.extend(header.open_events(range));
+                                open.push((header, out.len(), range.clone()));
+                            }
+                            None => close_block(&mut out, &mut open, &mut labels, range),
+                        }
+                        // Skip the paragraph end (if present)
+                        if i < events.len() {
+                            i += 1;
+                        }
+                        continue;
+                    }
+
+                    let rewritten =
+                        rewrite_paragraph(&paragraph, &mut numbering).map(|(mut header, body)| {
+                            self.claim_label(&mut header, &mut seen, range, &mut diagnostics);
+                            header.wrap(body, range)
+                        });
+                    if let Some((mut block_events, label)) = rewritten {
                         out.append(&mut block_events);
+                        labels.extend(label);
                     } else {
                         out.push(events[start_idx].clone());
                         out.append(&mut paragraph);
@@ -58,23 +243,94 @@ impl NotaBlockTransformer {
             }
         }
 
-        out
+        // Unclosed fences are reported and end with the document
+        let end = events.last().map(|(_, range)| range.end).unwrap_or(0);
+        while let Some((header, _, opening)) = open.last() {
+            diagnostics.push(self.diagnostic(
+                "nota.unclosed_block",
+                format!("`:::` {} block is never closed", header.kind),
+                header.kind.clone(),
+                opening,
+            ));
+            close_block(&mut out, &mut open, &mut labels, &(end..end));
+        }
+
+        (out, labels, diagnostics)
     }
 
-    fn rewrite_paragraph(&self, paragraph: &[Event<'static>]) -> Option<Vec<Event<'static>>> {
-        let mut flat = String::new();
-        for event in paragraph {
-            match event {
-                Event::Text(text) => flat.push_str(text),
-                Event::SoftBreak | Event::HardBreak => flat.push('\n'),
-                _ => break,
-            }
+    /// Keep the first block with each label; later ones lose their id, and
+    /// are reported when the label was written out
+    fn claim_label(
+        &self,
+        header: &mut BlockHeader,
+        seen: &mut HashSet<String>,
+        range: &Range<usize>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if header.label.is_empty() || seen.insert(header.label.clone()) {
+            return;
+        }
+        let label = std::mem::take(&mut header.label);
+        if !header.explicit {
+            return;
+        }
+        diagnostics.push(self.diagnostic(
+            "ref.duplicate_label",
+            format!("Nota block label '{}' is already used in this note", label),
+            label,
+            range,
+        ));
+    }
+
+    fn diagnostic(
+        &self,
+        code: &str,
+        message: String,
+        context: String,
+        range: &Range<usize>,
+    ) -> Diagnostic {
+        Diagnostic {
+            code: code.to_string(),
+            message,
+            severity: DiagnosticSeverity::Warning,
+            note_slug: self.note_slug.clone(),
+            source_path: self.source_path.clone(),
+            context: Some(context),
+            anchor: None,
+            span: self.source_map.map(|map| map.span(range)),
         }
+    }
+}
 
-        let caps = block_regex().captures(&flat)?;
-        let kind_raw = caps.name("kind")?.as_str();
-        let kind = kind_raw.to_lowercase();
+/// Kind, id, number and title of a block, from the `kind` / `label` /
+/// `title` captures of either syntax
+#[derive(Debug)]
+struct BlockHeader {
+    kind: String,
+    label: String,
+    /// Whether the label was written out rather than derived from the title
+    explicit: bool,
+    number: Option<String>,
+    /// Title as written, math and code included
+    title: String,
+    /// Inline events of the title, rendered in the heading
+    title_events: Vec<SpannedEvent>,
+}
+
+impl BlockHeader {
+    fn new(
+        caps: &Captures,
+        title_events: Vec<SpannedEvent>,
+        numbering: &mut NotaNumbering,
+    ) -> Self {
+        let kind = caps
+            .name("kind")
+            .map(|m| m.as_str().to_lowercase())
+            .unwrap_or_default();
         let title = caps.name("title").map(|m| m.as_str().trim()).unwrap_or("");
+        let explicit = caps
+            .name("label")
+            .is_some_and(|m| !m.as_str().trim().is_empty());
         let label = caps
             .name("label")
             .map(|m| m.as_str().trim().to_string())
@@ -84,63 +340,256 @@ impl NotaBlockTransformer {
             .strip_prefix("label=")
             .map(|s| s.to_string())
             .unwrap_or(label);
-        let consumed = caps
-            .name("rest")
-            .map(|m| m.start())
-            .unwrap_or_else(|| caps.get(0).map(|m| m.end()).unwrap_or(0));
-
-        // Build the body events by stripping the prefix text across the paragraph
-        let mut body_events: Vec<Event<'static>> = Vec::new();
-        let mut remaining = consumed;
-        for event in paragraph {
-            match event {
-                Event::Text(text) => {
-                    if remaining == 0 {
-                        body_events.push(event.clone());
-                        continue;
-                    }
-                    let len = text.len();
-                    if remaining >= len {
-                        remaining -= len;
-                        continue;
-                    }
-                    let new_text = text[remaining..].to_string();
-                    body_events.push(Event::Text(CowStr::Boxed(new_text.into_boxed_str())));
-                    remaining = 0;
-                }
-                Event::SoftBreak => {
-                    if remaining > 0 {
-                        remaining = remaining.saturating_sub(1);
-                        if remaining == 0 {
-                            body_events.push(Event::SoftBreak);
-                        }
-                    } else {
-                        body_events.push(Event::SoftBreak);
-                    }
-                }
-                Event::HardBreak => {
-                    if remaining > 0 {
-                        remaining = remaining.saturating_sub(1);
-                        if remaining == 0 {
-                            body_events.push(Event::HardBreak);
-                        }
-                    } else {
-                        body_events.push(Event::HardBreak);
-                    }
-                }
-                _ => body_events.push(event.clone()),
-            }
+        let number = numbering.next(&kind);
+        // Untitled, unlabelled blocks are still addressable by kind and number
+        let label = match (&number, label.is_empty()) {
+            (Some(number), true) => format!("{}-{}", kind, number.replace('.', "-")),
+            _ => label,
+        };
+        Self {
+            kind,
+            label,
+            explicit,
+            number,
+            title: title.to_string(),
+            title_events,
         }
+    }
 
-        let mut out = Vec::new();
-        out.push(Event::Html(CowStr::Boxed(
-            render_open(&kind, &label, title).into_boxed_str(),
-        )));
-        out.push(Event::Start(Tag::Paragraph));
+    /// Wrap a paragraph's body events in this block
+    fn wrap(
+        self,
+        body_events: Vec<SpannedEvent>,
+        paragraph_range: &Range<usize>,
+    ) -> (Vec<SpannedEvent>, Option<NotaLabel>) {
+        let mut out = self.open_events(paragraph_range);
+        let body_start = out.len();
+        out.push((Event::Start(Tag::Paragraph), paragraph_range.clone()));
         out.extend(body_events);
-        out.push(Event::End(TagEnd::Paragraph));
-        out.push(Event::Html(CowStr::Boxed(render_close().into_boxed_str())));
-        Some(out)
+        out.push((Event::End(TagEnd::Paragraph), paragraph_range.clone()));
+        out.push((
+            Event::Html(CowStr::Boxed(render_close().into_boxed_str())),
+            paragraph_range.clone(),
+        ));
+        let nota_label = self.into_label(&out[body_start..]);
+        (out, nota_label)
+    }
+
+    /// The block's opening markup. A title with math or code keeps its
+    /// inline events, so later passes render them as anywhere else.
+    fn open_events(&self, range: &Range<usize>) -> Vec<SpannedEvent> {
+        let html = |html: String| {
+            (
+                Event::Html(CowStr::Boxed(html.into_boxed_str())),
+                range.clone(),
+            )
+        };
+        let (kind, id, number) = (&self.kind, &self.label, self.number.as_deref());
+        if self
+            .title_events
+            .iter()
+            .all(|(event, _)| matches!(event, Event::Text(_)))
+        {
+            return vec![html(render_open(kind, id, number, &self.title))];
+        }
+        let mut events = vec![html(format!(
+            r#"{}<span class="nota-title">"#,
+            render_heading_start(kind, id, number)
+        ))];
+        events.extend(self.title_events.iter().cloned());
+        events.push(html(format!("</span>{}", HEADING_END)));
+        events
+    }
+
+    /// The label references use, given the block's body
+    fn into_label(self, body: &[SpannedEvent]) -> Option<NotaLabel> {
+        (!self.label.is_empty()).then(|| NotaLabel {
+            text: preview_text(body),
+            label: self.label,
+            kind: self.kind,
+            number: self.number,
+            title: self.title,
+        })
+    }
+}
+
+/// Close the innermost open fenced block
+fn close_block(
+    out: &mut Vec<SpannedEvent>,
+    open: &mut Vec<(BlockHeader, usize, Range<usize>)>,
+    labels: &mut Vec<NotaLabel>,
+    range: &Range<usize>,
+) {
+    let Some((header, start, _)) = open.pop() else {
+        return;
+    };
+    labels.extend(header.into_label(&out[start..]));
+    out.push((
+        Event::Html(CowStr::Boxed(render_close().into_boxed_str())),
+        range.clone(),
+    ));
+}
+
+/// Leading inline text of a paragraph as written (math as `$...$`, code in
+/// backticks, breaks as newlines), with where each event starts in it.
+/// Stops at the first event without a plain form, so fewer offsets than
+/// events means the text is only a prefix of the paragraph.
+fn flatten(paragraph: &[SpannedEvent]) -> (String, Vec<usize>) {
+    let mut flat = String::new();
+    let mut starts = Vec::new();
+    for (event, _) in paragraph {
+        let start = flat.len();
+        match event {
+            Event::Text(text) => flat.push_str(text),
+            Event::Code(code) => flat.push_str(&format!("`{code}`")),
+            Event::InlineMath(math) => flat.push_str(&format!("${math}$")),
+            Event::SoftBreak | Event::HardBreak => flat.push('\n'),
+            _ => break,
+        }
+        starts.push(start);
+    }
+    (flat, starts)
+}
+
+/// Events of a flattened paragraph covering `flat[from..to]`: text is cut
+/// to fit, other events are kept whole when they start inside
+fn events_between(
+    paragraph: &[SpannedEvent],
+    starts: &[usize],
+    flat: &str,
+    from: usize,
+    to: usize,
+) -> Vec<SpannedEvent> {
+    let mut events = Vec::new();
+    for (idx, (event, range)) in paragraph.iter().enumerate().take(starts.len()) {
+        let start = starts[idx];
+        let end = starts.get(idx + 1).copied().unwrap_or(flat.len());
+        if end <= from || start >= to {
+            continue;
+        }
+        match event {
+            Event::Text(text) => {
+                let (a, b) = (from.max(start) - start, to.min(end) - start);
+                events.push((
+                    Event::Text(CowStr::Boxed(text[a..b].to_string().into_boxed_str())),
+                    sub_range(range, text.len(), a, b),
+                ));
+            }
+            _ if start >= from => events.push((event.clone(), range.clone())),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Inline events of the `title` capture, trimmed like the title itself
+fn title_events(
+    paragraph: &[SpannedEvent],
+    starts: &[usize],
+    flat: &str,
+    caps: &Captures,
+) -> Vec<SpannedEvent> {
+    let Some(title) = caps.name("title") else {
+        return Vec::new();
+    };
+    let trimmed = title.as_str().trim();
+    let from = title.start() + (title.as_str().len() - title.as_str().trim_start().len());
+    events_between(paragraph, starts, flat, from, from + trimmed.len())
+}
+
+/// Surround `:::` fence lines with blank lines so each parses as a
+/// paragraph of its own; CommonMark would otherwise fold a closing fence
+/// into the preceding paragraph or list item. Returns the prepared
+/// markdown and the (sorted) offsets of the inserted newlines in it.
+pub(crate) fn isolate_fences(markdown: &str) -> (Cow<'_, str>, Vec<usize>) {
+    if !markdown.contains(":::") {
+        return (Cow::Borrowed(markdown), Vec::new());
+    }
+
+    let lines: Vec<&str> = markdown.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(markdown.len() + 16);
+    let mut inserted = Vec::new();
+    let mut code_fence: Option<&str> = None;
+    let blank = |line: Option<&&str>| line.is_none_or(|l| l.trim().is_empty());
+
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(marker) = code_fence {
+            if trimmed.starts_with(marker) {
+                code_fence = None;
+            }
+            out.push_str(line);
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            code_fence = Some(&trimmed[..3]);
+            out.push_str(line);
+            continue;
+        }
+
+        let is_fence =
+            close_fence_regex().is_match(trimmed) || open_fence_regex().is_match(trimmed);
+        if is_fence && idx > 0 && !blank(lines.get(idx - 1)) {
+            inserted.push(out.len());
+            out.push('\n');
+        }
+        out.push_str(line);
+        if is_fence && !blank(lines.get(idx + 1)) {
+            if !line.ends_with('\n') {
+                inserted.push(out.len());
+                out.push('\n');
+            }
+            inserted.push(out.len());
+            out.push('\n');
+        }
+    }
+
+    if inserted.is_empty() {
+        (Cow::Borrowed(markdown), inserted)
+    } else {
+        (Cow::Owned(out), inserted)
+    }
+}
+
+/// Split a paragraph starting with `@Kind[label]{Title}:` into the block's
+/// header and body events
+fn rewrite_paragraph(
+    paragraph: &[SpannedEvent],
+    numbering: &mut NotaNumbering,
+) -> Option<(BlockHeader, Vec<SpannedEvent>)> {
+    let (flat, starts) = flatten(paragraph);
+    let caps = block_regex().captures(&flat)?;
+    let title = title_events(paragraph, &starts, &flat, &caps);
+    let header = BlockHeader::new(&caps, title, numbering);
+    let consumed = caps
+        .name("rest")
+        .map(|m| m.start())
+        .unwrap_or_else(|| caps.get(0).map(|m| m.end()).unwrap_or(0));
+
+    // The body is everything after the prefix
+    let mut body_events = events_between(paragraph, &starts, &flat, consumed, flat.len());
+    body_events.extend(paragraph[starts.len()..].iter().cloned());
+
+    Some((header, body_events))
+}
+
+/// Plain text of a block body, cut to `PREVIEW_CHARS` on a char boundary
+fn preview_text(events: &[SpannedEvent]) -> String {
+    let mut text = String::new();
+    for (event, _) in events {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Item | TagEnd::Heading(_) | TagEnd::CodeBlock,
+            ) => text.push(' '),
+            _ => {}
+        }
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
     }
 }
 
@@ -154,21 +603,54 @@ fn block_regex() -> &'static Regex {
     })
 }
 
-fn render_open(kind: &str, id: &str, title: &str) -> String {
+fn open_fence_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^\s*:{3,}\s*(?P<kind>[A-Za-z]+)\s*(?:\[(?P<label>[^\]]*)\])?\s*(?:\{(?P<title>[^}]*)\})?\s*$",
+        )
+        .expect("valid regex")
+    })
+}
+
+fn close_fence_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*:{3,}\s*$").expect("valid regex"))
+}
+
+fn render_open(kind: &str, id: &str, number: Option<&str>, title: &str) -> String {
+    let mut html = render_heading_start(kind, id, number);
+    if !title.is_empty() {
+        html.push_str(&format!(
+            r#"<span class="nota-title">{}</span>"#,
+            html_escape(title)
+        ));
+    }
+    html.push_str(HEADING_END);
+    html
+}
+
+/// Block and heading markup up to the title; blocks without a label get no
+/// id
+fn render_heading_start(kind: &str, id: &str, number: Option<&str>) -> String {
     let kind_label = capitalize(kind);
+    let id = if id.is_empty() {
+        String::new()
+    } else {
+        format!(r#" id="{}""#, html_escape(id))
+    };
     let mut html = format!(
-        r#"<div class="nota-block nota-{}" id="{}"><div class="nota-block-heading"><span class="nota-kind">{}</span>"#,
+        r#"<div class="nota-block nota-{}"{}><div class="nota-block-heading"><span class="nota-kind">{}</span>"#,
         html_escape(kind),
-        html_escape(id),
+        id,
         html_escape(&kind_label)
     );
-    if !title.is_empty() {
+    if let Some(number) = number {
         html.push_str(&format!(
-            r#"<span class="nota-title">{}</span>"#,
-            html_escape(title)
+            r#"<span class="nota-number">{}</span>"#,
+            html_escape(number)
         ));
     }
-    html.push_str(r#"</div><div class="nota-block-body">"#);
     html
 }
 
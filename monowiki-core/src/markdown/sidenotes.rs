@@ -1,5 +1,6 @@
 //! Sidenote transformation for [^sidenote: text] syntax.
 
+use super::source_map::{strip_spans, MergedText, SpannedEvent};
 use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
 
 /// Transformer for sidenote syntax
@@ -16,23 +17,31 @@ impl SidenoteTransformer {
 
     /// Transform events, converting [^sidenote: text] to HTML spans
     pub fn transform(&self, events: Vec<Event<'_>>) -> Vec<Event<'static>> {
+        let events = events
+            .into_iter()
+            .map(|event| (self.event_into_static(event), 0..0))
+            .collect();
+        strip_spans(self.transform_spanned(events))
+    }
+
+    /// Like `transform`, but keeps each event's source range
+    pub fn transform_spanned(&self, events: Vec<SpannedEvent>) -> Vec<SpannedEvent> {
         let mut result = Vec::new();
         let mut in_code_block = false;
         let mut i = 0;
-        let events: Vec<_> = events.into_iter().collect();
 
         while i < events.len() {
             // Track code block context
-            match &events[i] {
+            match &events[i].0 {
                 Event::Start(Tag::CodeBlock(_)) => {
                     in_code_block = true;
-                    result.push(self.event_into_static(events[i].clone()));
+                    result.push(events[i].clone());
                     i += 1;
                     continue;
                 }
                 Event::End(TagEnd::CodeBlock) => {
                     in_code_block = false;
-                    result.push(self.event_into_static(events[i].clone()));
+                    result.push(events[i].clone());
                     i += 1;
                     continue;
                 }
@@ -41,17 +50,17 @@ impl SidenoteTransformer {
 
             // Skip sidenote processing inside code blocks
             if in_code_block {
-                result.push(self.event_into_static(events[i].clone()));
+                result.push(events[i].clone());
                 i += 1;
                 continue;
             }
 
-            if let Event::Text(_) = &events[i] {
+            if let Event::Text(_) = &events[i].0 {
                 // Merge consecutive Text events (pulldown-cmark splits [^sidenote:] across events)
-                let mut merged_text = String::new();
+                let mut merged = MergedText::new();
                 while i < events.len() {
-                    if let Event::Text(text) = &events[i] {
-                        merged_text.push_str(text.as_ref());
+                    if let (Event::Text(text), range) = &events[i] {
+                        merged.push(text, range.clone());
                         i += 1;
                     } else {
                         break;
@@ -59,13 +68,17 @@ impl SidenoteTransformer {
                 }
 
                 // Check if merged text contains sidenote syntax
-                if merged_text.contains("[^sidenote:") && merged_text.contains("]") {
-                    result.extend(self.process_sidenotes(&merged_text));
+                if merged.text.contains("[^sidenote:") && merged.text.contains("]") {
+                    result.extend(self.process_sidenotes(&merged));
                 } else {
-                    result.push(Event::Text(CowStr::Boxed(merged_text.into_boxed_str())));
+                    let range = merged.range();
+                    result.push((
+                        Event::Text(CowStr::Boxed(merged.text.into_boxed_str())),
+                        range,
+                    ));
                 }
             } else {
-                result.push(self.event_into_static(events[i].clone()));
+                result.push(events[i].clone());
                 i += 1;
             }
         }
@@ -73,23 +86,26 @@ impl SidenoteTransformer {
         result
     }
 
-    fn process_sidenotes(&self, text: &str) -> Vec<Event<'static>> {
+    fn process_sidenotes(&self, merged: &MergedText) -> Vec<SpannedEvent> {
         let mut events = Vec::new();
-        let mut remaining = text;
+        let text = merged.text.as_str();
+        let mut pos = 0;
         const SIDENOTE_PREFIX: &str = "[^sidenote:";
 
-        while let Some(start) = remaining.find(SIDENOTE_PREFIX) {
+        while let Some(found) = text[pos..].find(SIDENOTE_PREFIX) {
+            let start = pos + found;
             // Add text before the sidenote
-            if start > 0 {
-                events.push(Event::Text(CowStr::Boxed(
-                    remaining[..start].to_string().into_boxed_str(),
-                )));
+            if start > pos {
+                events.push((
+                    Event::Text(CowStr::Boxed(text[pos..start].to_string().into_boxed_str())),
+                    merged.source_range(pos, start),
+                ));
             }
 
             // Find the closing ]
             let search_start = start + SIDENOTE_PREFIX.len();
-            if let Some(end) = find_closing_bracket(remaining, search_start) {
-                let content = &remaining[search_start..end];
+            if let Some(end) = find_closing_bracket(text, search_start) {
+                let content = &text[search_start..end];
 
                 // Increment counter
                 let num = self.counter.get() + 1;
@@ -113,25 +129,29 @@ impl SidenoteTransformer {
                     content = rendered_content
                 );
 
-                events.push(Event::InlineHtml(CowStr::Boxed(
-                    sidenote_html.into_boxed_str(),
-                )));
+                events.push((
+                    Event::InlineHtml(CowStr::Boxed(sidenote_html.into_boxed_str())),
+                    merged.source_range(start, end + 1),
+                ));
 
-                remaining = &remaining[end + 1..];
+                pos = end + 1;
             } else {
                 // No closing ], treat as literal text
-                events.push(Event::Text(CowStr::Boxed(
-                    remaining.to_string().into_boxed_str(),
-                )));
+                events.push((
+                    Event::Text(CowStr::Boxed(text[start..].to_string().into_boxed_str())),
+                    merged.source_range(start, text.len()),
+                ));
+                pos = text.len();
                 break;
             }
         }
 
         // Add any remaining text
-        if !remaining.is_empty() {
-            events.push(Event::Text(CowStr::Boxed(
-                remaining.to_string().into_boxed_str(),
-            )));
+        if pos < text.len() {
+            events.push((
+                Event::Text(CowStr::Boxed(text[pos..].to_string().into_boxed_str())),
+                merged.source_range(pos, text.len()),
+            ));
         }
 
         events
@@ -0,0 +1,176 @@
+//! Source offset tracking through the transformer pipeline.
+//!
+//! Events are paired with the byte range of the markdown they came from
+//! (pulldown-cmark's offset iterator). Transformers that split or merge text
+//! map sub-slices back to source offsets so the diagnostics they emit can
+//! point at a line and column.
+
+use crate::models::SourceSpan;
+use pulldown_cmark::Event;
+use std::ops::Range;
+
+/// A markdown event with the byte range of the source it was produced from
+pub type SpannedEvent = (Event<'static>, Range<usize>);
+
+/// Line index for converting byte offsets into line/column positions
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+    /// Lines preceding the markdown in the original file (e.g. frontmatter)
+    line_offset: usize,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str, line_offset: usize) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+            line_offset,
+        }
+    }
+
+    /// 1-based line and column (in characters) of a byte offset
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line_idx = self
+            .line_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+        let line_start = self.line_starts[line_idx];
+        let column = self
+            .source
+            .get(line_start..offset)
+            .map(|prefix| prefix.chars().count())
+            .unwrap_or(0);
+        (line_idx + 1 + self.line_offset, column + 1)
+    }
+
+    /// Span covering a byte range (end exclusive)
+    pub fn span(&self, range: &Range<usize>) -> SourceSpan {
+        let (line, column) = self.position(range.start);
+        let (end_line, end_column) = self.position(range.end.max(range.start));
+        SourceSpan {
+            line,
+            column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+/// Map a sub-slice `a..b` of an event's text back to source offsets.
+///
+/// When the text is a verbatim copy of its source range the mapping is
+/// exact; otherwise (escapes, entities) the whole event range is used.
+pub fn sub_range(range: &Range<usize>, text_len: usize, a: usize, b: usize) -> Range<usize> {
+    if range.len() == text_len {
+        range.start + a..range.start + b
+    } else {
+        range.clone()
+    }
+}
+
+/// Text merged from consecutive `Text` events, remembering where each
+/// piece came from in the source.
+#[derive(Debug, Default)]
+pub struct MergedText {
+    pub text: String,
+    pieces: Vec<(usize, usize, Range<usize>)>, // (offset in text, len, source range)
+}
+
+impl MergedText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, text: &str, range: Range<usize>) {
+        self.pieces.push((self.text.len(), text.len(), range));
+        self.text.push_str(text);
+    }
+
+    /// Source range covering all merged pieces
+    pub fn range(&self) -> Range<usize> {
+        match (self.pieces.first(), self.pieces.last()) {
+            (Some(first), Some(last)) => first.2.start..last.2.end,
+            _ => 0..0,
+        }
+    }
+
+    /// Map a byte range of the merged text back to a source range
+    pub fn source_range(&self, a: usize, b: usize) -> Range<usize> {
+        let start = self.source_offset(a, false);
+        let end = self.source_offset(b, true);
+        start..end.max(start)
+    }
+
+    fn source_offset(&self, pos: usize, is_end: bool) -> usize {
+        let piece = self.pieces.iter().find(|(offset, len, _)| {
+            if is_end {
+                pos > *offset && pos <= offset + len
+            } else {
+                pos >= *offset && pos < offset + len
+            }
+        });
+        match piece {
+            Some((offset, len, range)) if range.len() == *len => range.start + (pos - offset),
+            Some((_, _, range)) if is_end => range.end,
+            Some((_, _, range)) => range.start,
+            None => {
+                let range = self.range();
+                if is_end {
+                    range.end
+                } else {
+                    range.start
+                }
+            }
+        }
+    }
+}
+
+/// Attach empty spans to events that did not come from the source
+pub fn unspanned(events: Vec<Event<'static>>) -> Vec<SpannedEvent> {
+    events.into_iter().map(|event| (event, 0..0)).collect()
+}
+
+/// Drop spans once no later stage needs them
+pub fn strip_spans(events: Vec<SpannedEvent>) -> Vec<Event<'static>> {
+    events.into_iter().map(|(event, _)| event).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_one_based_and_offset() {
+        let map = SourceMap::new("first\nsecond line\n", 3);
+        assert_eq!(map.position(0), (4, 1));
+        assert_eq!(map.position(6), (5, 1));
+        assert_eq!(map.position(13), (5, 8));
+        let span = map.span(&(6..12));
+        assert_eq!(
+            (span.line, span.column, span.end_line, span.end_column),
+            (5, 1, 5, 7)
+        );
+    }
+
+    #[test]
+    fn merged_text_maps_offsets_back_to_source() {
+        // "a [[b]]" split by pulldown-cmark into "a ", "[", "[b]]" at 10..17
+        let mut merged = MergedText::new();
+        merged.push("a ", 10..12);
+        merged.push("[", 12..13);
+        merged.push("[b]]", 13..17);
+        assert_eq!(merged.source_range(2, 7), 12..17);
+
+        // Escaped text ("\\[" -> "[") falls back to the whole piece
+        let mut merged = MergedText::new();
+        merged.push("[", 0..2);
+        merged.push("x", 2..3);
+        assert_eq!(merged.source_range(0, 2), 0..3);
+    }
+}
@@ -1,7 +1,9 @@
 //! Integration tests for markdown processing
 
 use super::*;
-use crate::bibliography::BibliographyStore;
+use crate::bibliography::{BibliographyStore, CitationStyle};
+use crate::config::{MathConfig, MathMacros, MathRender};
+use crate::models::{DiagnosticSeverity, SourceSpan};
 use std::collections::HashMap;
 use std::io::Write;
 use tempfile::NamedTempFile;
@@ -13,7 +15,7 @@ fn test_full_pipeline_with_wikilinks() {
     slug_map.insert("rust-safety".to_string(), "Rust Safety".to_string());
 
     let processor = MarkdownProcessor::new();
-    let (html, links, toc, _) = processor.convert(markdown, &slug_map, "/", None, None, None, None);
+    let (html, links, toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
 
     println!("Input: {}", markdown);
     println!("Output: {}", html);
@@ -33,7 +35,7 @@ fn test_wikilink_in_paragraph() {
     let slug_map = HashMap::new();
 
     let processor = MarkdownProcessor::new();
-    let (html, links, toc, _) = processor.convert(markdown, &slug_map, "/", None, None, None, None);
+    let (html, links, toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
 
     println!("HTML: {}", html);
     println!("Links: {:?}", links);
@@ -49,15 +51,7 @@ fn test_display_math_renders() {
     let markdown = "$$ x^2 + y^2 $$";
     let slug_map = HashMap::new();
     let processor = MarkdownProcessor::new();
-    let (html, _links, _toc, _) = processor.convert(
-        markdown,
-        &slug_map,
-        "/",
-        None,
-        None,
-        None,
-        None,
-    );
+    let (html, _links, _toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
 
     assert!(
         html.contains("math-display"),
@@ -75,8 +69,7 @@ fn test_nota_block_transformer_wraps_paragraph() {
     let mut slug_map = HashMap::new();
     slug_map.insert("eval".to_string(), "/eval".to_string());
     let processor = MarkdownProcessor::new();
-    let (html, links, _toc, _) =
-        processor.convert(markdown, &slug_map, "/", None, None, None, None);
+    let (html, links, _toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
 
     assert!(
         html.contains("nota-block nota-definition"),
@@ -104,21 +97,21 @@ fn test_citations_render_references() {
     write!(tmp, "{}", bibtex).unwrap();
 
     let mut store = BibliographyStore::new();
-    let bibliography = store.collect(&vec![tmp.path().to_path_buf()]);
+    let bibliography = store.collect(&[tmp.path().to_path_buf()]);
+    let style = CitationStyle::default();
     let ctx = citations::CitationContext {
         bibliography: &bibliography,
+        style: &style,
     };
 
     let slug_map = HashMap::new();
     let processor = MarkdownProcessor::new();
     let (html, _links, _toc, _) = processor.convert(
         "See [@knuth1990] for details.",
-        &slug_map,
-        "/",
-        None,
-        Some(&ctx),
-        None,
-        None,
+        &RenderContext {
+            citations: Some(&ctx),
+            ..RenderContext::new(&slug_map, "/")
+        },
     );
 
     assert!(
@@ -132,3 +125,478 @@ fn test_citations_render_references() {
         "Reference entry should include author information"
     );
 }
+
+#[test]
+fn test_diagnostics_carry_source_spans() {
+    let markdown = "# Title\n\nSome *emphasis* then [[Missing Note]] here.\n\nCite [@nobody].\n";
+    let bibliography = crate::bibliography::Bibliography::default();
+    let style = CitationStyle::default();
+    let ctx = citations::CitationContext {
+        bibliography: &bibliography,
+        style: &style,
+    };
+    let slug_map = HashMap::new();
+    let processor = MarkdownProcessor::new();
+    // Pretend three lines of frontmatter preceded the body
+    let (_html, _links, _toc, diags) = processor.convert(
+        markdown,
+        &RenderContext {
+            citations: Some(&ctx),
+            line_offset: 3,
+            ..RenderContext::new(&slug_map, "/")
+        },
+    );
+
+    let link = diags
+        .iter()
+        .find(|d| d.code == "link.unresolved")
+        .expect("unresolved link diagnostic");
+    assert_eq!(
+        link.span,
+        Some(SourceSpan {
+            line: 6,
+            column: 22,
+            end_line: 6,
+            end_column: 38,
+        })
+    );
+
+    let cite = diags
+        .iter()
+        .find(|d| d.code == "citation.missing_entry")
+        .expect("missing citation diagnostic");
+    assert_eq!(cite.span.map(|s| (s.line, s.column)), Some((8, 6)));
+}
+
+#[test]
+fn test_callout_body_keeps_other_transforms() {
+    let markdown = "> [!tip] Try $x^2$\n> See [[Eval]] and $y$.[^sidenote: Aside.]\n";
+    let mut slug_map = HashMap::new();
+    slug_map.insert("eval".to_string(), "/eval.html".to_string());
+    let processor = MarkdownProcessor::new();
+    let (html, links, _toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert!(html.contains("class=\"callout callout-tip\""));
+    assert!(html.contains("Try <span class=\"math math-inline\""));
+    assert!(html.contains("href=\"/eval.html\""));
+    assert!(html.contains("<span class=\"sidenote\""));
+    assert_eq!(links, vec!["eval"]);
+}
+
+#[test]
+fn test_heading_ids_are_unique_and_toc_nests() {
+    let markdown =
+        "## Examples\n\n### Using `map`\n\n#### Deep\n\n## Examples\n\n## Setup {#examples-1}\n";
+    let slug_map = HashMap::new();
+    let processor = MarkdownProcessor::new();
+    let (html, _links, toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert!(html.contains("<h2 id=\"examples\">"));
+    assert!(html.contains("<h3 id=\"using-map\">Using <code>map</code>"));
+    // The explicit id keeps its name; the repeated heading skips past it
+    assert!(html.contains("<h2 id=\"examples-2\">"));
+    assert!(html.contains("<h2 id=\"examples-1\">Setup"));
+    assert_eq!(
+        toc.unwrap(),
+        "<nav class=\"toc-nav\"><h3>Contents</h3><ul class=\"toc-list\">\
+         <li class=\"toc-level-2\"><a href=\"#examples\">Examples</a>\
+         <ul><li class=\"toc-level-3\"><a href=\"#using-map\">Using map</a>\
+         <ul><li class=\"toc-level-4\"><a href=\"#deep\">Deep</a></li></ul></li></ul></li>\
+         <li class=\"toc-level-2\"><a href=\"#examples-2\">Examples</a></li>\
+         <li class=\"toc-level-2\"><a href=\"#examples-1\">Setup</a></li></ul></nav>"
+    );
+
+    let processor = MarkdownProcessor::new().with_toc_depth(2, 3);
+    let (_html, _links, toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
+    let toc = toc.unwrap();
+    assert!(toc.contains("#using-map") && !toc.contains("#deep"));
+
+    let (_html, _links, toc, _) = MarkdownProcessor::new()
+        .with_toc_depth(5, 6)
+        .convert(markdown, &RenderContext::new(&slug_map, "/"));
+    assert!(toc.is_none());
+}
+
+#[test]
+fn test_nota_blocks_are_numbered_and_referenced() {
+    let markdown = "By @ref[thm:main] and [[#zorn|the lemma]], see @ref[missing].\n\n\
+                    @Lemma[zorn]{Zorn}: Chains have bounds.\n\n\
+                    @Theorem[thm:main]{Main}: It holds.\n\n\
+                    @Proof{}: Apply [[#zorn]].\n\n\
+                    @Theorem{}: Another.\n";
+    let slug_map = HashMap::new();
+    let processor = MarkdownProcessor::new();
+    let (html, _links, _toc, diags) =
+        processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert!(html
+        .contains("<span class=\"nota-kind\">Theorem</span><span class=\"nota-number\">1</span>"));
+    assert!(
+        html.contains("id=\"theorem-2\""),
+        "untitled blocks get an id"
+    );
+    assert!(html.contains(
+        "<a class=\"nota-ref\" href=\"#thm:main\" data-nota-kind=\"theorem\">Theorem 1</a>"
+    ));
+    assert!(html.contains("href=\"#zorn\" data-nota-kind=\"lemma\">the lemma</a>"));
+    assert!(html.contains(
+        "Apply <a class=\"nota-ref\" href=\"#zorn\" data-nota-kind=\"lemma\">Lemma 1</a>"
+    ));
+    assert!(
+        html.contains("<div class=\"nota-block nota-proof\"><div"),
+        "unlabelled proofs get no id"
+    );
+    assert!(!html.contains("id=\"\""));
+    assert!(html.contains("title=\"missing\">??</span>"));
+    let unresolved: Vec<_> = diags
+        .iter()
+        .filter(|d| d.code == "ref.unresolved")
+        .collect();
+    assert_eq!(unresolved.len(), 1);
+    assert_eq!(unresolved[0].context.as_deref(), Some("missing"));
+
+    // A shared counter numbered within `##` sections
+    let nota = NotaConfig {
+        counter: crate::config::NotaCounter::Shared,
+        by_section: true,
+        ..NotaConfig::default()
+    };
+    let labels = MarkdownProcessor::new().with_nota(nota).nota_labels(
+        "## One\n\n@Lemma[a]{A}: x\n\n@Theorem[b]{B}: y\n\n## Two\n\n@Theorem[c]{C}: z\n",
+    );
+    let refs: Vec<_> = labels.iter().map(NotaLabel::reference_text).collect();
+    assert_eq!(refs, ["Lemma 1.1", "Theorem 1.2", "Theorem 2.1"]);
+    assert_eq!(labels[2].text, "z");
+}
+
+#[test]
+fn test_duplicate_nota_labels_are_reported() {
+    let markdown = "@Lemma[key]{First}: One.\n\n\
+                    ::: theorem [key]\nTwo.\n:::\n\n\
+                    @Remark{Note}: x\n\n@Remark{Note}: y\n\n\
+                    See @ref[key].\n";
+    let slug_map = HashMap::new();
+    let processor = MarkdownProcessor::new();
+    let (html, _links, _toc, diags) =
+        processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert_eq!(html.matches("id=\"key\"").count(), 1);
+    assert_eq!(html.matches("id=\"note\"").count(), 1);
+    assert!(html.contains("href=\"#key\" data-nota-kind=\"lemma\">Lemma 1</a>"));
+    let duplicates: Vec<_> = diags
+        .iter()
+        .filter(|d| d.code == "ref.duplicate_label")
+        .collect();
+    assert_eq!(duplicates.len(), 1, "derived labels are not reported");
+    assert_eq!(duplicates[0].context.as_deref(), Some("key"));
+    assert_eq!(duplicates[0].span.as_ref().map(|s| s.line), Some(3));
+
+    let labels = processor.nota_labels(markdown);
+    let ids: Vec<_> = labels.iter().map(|l| l.label.as_str()).collect();
+    assert_eq!(ids, ["key", "note"]);
+}
+
+#[test]
+fn test_fenced_nota_blocks_nest_and_wrap_block_content() {
+    let markdown = "::: example [ex] {Squares}\n\
+                    Two paragraphs.\n\n\
+                    Second, with a list:\n\
+                    - one\n\
+                    - two\n\n\
+                    ::: proof\n\
+                    $$\nx^2 \\ge 0\n$$\n\
+                    :::\n\
+                    :::\n\n\
+                    See @ref[ex]. Use [[missing]] here.\n\n\
+                    @Theorem[t]{Shorthand}: Still works.\n";
+    let slug_map = HashMap::new();
+    let processor = MarkdownProcessor::new();
+    let (html, _links, _toc, diags) = processor.convert(
+        markdown,
+        &RenderContext {
+            line_offset: 3,
+            ..RenderContext::new(&slug_map, "/")
+        },
+    );
+
+    let example = html.find("id=\"ex\"").unwrap();
+    let list = html.find("<li>two</li>").unwrap();
+    let proof = html.find("nota-proof").unwrap();
+    let after = html.find("See <a class=\"nota-ref\"").unwrap();
+    assert!(example < list && list < proof && proof < after);
+    assert!(!html.contains(":::"), "{html}");
+    // The example closes after the nested proof
+    assert_eq!(html[proof..after].matches("</div></div>").count(), 2);
+    assert!(html.contains("<div class=\"nota-block nota-proof\"><div"));
+    assert!(html.contains(">Example 1</a>"));
+    assert!(
+        html.contains("<span class=\"nota-number\">1</span><span class=\"nota-title\">Shorthand")
+    );
+
+    // Source lines still point into the original markdown
+    let unresolved = diags
+        .iter()
+        .find(|d| d.code == "link.unresolved")
+        .expect("unresolved link diagnostic");
+    assert_eq!(unresolved.span.as_ref().map(|s| s.line), Some(18));
+
+    let labels = processor.nota_labels(markdown);
+    assert_eq!(labels[0].label, "ex");
+    assert_eq!(
+        labels[0].text,
+        "Two paragraphs. Second, with a list: one two"
+    );
+}
+
+#[test]
+fn test_unclosed_nota_block_is_reported() {
+    let markdown = "Intro.\n\n::: proof\nStep one.\n\n::: lemma [inner]\nNested.\n:::\n\nRest.\n";
+    let slug_map = HashMap::new();
+    let (html, _links, _toc, diags) =
+        MarkdownProcessor::new().convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert!(
+        html.trim_end().ends_with("<p>Rest.</p>\n</div></div>"),
+        "{html}"
+    );
+    let unclosed: Vec<_> = diags
+        .iter()
+        .filter(|d| d.code == "nota.unclosed_block")
+        .collect();
+    assert_eq!(unclosed.len(), 1);
+    assert_eq!(unclosed[0].context.as_deref(), Some("proof"));
+    assert_eq!(unclosed[0].span.as_ref().map(|s| s.line), Some(3));
+}
+
+#[test]
+fn test_nota_titles_with_math_and_code() {
+    let markdown = "::: theorem [thm:t] {Bound on $x$}\n\
+                    Body.\n\
+                    :::\n\n\
+                    ::: remark {Uses `foo`}\n\
+                    Text.\n\
+                    :::\n\n\
+                    @Lemma[l]{Small $\\epsilon$}: Take $\\delta$ and `bar`.\n\n\
+                    See @ref[thm:t].\n";
+    let slug_map = HashMap::new();
+    let processor = MarkdownProcessor::new();
+    let (html, _links, _toc, _) = processor.convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert!(!html.contains(":::"), "{html}");
+    assert!(html.contains("id=\"thm:t\""));
+    assert!(html.contains("<span class=\"nota-title\">Bound on <span class=\"math math-inline\""));
+    assert!(html.contains("<span class=\"nota-title\">Uses <code>foo</code></span>"));
+    assert!(html.contains("<span class=\"nota-title\">Small <span class=\"math math-inline\""));
+    let body = html.find("<p>Take ").expect("lemma body");
+    assert!(html[body..].contains("<code>bar</code>"));
+    assert!(html.contains(">Theorem 1</a>"));
+
+    let labels = processor.nota_labels(markdown);
+    assert_eq!(labels[0].title, "Bound on $x$");
+    assert_eq!(labels[0].text, "Body.");
+    assert_eq!(labels[1].label, "uses-foo");
+    assert_eq!(labels[2].text, "Take and bar.");
+}
+
+#[test]
+fn test_equation_labels_are_numbered_and_referenced() {
+    let markdown = "$$\nE = mc^2 \\label{eq:energy}\n$$\n\n\
+                    By \\eqref{eq:energy}, eq. \\ref{eq:energy} and [[#eq:energy]]. \
+                    Also [[alg#eq:sum]] and \\eqref{eq:missing}, [[#eq:gone]].\n";
+    let slug_map = HashMap::from([("alg".to_string(), "/alg.html".to_string())]);
+    let labels = HashMap::from([(
+        "alg".to_string(),
+        MarkdownProcessor::new().nota_labels("$$ a + b \\label{eq:sum} $$\n"),
+    )]);
+    let (html, links, _toc, diags) = MarkdownProcessor::new().convert(
+        markdown,
+        &RenderContext {
+            note_slug: Some("physics"),
+            labels: Some(&labels),
+            ..RenderContext::new(&slug_map, "/")
+        },
+    );
+
+    assert!(html.contains(
+        "<div class=\"math-equation\" id=\"eq:energy\"><div class=\"math math-display\""
+    ));
+    assert!(html.contains("<span class=\"equation-number\">(1)</span></div>"));
+    assert!(
+        !html.contains("\\label"),
+        "labels are stripped from the LaTeX"
+    );
+    assert!(html.contains(
+        "By <a class=\"nota-ref\" href=\"#eq:energy\" data-nota-kind=\"equation\">(1)</a>"
+    ));
+    assert!(html.contains(
+        "eq. <a class=\"nota-ref\" href=\"#eq:energy\" data-nota-kind=\"equation\">1</a>"
+    ));
+    assert!(html.contains("data-nota-kind=\"equation\">Equation (1)</a>"));
+    assert!(html.contains("href=\"/alg.html#eq:sum\" data-nota-kind=\"equation\">Equation (1)</a>"));
+    assert!(html.contains("title=\"eq:missing\">(??)</span>"));
+    assert!(links.contains(&"alg".to_string()));
+
+    let unresolved: Vec<_> = diags
+        .iter()
+        .filter(|d| d.code == "math.unresolved_ref")
+        .filter_map(|d| d.context.as_deref())
+        .collect();
+    assert_eq!(unresolved, vec!["eq:missing", "#eq:gone"]);
+}
+
+#[test]
+fn test_duplicate_and_extra_equation_labels_are_reported() {
+    let markdown = "@Lemma[key]{Key}: x\n\n\
+                    $$ a \\label{eq:a} $$\n\n\
+                    $$ b \\label{eq:a} $$\n\n\
+                    $$ c \\label{key} $$\n\n\
+                    $$\n\\begin{align}\nx &= 1 \\label{eq:x} \\\\\ny &= 2 \\label{eq:y}\n\\end{align}\n$$\n";
+    let slug_map = HashMap::new();
+    let (html, _links, _toc, diags) =
+        MarkdownProcessor::new().convert(markdown, &RenderContext::new(&slug_map, "/"));
+
+    assert_eq!(html.matches("id=\"eq:a\"").count(), 1);
+    assert_eq!(html.matches("id=\"key\"").count(), 1);
+    assert_eq!(html.matches("<div class=\"math-equation\">").count(), 2);
+    assert!(!html.contains("\\label"), "extra labels are stripped too");
+    let duplicates: Vec<_> = diags
+        .iter()
+        .filter(|d| d.code == "math.duplicate_label")
+        .collect();
+    assert_eq!(duplicates.len(), 2);
+    assert_eq!(duplicates[0].span.as_ref().map(|s| s.line), Some(5));
+    let extra: Vec<_> = diags
+        .iter()
+        .filter(|d| d.code == "math.extra_label")
+        .collect();
+    assert_eq!(extra.len(), 1);
+    assert!(extra[0].message.contains("'eq:y'"));
+}
+
+#[test]
+fn test_math_renders_to_mathml_with_mathjax_fallback() {
+    let markdown = "Inline $x^2$ math.\n\n\
+                    $$\n\\int_0^1 f(x)\\,dx \\label{eq:area}\n$$\n\n\
+                    $$\n\\newcommand{\\R}{\\mathbb{R}} \\R\n$$\n";
+    let math = MathConfig {
+        render: MathRender::Mathml,
+        ..Default::default()
+    };
+    let (html, _links, _toc, diags) = MarkdownProcessor::new().with_math(math).convert(
+        markdown,
+        &RenderContext {
+            note_slug: Some("calculus"),
+            source_path: Some("calculus.md"),
+            ..RenderContext::new(&HashMap::new(), "/")
+        },
+    );
+
+    assert!(html.contains(
+        "Inline <span class=\"math math-inline\"><math display=\"inline\"><semantics>\
+         <msup><mi>x</mi><mn>2</mn></msup>"
+    ));
+    assert!(html.contains(
+        "<div class=\"math-equation\" id=\"eq:area\"><div class=\"math math-display\">\
+         <math display=\"block\">"
+    ));
+    assert!(
+        !html.contains("<p><div"),
+        "display math is not wrapped in a paragraph"
+    );
+
+    // Unsupported constructs are left to MathJax and reported
+    assert!(html.contains("<div class=\"math math-display\" aria-label="));
+    assert!(needs_mathjax(&html));
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].code, "math.fallback");
+    assert_eq!(diags[0].severity, DiagnosticSeverity::Info);
+    assert_eq!(diags[0].span.as_ref().map(|s| s.line), Some(7));
+}
+
+#[test]
+fn test_math_macros_expand_and_are_checked() {
+    let math = MathConfig {
+        render: MathRender::Mathml,
+        macros: MathMacros::from([
+            ("R".to_string(), r"\mathbb{R}".to_string()),
+            (
+                r"\norm".to_string(),
+                r"\left\lVert #1 \right\rVert".to_string(),
+            ),
+        ]),
+    };
+    // The note redefines \R and adds a macro of its own
+    let note_macros = MathMacros::from([
+        ("R".to_string(), r"\mathbf{R}".to_string()),
+        ("e".to_string(), r"\varepsilon".to_string()),
+    ]);
+    let markdown = "Take $\\norm{x} < \\e$ in $\\R$.\n\nBroken: $\\norm$ and $\\frac{1}{2$.\n";
+    let (html, _links, _toc, diags) = MarkdownProcessor::new().with_math(math).convert(
+        markdown,
+        &RenderContext {
+            note_macros: Some(&note_macros),
+            ..RenderContext::new(&HashMap::new(), "/")
+        },
+    );
+
+    assert!(html.contains("<mo fence=\"true\" stretchy=\"true\">‖</mo><mi>x</mi>"));
+    assert!(html.contains("<mi>ε</mi>"));
+    assert!(html.contains("<mi>𝐑</mi>"));
+
+    // Malformed math is reported and left to MathJax without a fallback note
+    let codes: Vec<_> = diags.iter().map(|d| d.code.as_str()).collect();
+    assert_eq!(codes, vec!["math.macro_arity", "math.unbalanced_braces"]);
+    assert_eq!(diags[0].span.as_ref().map(|s| s.line), Some(3));
+    assert!(needs_mathjax(&html));
+}
+
+#[test]
+fn test_citation_style_formats_labels_and_references() {
+    let bibtex = r#"
+@book{pierce2002,
+  title = {Types and Programming Languages},
+  author = {Pierce, Benjamin C.},
+  publisher = {MIT Press},
+  date = {2002}
+}
+@article{knuth1984,
+  title = {Literate Programming & Friends},
+  author = {Knuth, Donald E.},
+  journal = {The Computer Journal},
+  date = {1984}
+}
+"#;
+    let mut tmp = NamedTempFile::new().unwrap();
+    write!(tmp, "{}", bibtex).unwrap();
+    let bibliography = BibliographyStore::new().collect(&[tmp.path().to_path_buf()]);
+    let render = |style: &CitationStyle| {
+        let ctx = citations::CitationContext {
+            bibliography: &bibliography,
+            style,
+        };
+        let (html, _links, _toc, _) = MarkdownProcessor::new().convert(
+            "See [@pierce2002] and [@knuth1984; @pierce2002].",
+            &RenderContext {
+                citations: Some(&ctx),
+                ..RenderContext::new(&HashMap::new(), "/")
+            },
+        );
+        html
+    };
+
+    // Author-date labels, alphabetical reference list
+    let apa = render(&CitationStyle::by_name("apa").unwrap());
+    assert!(apa
+        .contains("id=\"cite-1\" data-cites=\"pierce2002\"><a href=\"#ref-1\">(Pierce, 2002)</a>"));
+    assert!(apa.contains(">(Knuth, 1984; Pierce, 2002)</a>"));
+    let knuth = apa
+        .find("<li id=\"ref-2\">Knuth, D. E. (1984). Literate Programming &amp; Friends.")
+        .unwrap();
+    let pierce = apa.find("<li id=\"ref-1\">Pierce, B. C. (2002).").unwrap();
+    assert!(knuth < pierce);
+
+    // Numeric labels in order of first citation
+    let ieee = render(&CitationStyle::default());
+    assert!(ieee.contains("<a href=\"#ref-1\">[1]</a>"));
+    assert!(ieee.contains("<li id=\"ref-1\"><span class=\"ref-label\">[1]</span> B. C. Pierce,"));
+}
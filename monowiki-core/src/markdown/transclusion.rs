@@ -0,0 +1,480 @@
+//! Note transclusion for `![[note]]` and `![[note#section]]` embeds.
+//!
+//! The wikilink transformer leaves a marker comment where an embed appears.
+//! Once every note's source is known, `MarkdownProcessor::expand_embeds`
+//! replaces each marker with the rendered content of the embedded note (or
+//! one of its sections), recursing into nested embeds and refusing cycles.
+//! Ids inside embedded content that are already used on the page get a
+//! `-N` suffix, like repeated heading titles.
+
+use super::block_ids::scan_block_ids;
+use super::{heading_text, html_escape, HeadingIds, MarkdownProcessor, RenderContext};
+use crate::models::{Diagnostic, DiagnosticSeverity};
+use crate::slug::slugify;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashMap};
+
+static EMBED_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(<p>\s*)?<!--monowiki:embed ([^\s#]+)(?:#(\S+))? -->(\s*</p>)?").unwrap()
+});
+
+static ID_ATTR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\s)id="([^"]*)""#).unwrap());
+static HREF_FRAGMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r##"href="#([^"]*)""##).unwrap());
+
+/// Marker left in rendered HTML where a note should be embedded
+pub fn embed_marker(slug: &str, section: Option<&str>) -> String {
+    match section {
+        Some(section) => format!("<!--monowiki:embed {slug}#{section} -->"),
+        None => format!("<!--monowiki:embed {slug} -->"),
+    }
+}
+
+/// Markdown source of a note that can be embedded elsewhere
+#[derive(Debug, Clone)]
+pub struct EmbedSource {
+    pub slug: String,
+    pub title: String,
+    pub source_path: Option<String>,
+    /// Markdown body without frontmatter
+    pub body: String,
+    /// Lines preceding the body in the source file
+    pub line_offset: usize,
+    pub source_hash: String,
+}
+
+/// Everything needed to expand embeds while rendering a note
+pub struct EmbedContext<'a> {
+    pub sources: &'a [EmbedSource],
+    /// Slug map key (slug or alias slug) -> index into `sources`
+    pub index: &'a HashMap<String, usize>,
+    /// Site-wide rendering context; embedded notes fill in their own
+    /// per-note fields
+    pub render: RenderContext<'a>,
+}
+
+impl EmbedContext<'_> {
+    fn resolve(&self, key: &str) -> Option<&EmbedSource> {
+        self.index.get(key).map(|idx| &self.sources[*idx])
+    }
+}
+
+/// The page being expanded
+#[derive(Default)]
+struct Page {
+    /// Slugs of the notes being expanded, outermost (the host) first
+    stack: Vec<String>,
+    /// Ids used so far
+    ids: HeadingIds,
+}
+
+/// Result of expanding the embeds in a rendered note
+#[derive(Debug, Default)]
+pub struct Expansion {
+    pub html: String,
+    /// Slugs of notes embedded directly by the host note
+    pub embeds: Vec<String>,
+    /// Every slug map key embedded (transitively) with the source hash of
+    /// the note it resolved to
+    pub dependencies: BTreeMap<String, String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl MarkdownProcessor {
+    /// Replace embed markers in `html` with the rendered content they refer to
+    pub fn expand_embeds(
+        &self,
+        html: &str,
+        host_slug: &str,
+        host_path: Option<&str>,
+        ctx: &EmbedContext,
+    ) -> Expansion {
+        let mut expansion = Expansion::default();
+        let mut page = Page {
+            stack: vec![host_slug.to_string()],
+            ..Default::default()
+        };
+        for caps in ID_ATTR_RE.captures_iter(html) {
+            page.ids.reserve(&caps[2]);
+        }
+        expansion.html = self.expand_level(html, host_path, ctx, &mut page, &mut expansion);
+        expansion
+    }
+
+    fn expand_level(
+        &self,
+        html: &str,
+        host_path: Option<&str>,
+        ctx: &EmbedContext,
+        page: &mut Page,
+        expansion: &mut Expansion,
+    ) -> String {
+        if !html.contains("<!--monowiki:embed ") {
+            return html.to_string();
+        }
+
+        EMBED_RE
+            .replace_all(html, |caps: &Captures| {
+                // Only swallow the paragraph when the embed stands on its own
+                let (open, close) = match (caps.get(1), caps.get(4)) {
+                    (Some(_), Some(_)) => ("", ""),
+                    (open, close) => (
+                        open.map(|m| m.as_str()).unwrap_or_default(),
+                        close.map(|m| m.as_str()).unwrap_or_default(),
+                    ),
+                };
+                let key = &caps[2];
+                let section = caps.get(3).map(|m| m.as_str());
+                let inner = self.expand_one(key, section, host_path, ctx, page, expansion);
+                format!("{open}{inner}{close}")
+            })
+            .into_owned()
+    }
+
+    fn expand_one(
+        &self,
+        key: &str,
+        section: Option<&str>,
+        host_path: Option<&str>,
+        ctx: &EmbedContext,
+        page: &mut Page,
+        expansion: &mut Expansion,
+    ) -> String {
+        let Some(source) = ctx.resolve(key) else {
+            return String::new();
+        };
+        let direct = page.stack.len() == 1;
+        if direct && !expansion.embeds.contains(&source.slug) {
+            expansion.embeds.push(source.slug.clone());
+        }
+        expansion
+            .dependencies
+            .insert(key.to_string(), source.source_hash.clone());
+
+        let href = ctx.render.slug_map.get(key).cloned().unwrap_or_default();
+        let fallback = |href: String| {
+            let href = match section {
+                Some(section) => format!("{href}#{section}"),
+                None => href,
+            };
+            format!(
+                "<a class=\"transclusion-link\" href=\"{}\">{}</a>",
+                html_escape(&href),
+                html_escape(&source.title)
+            )
+        };
+
+        if page.stack.contains(&source.slug) {
+            // Report a cycle from the note that starts it, or the direct embed
+            if direct || page.stack.first() == Some(&source.slug) {
+                let mut chain = page.stack.clone();
+                chain.push(source.slug.clone());
+                expansion.diagnostics.push(embed_diagnostic(
+                    "embed.cycle",
+                    format!(
+                        "Embedding '{}' creates a cycle: {}",
+                        source.slug,
+                        chain.join(" -> ")
+                    ),
+                    &page.stack[0],
+                    host_path,
+                    key,
+                ));
+            }
+            return fallback(href);
+        }
+
+        let (markdown, line_offset) = match section {
+            Some(section) => match self.extract_section(&source.body, section) {
+                Some((start, end)) => (
+                    &source.body[start..end],
+                    source.line_offset + source.body[..start].matches('\n').count(),
+                ),
+                None => {
+                    if direct {
+                        expansion.diagnostics.push(embed_diagnostic(
+                            "embed.missing_section",
+                            format!("Note '{}' has no section '{}'", source.slug, section),
+                            &page.stack[0],
+                            host_path,
+                            key,
+                        ));
+                    }
+                    return fallback(href);
+                }
+            },
+            None => (source.body.as_str(), source.line_offset),
+        };
+
+        // Diagnostics inside the embedded note are reported when that note
+        // itself is rendered, so only the HTML is kept here
+        let render = RenderContext {
+            note_slug: Some(&source.slug),
+            source_path: source.source_path.as_deref(),
+            line_offset,
+            note_macros: None,
+            citations: None,
+            ..ctx.render
+        };
+        let (html, _, _, _) = self.convert(markdown, &render);
+        // Nested embeds are still markers here, so they claim their ids after
+        // the content around them
+        let html = dedupe_ids(&html, &mut page.ids);
+
+        page.stack.push(source.slug.clone());
+        let html = self.expand_level(&html, host_path, ctx, page, expansion);
+        page.stack.pop();
+
+        format!(
+            "<div class=\"transclusion\" data-embed=\"{}\">\n{}</div>\n",
+            html_escape(&source.slug),
+            html
+        )
+    }
+
+    /// Byte range of the section headed by `section` (a heading slug),
+    /// running until the next heading of the same or a higher level, or of
+    /// the block with that id for `^block-id` sections.
+    fn extract_section(&self, body: &str, section: &str) -> Option<(usize, usize)> {
+        if let Some(id) = section.strip_prefix('^') {
+            return scan_block_ids(body, self.options)
+                .into_iter()
+                .find(|(block_id, _)| block_id == id)
+                .map(|(_, range)| (range.start, range.end));
+        }
+
+        let mut found: Option<(usize, u32)> = None;
+        // Start, level, whether the id was explicit, and title text
+        let mut current: Option<(usize, u32, bool, String)> = None;
+        let mut ids = HeadingIds::default();
+
+        for (event, range) in Parser::new_ext(body, self.options).into_offset_iter() {
+            match event {
+                Event::Start(Tag::Heading { level, id, .. }) => {
+                    let level = level as u32;
+                    if let Some((start, found_level)) = found {
+                        if level <= found_level {
+                            return Some((start, range.start));
+                        }
+                    }
+                    if let Some(id) = &id {
+                        ids.reserve(id);
+                    }
+                    if found.is_none() && id.as_deref() == Some(section) {
+                        found = Some((range.start, level));
+                    } else {
+                        current = Some((range.start, level, id.is_some(), String::new()));
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    if let Some((start, level, explicit, title)) = current.take() {
+                        // Generated ids advance even once the section is found
+                        let id = if explicit {
+                            slugify(&title)
+                        } else {
+                            ids.unique(&title)
+                        };
+                        if found.is_none() && id == section {
+                            found = Some((start, level));
+                        }
+                    }
+                }
+                event => {
+                    if let (Some((_, _, _, title)), Some(text)) =
+                        (current.as_mut(), heading_text(&event))
+                    {
+                        title.push_str(text);
+                    }
+                }
+            }
+        }
+
+        found.map(|(start, _)| (start, body.len()))
+    }
+}
+
+/// Rename the ids in embedded `html` that are already taken on the page,
+/// along with the fragment links inside it that point at them
+fn dedupe_ids(html: &str, ids: &mut HeadingIds) -> String {
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    let html = ID_ATTR_RE.replace_all(html, |caps: &Captures| {
+        let id = ids.claim(&caps[2]);
+        if id != caps[2] {
+            renamed.insert(caps[2].to_string(), id.clone());
+        }
+        format!("{}id=\"{}\"", &caps[1], id)
+    });
+    if renamed.is_empty() {
+        return html.into_owned();
+    }
+    HREF_FRAGMENT_RE
+        .replace_all(&html, |caps: &Captures| match renamed.get(&caps[1]) {
+            Some(id) => format!("href=\"#{id}\""),
+            None => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+fn embed_diagnostic(
+    code: &str,
+    message: String,
+    host_slug: &str,
+    host_path: Option<&str>,
+    key: &str,
+) -> Diagnostic {
+    Diagnostic {
+        code: code.to_string(),
+        message,
+        severity: DiagnosticSeverity::Warning,
+        note_slug: Some(host_slug.to_string()),
+        source_path: host_path.map(|p| p.to_string()),
+        context: Some(key.to_string()),
+        anchor: None,
+        span: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(slug: &str, body: &str) -> EmbedSource {
+        EmbedSource {
+            slug: slug.to_string(),
+            title: slug.to_string(),
+            source_path: Some(format!("{slug}.md")),
+            body: body.to_string(),
+            line_offset: 0,
+            source_hash: format!("hash-{slug}"),
+        }
+    }
+
+    fn expand(host: &str, sources: Vec<EmbedSource>) -> Expansion {
+        let index: HashMap<String, usize> = sources
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| (s.slug.clone(), idx))
+            .collect();
+        let slug_map: HashMap<String, String> = sources
+            .iter()
+            .map(|s| (s.slug.clone(), format!("/{}.html", s.slug)))
+            .collect();
+        let ctx = EmbedContext {
+            sources: &sources,
+            index: &index,
+            render: RenderContext::new(&slug_map, "/"),
+        };
+        let processor = MarkdownProcessor::new();
+        let host_source = ctx.resolve(host).unwrap();
+        let render = RenderContext {
+            note_slug: Some(host),
+            ..ctx.render
+        };
+        let (html, _, _, _) = processor.convert(&host_source.body, &render);
+        processor.expand_embeds(&html, host, None, &ctx)
+    }
+
+    #[test]
+    fn embeds_whole_note_and_sections() {
+        let expansion = expand(
+            "host",
+            vec![
+                source(
+                    "host",
+                    "Intro\n\n![[shared]]\n\nAnd ![[shared#Usage]] inline.\n\n![[shared#^tip]]",
+                ),
+                source(
+                    "shared",
+                    "# Shared\n\nBoilerplate.\n\n## Usage\n\nRun it.\n\n### Detail\n\nMore.\n\n## Other\n\nSkipped.\n\nJust this. ^tip\n",
+                ),
+            ],
+        );
+
+        assert_eq!(expansion.embeds, vec!["shared"]);
+        assert_eq!(expansion.dependencies["shared"], "hash-shared");
+        assert!(expansion.diagnostics.is_empty());
+        assert!(!expansion.html.contains("<p><div"));
+        assert!(expansion
+            .html
+            .contains("<div class=\"transclusion\" data-embed=\"shared\">"));
+        assert!(expansion.html.contains("Boilerplate."));
+
+        let section = expansion.html.split("And ").nth(1).unwrap();
+        assert!(section.contains("Run it."));
+        assert!(section.contains("More."));
+        assert!(!section
+            .split("inline.")
+            .next()
+            .unwrap()
+            .contains("Skipped."));
+
+        let block = expansion
+            .html
+            .rsplit("data-embed=\"shared\"")
+            .next()
+            .unwrap();
+        // The whole note already brought the block in once
+        assert!(block.contains("<p id=\"^tip-1\">Just this.</p>"));
+        assert!(!block.contains("Boilerplate."));
+    }
+
+    #[test]
+    fn embedded_ids_do_not_repeat_host_ids() {
+        let expansion = expand(
+            "host",
+            vec![
+                source(
+                    "host",
+                    "# Examples\n\n![[shared]]\n\n![[shared#Examples]]\n",
+                ),
+                source(
+                    "shared",
+                    "## Examples\n\nSee [above](#examples).\n\nA tip. ^tip\n",
+                ),
+            ],
+        );
+
+        let html = &expansion.html;
+        assert!(html.contains("<h1 id=\"examples\">"), "{html}");
+        assert!(html.contains("<h2 id=\"examples-1\">"), "{html}");
+        assert!(html.contains("<h2 id=\"examples-2\">"), "{html}");
+        assert!(html.contains("href=\"#examples-1\""), "{html}");
+        assert!(html.contains("href=\"#examples-2\""), "{html}");
+        assert!(html.contains("id=\"^tip\""), "{html}");
+        assert!(html.contains("id=\"^tip-1\""), "{html}");
+    }
+
+    #[test]
+    fn cycles_are_reported_and_rendered_as_links() {
+        let expansion = expand(
+            "a",
+            vec![
+                source("a", "A body\n\n![[b]]\n"),
+                source("b", "B body\n\n![[a]]\n"),
+            ],
+        );
+
+        assert!(expansion.html.contains("B body"));
+        assert!(expansion
+            .html
+            .contains("class=\"transclusion-link\" href=\"/a.html\""));
+        assert_eq!(expansion.diagnostics.len(), 1);
+        assert_eq!(expansion.diagnostics[0].code, "embed.cycle");
+        assert!(expansion.diagnostics[0].message.contains("a -> b -> a"));
+    }
+
+    #[test]
+    fn missing_section_falls_back_to_link() {
+        let expansion = expand(
+            "host",
+            vec![
+                source("host", "![[other#Nope]]"),
+                source("other", "# Other\n"),
+            ],
+        );
+
+        assert_eq!(expansion.diagnostics[0].code, "embed.missing_section");
+        assert!(expansion.html.contains("href=\"/other.html#nope\""));
+    }
+}
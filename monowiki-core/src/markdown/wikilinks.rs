@@ -1,9 +1,20 @@
-//! Wikilink transformation for [[target]] and [[target|text]] syntax.
+//! Wikilink transformation for [[target]] and [[target|text]] syntax,
+//! `@ref[label]` references to numbered nota blocks, `\eqref{label}` /
+//! `\ref{label}` references to numbered equations, and resolution of plain
+//! Markdown links to other vault files.
 
+use super::block_ids::{block_anchor, BlockIndex};
+use super::html_escape;
+use super::nota_blocks::{LabelIndex, NotaLabel};
+use super::source_map::{strip_spans, MergedText, SourceMap, SpannedEvent};
+use super::transclusion::embed_marker;
+use crate::assets::{asset_kind, is_asset_path, is_local_reference, AssetIndex, AssetKind};
 use crate::models::{Diagnostic, DiagnosticSeverity};
 use crate::slug::slugify;
+use percent_encoding::percent_decode_str;
 use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 /// Transformer for wikilink syntax
 pub struct WikilinkTransformer<'a> {
@@ -11,6 +22,14 @@ pub struct WikilinkTransformer<'a> {
     base_url: String,
     note_slug: Option<String>,
     source_path: Option<String>,
+    source_map: Option<&'a SourceMap<'a>>,
+    block_index: Option<&'a BlockIndex>,
+    unpublished: Option<&'a HashSet<String>>,
+    note_paths: Option<&'a HashMap<String, String>>,
+    assets: Option<&'a AssetIndex>,
+    /// Nota blocks of the note being rendered
+    note_labels: &'a [NotaLabel],
+    label_index: Option<&'a LabelIndex>,
 }
 
 impl<'a> WikilinkTransformer<'a> {
@@ -25,9 +44,60 @@ impl<'a> WikilinkTransformer<'a> {
             base_url: crate::config::normalize_base_url(base_url),
             note_slug,
             source_path,
+            source_map: None,
+            block_index: None,
+            unpublished: None,
+            note_paths: None,
+            assets: None,
+            note_labels: &[],
+            label_index: None,
         }
     }
 
+    /// Attach a source map so diagnostics carry line/column spans
+    pub fn with_source_map(mut self, source_map: &'a SourceMap<'a>) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// Check `[[note#^block-id]]` targets against the block ids each note defines
+    pub fn with_block_index(mut self, block_index: &'a BlockIndex) -> Self {
+        self.block_index = Some(block_index);
+        self
+    }
+
+    /// Render links to these slugs (scheduled or expired notes) as plain text
+    pub fn with_unpublished(mut self, unpublished: &'a HashSet<String>) -> Self {
+        self.unpublished = Some(unpublished);
+        self
+    }
+
+    /// Resolve relative `[text](other.md)` links through these vault-relative
+    /// source paths (path -> slug)
+    pub fn with_note_paths(mut self, note_paths: &'a HashMap<String, String>) -> Self {
+        self.note_paths = Some(note_paths);
+        self
+    }
+
+    /// Resolve `![[file.png|400]]` attachments and check that local images
+    /// and file links point at vault assets
+    pub fn with_assets(mut self, assets: &'a AssetIndex) -> Self {
+        self.assets = Some(assets);
+        self
+    }
+
+    /// Resolve `@ref[label]` and `[[#label]]` against the note's own nota blocks
+    pub fn with_note_labels(mut self, labels: &'a [NotaLabel]) -> Self {
+        self.note_labels = labels;
+        self
+    }
+
+    /// Resolve `[[note#label]]` against the nota blocks each note defines
+    pub fn with_label_index(mut self, label_index: &'a LabelIndex) -> Self {
+        self.label_index = Some(label_index);
+        self
+    }
+
     /// Transform events, converting [[wikilinks]] to HTML links
     ///
     /// Returns (transformed_events, outgoing_links)
@@ -35,24 +105,87 @@ impl<'a> WikilinkTransformer<'a> {
         &self,
         events: Vec<Event<'_>>,
     ) -> (Vec<Event<'static>>, Vec<String>, Vec<Diagnostic>) {
+        let events = events
+            .into_iter()
+            .map(|event| (event.into_static(), 0..0))
+            .collect();
+        let (events, links, diagnostics) = self.transform_spanned(events);
+        (strip_spans(events), links, diagnostics)
+    }
+
+    /// Like `transform`, but keeps each event's source range
+    pub fn transform_spanned(
+        &self,
+        events: Vec<SpannedEvent>,
+    ) -> (Vec<SpannedEvent>, Vec<String>, Vec<Diagnostic>) {
         let mut result = Vec::new();
         let mut outgoing_links = Vec::new();
         let mut diagnostics = Vec::new();
         let mut i = 0;
         let mut in_code_block = false;
+        // Set when a Markdown link was dropped, so its closing tag is too
+        let mut drop_link_end = false;
 
         while i < events.len() {
             // Track code block context
-            match &events[i] {
+            match &events[i].0 {
+                Event::Start(tag @ (Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }))
+                    if self.assets.is_some() && is_asset_reference(dest_url) =>
+                {
+                    let range = events[i].1.clone();
+                    let (href, diag) = self.resolve_asset_link(dest_url, &range);
+                    diagnostics.extend(diag);
+                    let tag = match href {
+                        Some(href) => with_dest_url(tag.clone(), href),
+                        None => tag.clone(),
+                    };
+                    result.push((Event::Start(tag), range));
+                    i += 1;
+                    continue;
+                }
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) if self.note_paths.is_some() => {
+                    let range = events[i].1.clone();
+                    match self.resolve_markdown_link(dest_url, &range) {
+                        Some((href, slug, diag)) => {
+                            outgoing_links.extend(slug);
+                            diagnostics.extend(diag);
+                            match href {
+                                Some(href) => result.push((
+                                    Event::Start(Tag::Link {
+                                        link_type: *link_type,
+                                        dest_url: CowStr::Boxed(href.into_boxed_str()),
+                                        title: title.clone(),
+                                        id: id.clone(),
+                                    }),
+                                    range,
+                                )),
+                                None => drop_link_end = true,
+                            }
+                        }
+                        None => result.push(events[i].clone()),
+                    }
+                    i += 1;
+                    continue;
+                }
+                Event::End(TagEnd::Link) if drop_link_end => {
+                    drop_link_end = false;
+                    i += 1;
+                    continue;
+                }
                 Event::Start(Tag::CodeBlock(_)) => {
                     in_code_block = true;
-                    result.push(events[i].clone().into_static());
+                    result.push(events[i].clone());
                     i += 1;
                     continue;
                 }
                 Event::End(TagEnd::CodeBlock) => {
                     in_code_block = false;
-                    result.push(events[i].clone().into_static());
+                    result.push(events[i].clone());
                     i += 1;
                     continue;
                 }
@@ -61,35 +194,43 @@ impl<'a> WikilinkTransformer<'a> {
 
             // Skip wikilink processing inside code blocks
             if in_code_block {
-                result.push(events[i].clone().into_static());
+                result.push(events[i].clone());
                 i += 1;
                 continue;
             }
 
-            if let Event::Text(_) = &events[i] {
+            if let Event::Text(_) = &events[i].0 {
                 // Collect all consecutive Text events and merge them
-                let mut merged_text = String::new();
+                let mut merged = MergedText::new();
 
                 while i < events.len() {
-                    if let Event::Text(text) = &events[i] {
-                        merged_text.push_str(text.as_ref());
+                    if let (Event::Text(text), range) = &events[i] {
+                        merged.push(text, range.clone());
                         i += 1;
                     } else {
                         break;
                     }
                 }
 
-                // Check if merged text contains wikilink syntax
-                if merged_text.contains("[[") && merged_text.contains("]]") {
-                    let (transformed, links, mut diags) = self.process_wikilinks(&merged_text);
+                // Check if merged text contains wikilink or reference syntax
+                if (merged.text.contains("[[") && merged.text.contains("]]"))
+                    || REFERENCES
+                        .iter()
+                        .any(|(open, _)| merged.text.contains(open))
+                {
+                    let (transformed, links, mut diags) = self.process_wikilinks(&merged);
                     result.extend(transformed);
                     outgoing_links.extend(links);
                     diagnostics.append(&mut diags);
                 } else {
-                    result.push(Event::Text(CowStr::Boxed(merged_text.into_boxed_str())));
+                    let range = merged.range();
+                    result.push((
+                        Event::Text(CowStr::Boxed(merged.text.into_boxed_str())),
+                        range,
+                    ));
                 }
             } else {
-                result.push(events[i].clone().into_static());
+                result.push(events[i].clone());
                 i += 1;
             }
         }
@@ -97,25 +238,56 @@ impl<'a> WikilinkTransformer<'a> {
         (result, outgoing_links, diagnostics)
     }
 
-    fn process_wikilinks(&self, text: &str) -> (Vec<Event<'static>>, Vec<String>, Vec<Diagnostic>) {
+    fn process_wikilinks(
+        &self,
+        merged: &MergedText,
+    ) -> (Vec<SpannedEvent>, Vec<String>, Vec<Diagnostic>) {
         let mut events = Vec::new();
         let mut links = Vec::new();
         let mut diagnostics = Vec::new();
-        let mut remaining = text;
+        let text = merged.text.as_str();
+        let mut pos = 0;
+
+        while let Some(found) = text[pos..].find("[[") {
+            let start = pos + found;
+            // `![[note]]` embeds the note instead of linking to it
+            let embed = start > pos && text[..start].ends_with('!');
+            let text_end = if embed { start - 1 } else { start };
 
-        while let Some(start) = remaining.find("[[") {
             // Add text before the wikilink
-            if start > 0 {
-                events.push(Event::Text(CowStr::Boxed(
-                    remaining[..start].to_string().into_boxed_str(),
-                )));
-            }
+            self.push_text(
+                merged,
+                pos,
+                text_end,
+                &mut events,
+                &mut links,
+                &mut diagnostics,
+            );
 
             // Find the closing ]]
-            if let Some(end) = remaining[start..].find("]]") {
-                let wikilink = &remaining[start + 2..start + end];
-                let (link_event, target_slug, diag) = self.create_link(wikilink);
-                events.extend(link_event);
+            if let Some(end) = text[start..].find("]]") {
+                let wikilink = &text[start + 2..start + end];
+                let range = merged.source_range(text_end, start + end + 2);
+
+                let target = wikilink.split(['|', '#']).next().unwrap_or_default().trim();
+                if self.assets.is_some() && is_asset_path(target) {
+                    let (event, diag) = self.create_asset(wikilink, embed, &range);
+                    events.push((event, range));
+                    diagnostics.extend(diag);
+                    pos = start + end + 2;
+                    continue;
+                }
+
+                if embed {
+                    if let Some(marker) = self.create_embed(wikilink) {
+                        events.push((marker, range));
+                        pos = start + end + 2;
+                        continue;
+                    }
+                }
+
+                let (link_event, target_slug, diag) = self.create_link(wikilink, &range);
+                events.extend(link_event.into_iter().map(|event| (event, range.clone())));
 
                 if let Some(slug) = target_slug {
                     links.push(slug);
@@ -124,29 +296,191 @@ impl<'a> WikilinkTransformer<'a> {
                     diagnostics.push(diag);
                 }
 
-                remaining = &remaining[start + end + 2..];
+                pos = start + end + 2;
             } else {
                 // No closing ]], treat as literal text
-                events.push(Event::Text(CowStr::Boxed(
-                    remaining.to_string().into_boxed_str(),
-                )));
                 break;
             }
         }
 
         // Add any remaining text
-        if !remaining.is_empty() {
-            events.push(Event::Text(CowStr::Boxed(
-                remaining.to_string().into_boxed_str(),
-            )));
-        }
+        self.push_text(
+            merged,
+            pos,
+            text.len(),
+            &mut events,
+            &mut links,
+            &mut diagnostics,
+        );
 
         (events, links, diagnostics)
     }
 
+    /// Emit `merged.text[start..end]`, turning `@ref[label]`,
+    /// `\eqref{label}` and `\ref{label}` (optionally `note#label`) into links
+    /// to the nota blocks and equations they name
+    fn push_text(
+        &self,
+        merged: &MergedText,
+        start: usize,
+        end: usize,
+        events: &mut Vec<SpannedEvent>,
+        links: &mut Vec<String>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let text = &merged.text[..end];
+        let mut pos = start;
+        while let Some((ref_start, open, close)) = REFERENCES
+            .iter()
+            .filter_map(|(open, close)| {
+                text[pos..]
+                    .find(open)
+                    .map(|found| (pos + found, *open, *close))
+            })
+            .min_by_key(|(at, _, _)| *at)
+        {
+            let Some(close_at) = text[ref_start..].find(close) else {
+                break;
+            };
+            let ref_end = ref_start + close_at + 1;
+            if ref_start > pos {
+                events.push((
+                    Event::Text(CowStr::Boxed(
+                        text[pos..ref_start].to_string().into_boxed_str(),
+                    )),
+                    merged.source_range(pos, ref_start),
+                ));
+            }
+
+            let range = merged.source_range(ref_start, ref_end);
+            let target = text[ref_start + open.len()..ref_end - 1].trim();
+            let (base, label) = target.split_once('#').unwrap_or(("", target));
+            let slug = slugify(base.trim());
+            let equation = open != "@ref[";
+            // Depend on the target note even when the label is missing, so
+            // the reference resolves once the note gains it
+            if !slug.is_empty() {
+                links.push(slug.clone());
+            }
+            let html = match self.find_label(&slug, label) {
+                Some(found) => {
+                    // `\eqref` reads "(3)" and `\ref` just "3"
+                    let display = match (&found.number, open) {
+                        (Some(number), "\\eqref{") => Some(format!("({number})")),
+                        (Some(number), "\\ref{") => Some(number.clone()),
+                        _ => None,
+                    };
+                    self.label_link(&slug, found, display.as_deref())
+                }
+                None => {
+                    diagnostics.push(if equation {
+                        self.unresolved_equation(target, &range)
+                    } else {
+                        Diagnostic {
+                            code: "ref.unresolved".to_string(),
+                            message: format!("No nota block labelled '{}'", target),
+                            severity: DiagnosticSeverity::Warning,
+                            note_slug: self.note_slug.clone(),
+                            source_path: self.source_path.clone(),
+                            context: Some(target.to_string()),
+                            anchor: None,
+                            span: self.source_map.map(|map| map.span(&range)),
+                        }
+                    });
+                    format!(
+                        "<span class=\"nota-ref nota-ref-unresolved\" title=\"{}\">{}</span>",
+                        html_escape(target),
+                        if open == "\\eqref{" { "(??)" } else { "??" }
+                    )
+                }
+            };
+            events.push((
+                Event::InlineHtml(CowStr::Boxed(html.into_boxed_str())),
+                range,
+            ));
+            pos = ref_end;
+        }
+
+        if end > pos {
+            events.push((
+                Event::Text(CowStr::Boxed(text[pos..end].to_string().into_boxed_str())),
+                merged.source_range(pos, end),
+            ));
+        }
+    }
+
+    fn unresolved_equation(&self, target: &str, range: &Range<usize>) -> Diagnostic {
+        Diagnostic {
+            code: "math.unresolved_ref".to_string(),
+            message: format!("No equation labelled '{}'", target),
+            severity: DiagnosticSeverity::Warning,
+            note_slug: self.note_slug.clone(),
+            source_path: self.source_path.clone(),
+            context: Some(target.to_string()),
+            anchor: None,
+            span: self.source_map.map(|map| map.span(range)),
+        }
+    }
+
+    /// The nota block labelled `label` in the note at `slug` (this note when
+    /// `slug` is empty or its own)
+    fn find_label(&self, slug: &str, label: &str) -> Option<&'a NotaLabel> {
+        let label = label.trim();
+        let labels = if slug.is_empty() || self.note_slug.as_deref() == Some(slug) {
+            self.note_labels
+        } else if self.slug_map.contains_key(slug) {
+            self.label_index?.get(slug)?.as_slice()
+        } else {
+            return None;
+        };
+        labels
+            .iter()
+            .find(|l| l.label == label)
+            .or_else(|| labels.iter().find(|l| l.label == slugify(label)))
+    }
+
+    /// Link to a nota block, reading "Theorem 3" unless `display` is given
+    fn label_link(&self, slug: &str, label: &NotaLabel, display: Option<&str>) -> String {
+        let page = match self.slug_map.get(slug) {
+            Some(dest) if self.note_slug.as_deref() != Some(slug) => dest.as_str(),
+            _ => "",
+        };
+        let text = display
+            .map(str::to_string)
+            .unwrap_or_else(|| label.reference_text());
+        format!(
+            "<a class=\"nota-ref\" href=\"{}#{}\" data-nota-kind=\"{}\">{}</a>",
+            html_escape(page),
+            html_escape(&label.label),
+            html_escape(&label.kind),
+            html_escape(&text)
+        )
+    }
+
+    /// Placeholder for `![[target#section]]`, expanded once all notes are
+    /// known (see `transclusion`). Unresolved targets fall back to a link.
+    fn create_embed(&self, wikilink: &str) -> Option<Event<'static>> {
+        let target = wikilink.split('|').next().unwrap_or_default().trim();
+        let (target_base, section) = match target.split_once('#') {
+            Some((base, section)) => (base.trim(), Some(normalize_fragment(section))),
+            None => (target, None),
+        };
+        let slug = slugify(target_base);
+        if slug.is_empty() || !self.slug_map.contains_key(&slug) {
+            return None;
+        }
+        if !self.block_exists(&slug, section.as_deref()) {
+            return None;
+        }
+
+        let marker = embed_marker(&slug, section.as_deref().filter(|s| !s.is_empty()));
+        Some(Event::InlineHtml(CowStr::Boxed(marker.into_boxed_str())))
+    }
+
     fn create_link(
         &self,
         wikilink: &str,
+        range: &Range<usize>,
     ) -> (Vec<Event<'static>>, Option<String>, Option<Diagnostic>) {
         // Parse [[target|display text]] or [[target]]
         let (target, display) = if let Some(pipe_pos) = wikilink.find('|') {
@@ -164,12 +498,54 @@ impl<'a> WikilinkTransformer<'a> {
         };
 
         let slug = slugify(target_base);
-        let fragment = fragment_raw.filter(|f| !f.is_empty()).map(slugify);
+        let fragment = fragment_raw
+            .filter(|f| !f.is_empty())
+            .map(normalize_fragment);
 
         let display_text = display.unwrap_or(target);
 
+        // `[[#label]]` / `[[note#label]]` naming a nota block
+        if let Some(label) = fragment_raw.and_then(|raw| self.find_label(&slug, raw)) {
+            let html = self.label_link(&slug, label, display);
+            let outgoing = (!slug.is_empty()).then_some(slug);
+            return (
+                vec![Event::InlineHtml(CowStr::Boxed(html.into_boxed_str()))],
+                outgoing,
+                None,
+            );
+        }
+
+        if !self.slug_map.contains_key(&slug)
+            && self
+                .unpublished
+                .is_some_and(|hidden| hidden.contains(&slug))
+        {
+            // Keep the slug as an outgoing link so the page is rebuilt once
+            // the target is published
+            let events = vec![Event::Text(CowStr::Boxed(
+                display_text.to_string().into_boxed_str(),
+            ))];
+            let diagnostic = Diagnostic {
+                code: "link.unpublished".to_string(),
+                message: format!(
+                    "Link to '{}' rendered as plain text: the note is scheduled or expired",
+                    target
+                ),
+                severity: DiagnosticSeverity::Info,
+                note_slug: self.note_slug.clone(),
+                source_path: self.source_path.clone(),
+                context: Some(target.to_string()),
+                anchor: None,
+                span: self.source_map.map(|map| map.span(range)),
+            };
+            return (events, Some(slug), Some(diagnostic));
+        }
+
         // Check if target exists in slug map
-        let href = if let Some(dest) = self.slug_map.get(&slug) {
+        let href = if slug.is_empty() {
+            // `[[#Heading]]` links within the note
+            format!("#{}", fragment.as_deref().unwrap_or_default())
+        } else if let Some(dest) = self.slug_map.get(&slug) {
             if let Some(frag) = &fragment {
                 format!("{dest}#{frag}")
             } else {
@@ -206,7 +582,18 @@ impl<'a> WikilinkTransformer<'a> {
         events.push(Event::End(TagEnd::Link));
 
         let outgoing = if slug.is_empty() { None } else { Some(slug) };
-        if outgoing.is_some() && !self.slug_map.contains_key(outgoing.as_ref().unwrap()) {
+        // Equation labels are known for this note, and for others once the
+        // label index is available
+        let equation_checked = fragment_raw.is_some_and(|f| f.starts_with("eq:"))
+            && (outgoing.is_none()
+                || self.note_slug == outgoing
+                || (self.label_index.is_some()
+                    && outgoing
+                        .as_ref()
+                        .is_some_and(|slug| self.slug_map.contains_key(slug))));
+        if equation_checked {
+            diagnostic = Some(self.unresolved_equation(target, range));
+        } else if outgoing.is_some() && !self.slug_map.contains_key(outgoing.as_ref().unwrap()) {
             diagnostic = Some(Diagnostic {
                 code: "link.unresolved".to_string(),
                 message: format!("Unresolved wikilink target '{}'", target),
@@ -215,11 +602,293 @@ impl<'a> WikilinkTransformer<'a> {
                 source_path: self.source_path.clone(),
                 context: Some(target.to_string()),
                 anchor: None,
+                span: self.source_map.map(|map| map.span(range)),
             });
+        } else if let Some(slug) = outgoing.as_deref() {
+            if !self.block_exists(slug, fragment.as_deref()) {
+                diagnostic = Some(Diagnostic {
+                    code: "link.unresolved_block".to_string(),
+                    message: format!(
+                        "Note '{}' has no block '{}'",
+                        slug,
+                        fragment.as_deref().unwrap_or_default()
+                    ),
+                    severity: DiagnosticSeverity::Warning,
+                    note_slug: self.note_slug.clone(),
+                    source_path: self.source_path.clone(),
+                    context: Some(target.to_string()),
+                    anchor: None,
+                    span: self.source_map.map(|map| map.span(range)),
+                });
+            }
         }
 
         (events, outgoing, diagnostic)
     }
+
+    /// `![[file.png|400]]` embeds and `[[file.pdf|text]]` links to vault
+    /// assets, found by path or by name. Embeds take an optional size hint
+    /// (`|400` or `|400x300`) and `|noresize` to keep an image out of the
+    /// responsive image pipeline; other text after `|` is the alt text / label.
+    fn create_asset(
+        &self,
+        wikilink: &str,
+        embed: bool,
+        range: &Range<usize>,
+    ) -> (Event<'static>, Option<Diagnostic>) {
+        let mut parts = wikilink.split('|').map(str::trim);
+        let target = parts.next().unwrap_or_default();
+        let mut label = None;
+        let mut size = None;
+        let mut no_resize = false;
+        for part in parts {
+            match parse_size(part) {
+                Some(parsed) if embed => size = Some(parsed),
+                _ if embed && part.eq_ignore_ascii_case("noresize") => no_resize = true,
+                _ => label = Some(part),
+            }
+        }
+
+        let Some(path) = self
+            .assets
+            .and_then(|assets| assets.resolve(self.source_path.as_deref(), target))
+        else {
+            let event = Event::Text(CowStr::Boxed(target.to_string().into_boxed_str()));
+            return (event, Some(self.missing_asset(target, range)));
+        };
+
+        let src = html_escape(&format!("{}{}", self.base_url, path));
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        let label = html_escape(label.unwrap_or(name));
+        let mut attrs = match size {
+            Some((width, Some(height))) => format!(" width=\"{width}\" height=\"{height}\""),
+            Some((width, None)) => format!(" width=\"{width}\""),
+            None => String::new(),
+        };
+        if no_resize {
+            attrs.push_str(" data-no-resize");
+        }
+        let html = match (embed, asset_kind(&path)) {
+            (true, AssetKind::Image) => {
+                format!("<img src=\"{src}\" alt=\"{label}\"{attrs}>")
+            }
+            (true, AssetKind::Audio) => format!("<audio controls src=\"{src}\"></audio>"),
+            (true, AssetKind::Video) => {
+                format!("<video controls src=\"{src}\"{attrs}></video>")
+            }
+            _ => format!("<a href=\"{src}\">{label}</a>"),
+        };
+        (
+            Event::InlineHtml(CowStr::Boxed(html.into_boxed_str())),
+            None,
+        )
+    }
+
+    /// Href for a Markdown link or image pointing at a vault asset, and a
+    /// diagnostic when the file doesn't exist
+    fn resolve_asset_link(
+        &self,
+        dest: &str,
+        range: &Range<usize>,
+    ) -> (Option<String>, Option<Diagnostic>) {
+        let resolved = self
+            .assets
+            .and_then(|assets| assets.resolve(self.source_path.as_deref(), dest));
+        match resolved {
+            Some(path) => {
+                let suffix = dest.find(['#', '?']).map(|idx| &dest[idx..]);
+                let href = format!("{}{}{}", self.base_url, path, suffix.unwrap_or_default());
+                (Some(href), None)
+            }
+            None => (None, Some(self.missing_asset(dest, range))),
+        }
+    }
+
+    fn missing_asset(&self, reference: &str, range: &Range<usize>) -> Diagnostic {
+        Diagnostic {
+            code: "asset.missing".to_string(),
+            message: format!("Asset '{}' not found in the vault", reference),
+            severity: DiagnosticSeverity::Warning,
+            note_slug: self.note_slug.clone(),
+            source_path: self.source_path.clone(),
+            context: Some(reference.to_string()),
+            anchor: None,
+            span: self.source_map.map(|map| map.span(range)),
+        }
+    }
+
+    /// Resolve a Markdown link to a `.md` file in the vault, relative to the
+    /// current note (or to the vault root with a leading `/`).
+    ///
+    /// Returns `None` for links that aren't to Markdown files. Otherwise
+    /// returns the rewritten href (`None` when the target is missing or
+    /// unpublished and the link should render as text), the outgoing slug and
+    /// a diagnostic.
+    #[allow(clippy::type_complexity)]
+    fn resolve_markdown_link(
+        &self,
+        dest: &str,
+        range: &Range<usize>,
+    ) -> Option<(Option<String>, Option<String>, Option<Diagnostic>)> {
+        let note_paths = self.note_paths?;
+        let (path, fragment) = markdown_link_target(dest)?;
+        let resolved = resolve_vault_path(self.source_path.as_deref(), &path);
+        let fragment = fragment
+            .filter(|f| !f.is_empty())
+            .map(|f| {
+                let f = percent_decode_str(f).decode_utf8_lossy();
+                format!("#{}", normalize_fragment(&f))
+            })
+            .unwrap_or_default();
+        let diagnostic = |code: &str, message: String, severity| Diagnostic {
+            code: code.to_string(),
+            message,
+            severity,
+            note_slug: self.note_slug.clone(),
+            source_path: self.source_path.clone(),
+            context: Some(dest.to_string()),
+            anchor: None,
+            span: self.source_map.map(|map| map.span(range)),
+        };
+
+        let slug = resolved.as_deref().and_then(|rel| note_paths.get(rel));
+        match slug.map(|slug| (slug, self.slug_map.get(slug))) {
+            Some((slug, Some(href))) => {
+                Some((Some(format!("{href}{fragment}")), Some(slug.clone()), None))
+            }
+            Some((slug, None)) => Some((
+                None,
+                Some(slug.clone()),
+                Some(diagnostic(
+                    "link.unpublished",
+                    format!(
+                        "Link to '{}' rendered as plain text: the note is scheduled or expired",
+                        dest
+                    ),
+                    DiagnosticSeverity::Info,
+                )),
+            )),
+            None => Some((
+                None,
+                None,
+                Some(diagnostic(
+                    "link.unresolved",
+                    format!(
+                        "Link to '{}' rendered as plain text: no such file in the vault",
+                        dest
+                    ),
+                    DiagnosticSeverity::Warning,
+                )),
+            )),
+        }
+    }
+
+    /// Whether a `^block-id` fragment exists in the target note. Non-block
+    /// fragments, and all fragments when no block index is attached, pass.
+    fn block_exists(&self, slug: &str, fragment: Option<&str>) -> bool {
+        let (Some(index), Some(id)) =
+            (self.block_index, fragment.and_then(|f| f.strip_prefix('^')))
+        else {
+            return true;
+        };
+        index.get(slug).is_some_and(|ids| ids.contains(id))
+    }
+}
+
+/// Reference openers handled in running text, with their closing delimiter
+const REFERENCES: [(&str, char); 3] = [("@ref[", ']'), ("\\eqref{", '}'), ("\\ref{", '}')];
+
+/// Whether a link or image destination points at a vault asset
+pub(crate) fn is_asset_reference(dest: &str) -> bool {
+    is_local_reference(dest) && is_asset_path(dest.split(['#', '?']).next().unwrap_or_default())
+}
+
+/// Percent-decoded path and fragment of a local link to a `.md` file, or
+/// `None` for any other link
+pub(crate) fn markdown_link_target(dest: &str) -> Option<(String, Option<&str>)> {
+    if dest.contains("://") || dest.starts_with("mailto:") {
+        return None;
+    }
+    let (path, fragment) = match dest.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (dest, None),
+    };
+    if !path.ends_with(".md") {
+        return None;
+    }
+    Some((
+        percent_decode_str(path).decode_utf8_lossy().into_owned(),
+        fragment,
+    ))
+}
+
+/// `tag` (a link or image) pointing at `href` instead
+fn with_dest_url(tag: Tag<'static>, href: String) -> Tag<'static> {
+    let dest_url = CowStr::Boxed(href.into_boxed_str());
+    match tag {
+        Tag::Link {
+            link_type,
+            title,
+            id,
+            ..
+        } => Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        },
+        Tag::Image {
+            link_type,
+            title,
+            id,
+            ..
+        } => Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        },
+        tag => tag,
+    }
+}
+
+/// Parse an embed size hint: `400` or `400x300`
+fn parse_size(hint: &str) -> Option<(u32, Option<u32>)> {
+    match hint.split_once('x') {
+        Some((width, height)) => Some((width.parse().ok()?, Some(height.parse().ok()?))),
+        None => Some((hint.parse().ok()?, None)),
+    }
+}
+
+/// Vault-relative path of `link` as seen from the note at `from`, or `None`
+/// if it climbs out of the vault
+pub(crate) fn resolve_vault_path(from: Option<&str>, link: &str) -> Option<String> {
+    let mut segments: Vec<&str> = match (link.starts_with('/'), from) {
+        (false, Some(from)) => from.split('/').collect(),
+        _ => Vec::new(),
+    };
+    // Drop the note's own file name
+    segments.pop();
+    for segment in link.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Heading fragments are slugified to match heading ids; `^block-id`
+/// fragments are kept verbatim
+fn normalize_fragment(fragment: &str) -> String {
+    let fragment = fragment.trim();
+    match fragment.strip_prefix('^') {
+        Some(id) => block_anchor(id.trim()),
+        None => slugify(fragment),
+    }
 }
 
 #[cfg(test)]
@@ -271,6 +940,165 @@ mod tests {
         assert!(links.contains(&"page-two".to_string()));
     }
 
+    #[test]
+    fn test_block_reference_links() {
+        let slug_map = HashMap::from([("paper".to_string(), "/paper.html".to_string())]);
+        let block_index = BlockIndex::from([(
+            "paper".to_string(),
+            std::collections::HashSet::from(["claim".to_string()]),
+        )]);
+        let transformer =
+            WikilinkTransformer::new(&slug_map, "/", None, None).with_block_index(&block_index);
+        let events = vec![Event::Text(CowStr::Borrowed(
+            "See [[paper#^claim]] and [[paper#^gone]].",
+        ))];
+
+        let (result, links, diags) = transformer.transform(events);
+
+        assert_eq!(links, vec!["paper", "paper"]);
+        assert!(
+            result.iter().any(|e| matches!(e,
+            Event::Start(Tag::Link { dest_url, .. }) if dest_url.as_ref() == "/paper.html#^claim"))
+        );
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "link.unresolved_block");
+        assert!(diags[0].message.contains("^gone"));
+    }
+
+    #[test]
+    fn links_to_unpublished_notes_become_text() {
+        let slug_map = HashMap::new();
+        let unpublished = HashSet::from(["draft-plan".to_string()]);
+        let transformer =
+            WikilinkTransformer::new(&slug_map, "/", None, None).with_unpublished(&unpublished);
+
+        let events = vec![Event::Text(CowStr::Borrowed(
+            "See [[Draft Plan|the plan]].",
+        ))];
+        let (result, links, diags) = transformer.transform(events);
+
+        assert!(!result
+            .iter()
+            .any(|e| matches!(e, Event::Start(Tag::Link { .. }))));
+        assert!(result
+            .iter()
+            .any(|e| matches!(e, Event::Text(t) if t.as_ref() == "the plan")));
+        assert_eq!(links, vec!["draft-plan"]);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "link.unpublished");
+        assert_eq!(diags[0].severity, DiagnosticSeverity::Info);
+    }
+
+    #[test]
+    fn relative_markdown_links_resolve_through_note_paths() {
+        let slug_map = HashMap::from([
+            (
+                "getting-started".to_string(),
+                "/docs/getting-started.html".to_string(),
+            ),
+            ("cafe".to_string(), "/docs/cafe.html".to_string()),
+        ]);
+        let note_paths = HashMap::from([
+            (
+                "essays/getting-started.md".to_string(),
+                "getting-started".to_string(),
+            ),
+            ("essays/café (draft).md".to_string(), "cafe".to_string()),
+        ]);
+        let transformer = WikilinkTransformer::new(
+            &slug_map,
+            "/docs/",
+            None,
+            Some("notes/intro.md".to_string()),
+        )
+        .with_note_paths(&note_paths);
+
+        let markdown = "[a](../essays/getting-started.md#First%20Steps), [b](missing.md), \
+                        [c](https://example.com/x.md), [d](/essays/getting-started.md) and \
+                        [e](../essays/caf%C3%A9%20%28draft%29.md#Caf%C3%A9).";
+        let events = pulldown_cmark::Parser::new(markdown).collect();
+        let (result, links, diags) = transformer.transform(events);
+
+        let hrefs: Vec<_> = result
+            .iter()
+            .filter_map(|event| match event {
+                Event::Start(Tag::Link { dest_url, .. }) => Some(dest_url.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            hrefs,
+            vec![
+                "/docs/getting-started.html#first-steps",
+                "https://example.com/x.md",
+                "/docs/getting-started.html",
+                "/docs/cafe.html#café",
+            ]
+        );
+        assert_eq!(links, vec!["getting-started", "getting-started", "cafe"]);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].code, "link.unresolved");
+        assert_eq!(diags[0].context.as_deref(), Some("missing.md"));
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, result.into_iter());
+        assert!(html.contains(", b, "), "{html}");
+    }
+
+    #[test]
+    fn attachments_embed_with_size_hints_and_missing_ones_are_reported() {
+        let slug_map = HashMap::new();
+        let assets = AssetIndex::new(
+            [
+                "attachments/diagram.png".to_string(),
+                "essays/img/plot.png".to_string(),
+            ],
+            Some("attachments"),
+        );
+        let transformer =
+            WikilinkTransformer::new(&slug_map, "/", None, Some("essays/intro.md".to_string()))
+                .with_assets(&assets);
+
+        let markdown = "![[diagram.png|400x300]] ![[diagram.png|A diagram|200]] ![[gone.png]]\n\n\
+                        ![plot](img/plot.png) ![lost](img/lost.png)";
+        let events = pulldown_cmark::Parser::new(markdown).collect();
+        let (result, links, diags) = transformer.transform(events);
+
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, result.into_iter());
+        assert!(html.contains(
+            "<img src=\"/attachments/diagram.png\" alt=\"diagram.png\" width=\"400\" height=\"300\">"
+        ));
+        assert!(html.contains("alt=\"A diagram\" width=\"200\""));
+        assert!(html.contains("<img src=\"/essays/img/plot.png\" alt=\"plot\""));
+        assert!(html.contains("<img src=\"img/lost.png\""));
+        assert!(links.is_empty());
+
+        let missing: Vec<_> = diags
+            .iter()
+            .map(|d| (d.code.as_str(), d.context.as_deref()))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![
+                ("asset.missing", Some("gone.png")),
+                ("asset.missing", Some("img/lost.png"))
+            ]
+        );
+    }
+
+    #[test]
+    fn vault_paths_resolve_relative_to_the_note() {
+        assert_eq!(
+            resolve_vault_path(Some("a/b/note.md"), "../c/./other.md").as_deref(),
+            Some("a/c/other.md")
+        );
+        assert_eq!(
+            resolve_vault_path(Some("a/note.md"), "/top.md").as_deref(),
+            Some("top.md")
+        );
+        assert_eq!(resolve_vault_path(Some("note.md"), "../out.md"), None);
+    }
+
     #[test]
     fn test_wikilink_with_fragment() {
         let slug_map =
@@ -0,0 +1,286 @@
+//! Unlinked mentions: plain-text occurrences of a note's title or aliases in
+//! other notes that could become `[[wikilinks]]`.
+
+use crate::markdown::{HeadingIds, MarkdownProcessor};
+use crate::models::{Note, NoteType, SiteIndex};
+use once_cell::sync::Lazy;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// `[[wikilinks]]` and `![[embeds]]` are links already, not mentions
+static WIKILINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!?\[\[[^\]]*\]\]").unwrap());
+
+/// Characters of context kept on each side of a mention in its snippet
+const SNIPPET_CONTEXT: usize = 60;
+
+/// A plain-text occurrence of a note's title or alias in another note
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mention {
+    /// Note containing the mention
+    pub slug: String,
+    pub title: String,
+    /// Heading of the section containing the mention (empty for top matter)
+    pub section: String,
+    /// Heading id of that section, for linking to it
+    pub anchor: Option<String>,
+    /// Text that matched, as written
+    pub text: String,
+    /// Surrounding prose with the match
+    pub snippet: String,
+}
+
+/// Prose of one note, split into runs of text outside code and links
+struct SourceText {
+    slug: String,
+    title: String,
+    /// (section heading, heading id, text)
+    runs: Vec<(String, Option<String>, String)>,
+}
+
+/// Searchable prose of every published note, extracted once so many notes
+/// can be checked for mentions without re-parsing the vault.
+pub struct MentionIndex {
+    sources: Vec<SourceText>,
+}
+
+impl MentionIndex {
+    pub fn new(site_index: &SiteIndex) -> Self {
+        let options = MarkdownProcessor::new().options();
+        let sources = site_index
+            .notes
+            .iter()
+            .filter(|note| !note.is_draft() && note.note_type != NoteType::Comment)
+            .filter_map(|note| {
+                let body = note.raw_body.as_deref()?;
+                Some(SourceText {
+                    slug: note.slug.clone(),
+                    title: note.title.clone(),
+                    runs: prose_runs(body, options),
+                })
+            })
+            .collect();
+        Self { sources }
+    }
+
+    /// Unlinked mentions of `target`'s title or aliases in other notes, in
+    /// note order
+    pub fn mentions_of(&self, target: &Note) -> Vec<Mention> {
+        let Some(pattern) = mention_pattern(target) else {
+            return Vec::new();
+        };
+
+        let mut mentions = Vec::new();
+        for source in self.sources.iter().filter(|s| s.slug != target.slug) {
+            for (section, anchor, text) in &source.runs {
+                for found in pattern.find_iter(text) {
+                    if !at_word_boundaries(text, found.start(), found.end()) {
+                        continue;
+                    }
+                    mentions.push(Mention {
+                        slug: source.slug.clone(),
+                        title: source.title.clone(),
+                        section: section.clone(),
+                        anchor: anchor.clone(),
+                        text: found.as_str().to_string(),
+                        snippet: snippet(text, found.start(), found.end()),
+                    });
+                }
+            }
+        }
+        mentions
+    }
+}
+
+/// Case-insensitive pattern for a note's title and aliases, longest first so
+/// "Rust Safety" wins over "Rust"
+fn mention_pattern(note: &Note) -> Option<Regex> {
+    let mut terms: Vec<String> = std::iter::once(&note.title)
+        .chain(&note.aliases)
+        .map(|term| term.trim().to_string())
+        .filter(|term| !term.is_empty())
+        .collect();
+    terms.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    terms.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    if terms.is_empty() {
+        return None;
+    }
+
+    let alternation: Vec<String> = terms.iter().map(|term| regex::escape(term)).collect();
+    Regex::new(&format!("(?i){}", alternation.join("|"))).ok()
+}
+
+fn at_word_boundaries(text: &str, start: usize, end: usize) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    !text[..start].chars().next_back().is_some_and(is_word)
+        && !text[end..].chars().next().is_some_and(is_word)
+}
+
+/// Runs of prose in `markdown` with the section each belongs to. Code,
+/// links, images, raw HTML and wikilinks are left out.
+fn prose_runs(
+    markdown: &str,
+    options: pulldown_cmark::Options,
+) -> Vec<(String, Option<String>, String)> {
+    let mut runs = Vec::new();
+    let mut section = String::new();
+    let mut anchor = None;
+    let mut heading: Option<String> = None;
+    let mut ids = HeadingIds::default();
+    // Nesting depth of code blocks, links and images
+    let mut skip = 0usize;
+    let mut current = String::new();
+
+    let mut flush = |current: &mut String, section: &str, anchor: &Option<String>| {
+        let text = WIKILINK_RE.replace_all(current, "\u{FFFC}");
+        for part in text.split('\u{FFFC}') {
+            if !part.trim().is_empty() {
+                runs.push((section.to_string(), anchor.clone(), part.to_string()));
+            }
+        }
+        current.clear();
+    };
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Text(text) if skip == 0 => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+                current.push_str(&text);
+            }
+            Event::Code(code) if heading.is_some() => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.push_str(&code);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak if skip == 0 => current.push(' '),
+            Event::Start(Tag::CodeBlock(_) | Tag::Link { .. } | Tag::Image { .. }) => {
+                flush(&mut current, &section, &anchor);
+                skip += 1;
+            }
+            Event::End(TagEnd::CodeBlock | TagEnd::Link | TagEnd::Image) => {
+                skip = skip.saturating_sub(1);
+            }
+            Event::Start(Tag::Heading { id, .. }) => {
+                flush(&mut current, &section, &anchor);
+                heading = Some(String::new());
+                anchor = id.map(|id| {
+                    ids.reserve(&id);
+                    id.to_string()
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let title = heading.take().unwrap_or_default();
+                anchor = anchor.or_else(|| Some(ids.unique(&title)));
+                flush(&mut current, &title, &anchor);
+                section = title;
+            }
+            // Emphasis doesn't interrupt a run of prose
+            Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough)
+            | Event::End(TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough)
+            | Event::Text(_)
+            | Event::SoftBreak
+            | Event::HardBreak => {}
+            _ => flush(&mut current, &section, &anchor),
+        }
+    }
+    flush(&mut current, &section, &anchor);
+
+    runs
+}
+
+/// `text` trimmed to a window around `start..end`, on char boundaries
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let mut from = start.saturating_sub(SNIPPET_CONTEXT);
+    while !text.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (end + SNIPPET_CONTEXT).min(text.len());
+    while !text.is_char_boundary(to) {
+        to += 1;
+    }
+
+    let mut out = String::new();
+    if from > 0 {
+        out.push('…');
+    }
+    out.push_str(text[from..to].trim());
+    if to < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(slug: &str, title: &str, aliases: &[&str], body: &str) -> Note {
+        Note {
+            slug: slug.into(),
+            title: title.into(),
+            content_html: "".into(),
+            frontmatter: Default::default(),
+            note_type: NoteType::Essay,
+            tags: vec![],
+            date: None,
+            updated: None,
+            date_time: None,
+            updated_time: None,
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            permalink: None,
+            outgoing_links: vec![],
+            embeds: vec![],
+            block_ids: vec![],
+            nota_labels: vec![],
+            assets: vec![],
+            preview: None,
+            toc_html: None,
+            raw_body: Some(body.into()),
+            source_path: None,
+        }
+    }
+
+    #[test]
+    fn finds_titles_and_aliases_outside_code_and_links() {
+        let target = note("rust-safety", "Rust Safety", &["borrowck"], "Body.");
+        let source = note(
+            "essay",
+            "Essay",
+            &[],
+            "Intro on rust safety.\n\n## Details\n\nThe BORROWCK helps. Already [[Rust Safety]] \
+             and [Rust Safety](rust-safety.html).\n\n```\nRust Safety\n```\n\n`Rust Safety`\n\n\
+             Not a mention: borrowcking.\n",
+        );
+        let index = SiteIndex {
+            notes: vec![target.clone(), source],
+            ..SiteIndex::new()
+        };
+
+        let mentions = MentionIndex::new(&index).mentions_of(&target);
+        let found: Vec<_> = mentions
+            .iter()
+            .map(|m| (m.text.as_str(), m.section.as_str(), m.anchor.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("rust safety", "", None),
+                ("BORROWCK", "Details", Some("details"))
+            ]
+        );
+        assert_eq!(mentions[0].snippet, "Intro on rust safety.");
+        assert_eq!(mentions[1].slug, "essay");
+    }
+
+    #[test]
+    fn long_text_is_trimmed_into_a_snippet() {
+        let text = format!("{} Rust Safety {}", "a".repeat(100), "b".repeat(100));
+        let start = text.find("Rust").unwrap();
+        let out = snippet(&text, start, start + "Rust Safety".len());
+        assert!(out.starts_with('…') && out.ends_with('…'));
+        assert!(out.contains("Rust Safety"));
+        assert!(out.chars().count() < 150);
+    }
+}
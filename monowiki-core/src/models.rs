@@ -1,21 +1,28 @@
 //! Content model structs for notes, links, and site index.
 
-use chrono::NaiveDate;
+use crate::markdown::NotaLabel;
+use chrono::{DateTime, FixedOffset, NaiveDate};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-
-/// Type of note content
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Type of note content.
+///
+/// The built-in types have their own variants; any other type must be
+/// declared under `note_types` in monowiki.yml and is carried as `Custom`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum NoteType {
     Essay,
     Thought,
     Draft,
     Doc, // For code documentation
     Comment,
+    Custom(String),
 }
 
 impl NoteType {
+    /// Parse a built-in type name (case-insensitive)
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "essay" => Some(NoteType::Essay),
@@ -27,15 +34,37 @@ impl NoteType {
         }
     }
 
-    pub fn as_str(&self) -> &'static str {
+    /// The built-in type with this name, or a custom type
+    pub fn named(s: &str) -> Self {
+        Self::from_str(s).unwrap_or_else(|| NoteType::Custom(s.trim().to_lowercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
         match self {
             NoteType::Essay => "essay",
             NoteType::Thought => "thought",
             NoteType::Draft => "draft",
             NoteType::Doc => "doc",
             NoteType::Comment => "comment",
+            NoteType::Custom(name) => name,
         }
     }
+
+    pub fn is_builtin(&self) -> bool {
+        !matches!(self, NoteType::Custom(_))
+    }
+}
+
+impl From<String> for NoteType {
+    fn from(s: String) -> Self {
+        Self::named(&s)
+    }
+}
+
+impl From<NoteType> for String {
+    fn from(note_type: NoteType) -> Self {
+        note_type.as_str().to_string()
+    }
 }
 
 /// Frontmatter metadata from markdown files
@@ -65,6 +94,14 @@ pub struct Frontmatter {
     #[serde(default)]
     pub updated: Option<String>,
 
+    /// Leave the note out of the site until this date
+    #[serde(default)]
+    pub publish_after: Option<String>,
+
+    /// Leave the note out of the site from this date on
+    #[serde(default)]
+    pub expires: Option<String>,
+
     #[serde(default)]
     pub slug: Option<String>,
 
@@ -74,9 +111,26 @@ pub struct Frontmatter {
     #[serde(default)]
     pub aliases: Vec<String>,
 
+    /// Retired URLs (e.g. "old/path/" or "old-name.html") that should
+    /// redirect to this note
+    #[serde(default)]
+    pub redirect_from: Vec<String>,
+
+    /// Set to `false` to leave the table of contents off this note
+    #[serde(default)]
+    pub toc: Option<bool>,
+
     #[serde(default)]
     pub bibliography: Vec<String>,
 
+    /// Citation style for this note, overriding `citation_style` from the config
+    #[serde(default)]
+    pub citation_style: Option<String>,
+
+    /// LaTeX macros for this note, overriding `math.macros` from the config
+    #[serde(default)]
+    pub macros: BTreeMap<String, String>,
+
     #[serde(default)]
     pub target_slug: Option<String>,
 
@@ -152,6 +206,14 @@ pub struct Note {
     /// Last updated date
     pub updated: Option<NaiveDate>,
 
+    /// Publication timestamp, when `date` includes a time of day
+    #[serde(default)]
+    pub date_time: Option<DateTime<FixedOffset>>,
+
+    /// Last updated timestamp, when `updated` includes a time of day
+    #[serde(default)]
+    pub updated_time: Option<DateTime<FixedOffset>>,
+
     /// Alternative slugs/names
     pub aliases: Vec<String>,
 
@@ -161,6 +223,22 @@ pub struct Note {
     /// Slugs of notes this note links to
     pub outgoing_links: Vec<String>,
 
+    /// Slugs of notes transcluded into this note with `![[note]]`
+    #[serde(default)]
+    pub embeds: Vec<String>,
+
+    /// Block ids (`^block-id`) defined in this note
+    #[serde(default)]
+    pub block_ids: Vec<String>,
+
+    /// Labelled nota blocks (theorems, definitions...) defined in this note
+    #[serde(default)]
+    pub nota_labels: Vec<NotaLabel>,
+
+    /// Vault-relative paths of assets this note references
+    #[serde(default)]
+    pub assets: Vec<String>,
+
     /// Preview text (for link previews)
     pub preview: Option<String>,
 
@@ -209,6 +287,14 @@ pub struct LinkGraph {
 
     /// Map from slug to list of source slugs (backlinks)
     pub incoming: HashMap<String, Vec<String>>,
+
+    /// Map from slug to list of transcluded slugs
+    #[serde(default)]
+    pub embeds: HashMap<String, Vec<String>>,
+
+    /// Map from slug to list of slugs that transclude it
+    #[serde(default)]
+    pub embedded_by: HashMap<String, Vec<String>>,
 }
 
 impl LinkGraph {
@@ -229,6 +315,19 @@ impl LinkGraph {
             .push(source.to_string());
     }
 
+    /// Add a transclusion edge from source to the embedded target
+    pub fn add_embed(&mut self, source: &str, target: &str) {
+        self.embeds
+            .entry(source.to_string())
+            .or_default()
+            .push(target.to_string());
+
+        self.embedded_by
+            .entry(target.to_string())
+            .or_default()
+            .push(source.to_string());
+    }
+
     /// Get backlinks for a given note slug
     pub fn backlinks(&self, slug: &str) -> Vec<String> {
         self.incoming.get(slug).cloned().unwrap_or_default()
@@ -238,6 +337,11 @@ impl LinkGraph {
     pub fn outgoing(&self, slug: &str) -> Vec<String> {
         self.outgoing.get(slug).cloned().unwrap_or_default()
     }
+
+    /// Get slugs of notes that transclude the given note
+    pub fn embedded_by(&self, slug: &str) -> Vec<String> {
+        self.embedded_by.get(slug).cloned().unwrap_or_default()
+    }
 }
 
 /// Complete site index containing all notes and the link graph
@@ -249,6 +353,19 @@ pub struct SiteIndex {
     pub diagnostics: Vec<Diagnostic>,
     #[serde(default)]
     pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub redirects: Vec<Redirect>,
+}
+
+/// A redirect stub written at an alias's or retired URL's output path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redirect {
+    /// Output path of the stub, relative to the output directory
+    pub from: String,
+    /// URL of the note it points at, including the base URL
+    pub to: String,
+    /// Slug of that note
+    pub slug: String,
 }
 
 impl SiteIndex {
@@ -258,6 +375,7 @@ impl SiteIndex {
             graph: LinkGraph::new(),
             diagnostics: Vec::new(),
             comments: Vec::new(),
+            redirects: Vec::new(),
         }
     }
 
@@ -270,7 +388,7 @@ impl SiteIndex {
     pub fn find_by_permalink(&self, permalink: &str) -> Option<&Note> {
         self.notes
             .iter()
-            .find(|n| n.permalink.as_ref().map(|p| p.as_str()) == Some(permalink))
+            .find(|n| n.permalink.as_deref() == Some(permalink))
     }
 
     /// Find a note by alias
@@ -303,6 +421,48 @@ impl SiteIndex {
             .filter(|n| !n.is_draft() && n.note_type == NoteType::Doc)
             .collect()
     }
+
+    /// Published notes under each tag (drafts and comments excluded).
+    ///
+    /// Nested tags roll up into their parents, so a note tagged `rust/async`
+    /// is listed under both `rust/async` and `rust`.
+    pub fn notes_by_tag(&self) -> BTreeMap<String, Vec<&Note>> {
+        let mut tags: BTreeMap<String, Vec<&Note>> = BTreeMap::new();
+        for note in self
+            .notes
+            .iter()
+            .filter(|n| !n.is_draft() && n.note_type != NoteType::Comment)
+        {
+            let mut seen = HashSet::new();
+            for tag in note.tags.iter().flat_map(|t| tag_hierarchy(t)) {
+                if seen.insert(tag.clone()) {
+                    tags.entry(tag).or_default().push(note);
+                }
+            }
+        }
+        tags
+    }
+
+    /// Number of published notes under each tag, including nested tags
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        self.notes_by_tag()
+            .into_iter()
+            .map(|(tag, notes)| (tag, notes.len()))
+            .collect()
+    }
+}
+
+/// A tag followed by each of its parents, normalized to lowercase
+/// (`Rust/Async` -> `rust`, `rust/async`)
+pub fn tag_hierarchy(tag: &str) -> Vec<String> {
+    let segments: Vec<String> = tag
+        .split('/')
+        .map(|segment| segment.trim().to_lowercase())
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    (1..=segments.len())
+        .map(|depth| segments[..depth].join("/"))
+        .collect()
 }
 
 impl Default for SiteIndex {
@@ -321,6 +481,12 @@ mod tests {
         assert_eq!(NoteType::from_str("THOUGHT"), Some(NoteType::Thought));
         assert_eq!(NoteType::from_str("Draft"), Some(NoteType::Draft));
         assert_eq!(NoteType::from_str("invalid"), None);
+        assert_eq!(NoteType::named("RFC"), NoteType::Custom("rfc".into()));
+
+        let json = serde_json::to_string(&NoteType::Custom("paper".into())).unwrap();
+        assert_eq!(json, "\"paper\"");
+        let parsed: NoteType = serde_json::from_str("\"thought\"").unwrap();
+        assert_eq!(parsed, NoteType::Thought);
     }
 
     #[test]
@@ -338,6 +504,57 @@ mod tests {
         assert!(graph
             .backlinks("memory-model")
             .contains(&"ownership".to_string()));
+
+        // Embeds are tracked separately from links
+        graph.add_embed("ownership", "boilerplate");
+        assert_eq!(graph.embedded_by("boilerplate"), vec!["ownership"]);
+        assert!(graph.backlinks("boilerplate").is_empty());
+        assert_eq!(graph.outgoing("ownership").len(), 1);
+    }
+
+    #[test]
+    fn nested_tags_roll_up_into_parents() {
+        assert_eq!(tag_hierarchy(" Rust/Async/ "), vec!["rust", "rust/async"]);
+
+        let note = |slug: &str, tags: &[&str], note_type: NoteType| Note {
+            slug: slug.into(),
+            title: slug.into(),
+            content_html: "".into(),
+            frontmatter: Frontmatter::default(),
+            note_type,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            date: None,
+            updated: None,
+            date_time: None,
+            updated_time: None,
+            aliases: vec![],
+            permalink: None,
+            outgoing_links: vec![],
+            embeds: vec![],
+            block_ids: vec![],
+            nota_labels: vec![],
+            assets: vec![],
+            preview: None,
+            toc_html: None,
+            raw_body: None,
+            source_path: None,
+        };
+        let index = SiteIndex {
+            notes: vec![
+                note("tokio", &["rust/async"], NoteType::Essay),
+                note("ownership", &["rust", "rust/memory"], NoteType::Essay),
+                note("both", &["rust", "rust/async"], NoteType::Thought),
+                note("wip", &["rust"], NoteType::Draft),
+                note("reply", &["rust"], NoteType::Comment),
+            ],
+            ..SiteIndex::new()
+        };
+
+        let counts = index.tag_counts();
+        assert_eq!(counts["rust"], 3);
+        assert_eq!(counts["rust/async"], 2);
+        assert_eq!(counts["rust/memory"], 1);
+        assert_eq!(counts.len(), 3);
     }
 
     #[test]
@@ -351,9 +568,15 @@ mod tests {
             tags: vec![],
             date: None,
             updated: None,
+            date_time: None,
+            updated_time: None,
             aliases: vec![],
             permalink: None,
             outgoing_links: vec![],
+            embeds: vec![],
+            block_ids: vec![],
+            nota_labels: vec![],
+            assets: vec![],
             preview: None,
             toc_html: None,
             raw_body: None,
@@ -376,11 +599,11 @@ mod tests {
     }
 }
 
-fn normalize_permalink(permalink: &str) -> String {
+pub(crate) fn normalize_permalink(permalink: &str) -> String {
     let mut p = permalink.trim().trim_start_matches('/').to_string();
 
-    if p.ends_with('/') {
-        p = format!("{}index.html", p.trim_end_matches('/'));
+    if p.is_empty() || p.ends_with('/') {
+        p = format!("{}index.html", p);
     } else if !p.ends_with(".html") {
         p = format!("{}.html", p);
     }
@@ -423,21 +646,41 @@ pub struct Diagnostic {
     /// Optional related anchor/id
     #[serde(default)]
     pub anchor: Option<String>,
+
+    /// Location within the source file (if known)
+    #[serde(default)]
+    pub span: Option<SourceSpan>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Line/column range within a source file (1-based, inclusive start)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl SourceSpan {
+    /// A zero-width span at a single position
+    pub fn point(line: usize, column: usize) -> Self {
+        Self {
+            line,
+            column,
+            end_line: line,
+            end_column: column,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum CommentStatus {
+    #[default]
     Open,
     Resolved,
 }
 
-impl Default for CommentStatus {
-    fn default() -> Self {
-        CommentStatus::Open
-    }
-}
-
 impl std::fmt::Display for CommentStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
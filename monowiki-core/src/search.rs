@@ -1,5 +1,5 @@
-use blake3;
-///! Section-level search indexing for precise search results
+//! Section-level search indexing for precise search results
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -236,13 +236,16 @@ pub struct SectionDigest {
 }
 
 /// Extract section digests (stable IDs + hashes) from rendered HTML
+///
+/// Headed sections come first, followed by one digest per nota block
+/// (theorems, proofs, ...) so comments can anchor to a single block.
 pub fn section_digests_from_html(
     slug: &str,
     title: &str,
     content_html: &str,
 ) -> Vec<SectionDigest> {
     let entries = build_search_index(slug, title, content_html, &[], "", "/");
-    entries
+    let mut digests: Vec<SectionDigest> = entries
         .into_iter()
         .map(|entry| SectionDigest {
             section_id: entry.section_id,
@@ -250,7 +253,71 @@ pub fn section_digests_from_html(
             hash: entry.section_hash,
             anchor_id: entry.id.split('#').nth(1).map(|s| s.to_string()),
         })
-        .collect()
+        .collect();
+
+    for (heading, id, text) in extract_blocks_from_html(content_html) {
+        let hash = compute_section_hash(&text);
+        digests.push(SectionDigest {
+            section_id: format!("{}-{}", id, &hash[..8]),
+            heading,
+            hash,
+            anchor_id: Some(id),
+        });
+    }
+    digests
+}
+
+/// Nota blocks in rendered HTML as (heading, id, text), outermost first
+fn extract_blocks_from_html(html: &str) -> Vec<(String, String, String)> {
+    const OPEN: &str = "<div class=\"nota-block ";
+    let mut blocks = Vec::new();
+
+    for (start, _) in html.match_indices(OPEN) {
+        let Some(tag_len) = html[start..].find('>') else {
+            continue;
+        };
+        let tag = &html[start..start + tag_len];
+        let Some(id) = tag
+            .split("id=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .filter(|id| !id.is_empty())
+        else {
+            continue;
+        };
+
+        // Find the matching `</div>`, counting nested divs
+        let body_start = start + tag_len + 1;
+        let mut depth = 1;
+        let mut pos = body_start;
+        let mut body_end = html.len();
+        while let Some(close) = html[pos..].find("</div>").map(|i| pos + i) {
+            match html[pos..close].find("<div") {
+                Some(open) => {
+                    depth += 1;
+                    pos += open + 4;
+                }
+                None => {
+                    depth -= 1;
+                    pos = close + 6;
+                    if depth == 0 {
+                        body_end = close;
+                        break;
+                    }
+                }
+            }
+        }
+        let inner = &html[body_start..body_end];
+
+        let heading = inner
+            .strip_prefix("<div class=\"nota-block-heading\">")
+            .and_then(|rest| rest.split("</div>").next())
+            .map(html_to_text)
+            .unwrap_or_default();
+        blocks.push((heading, id.to_string(), html_to_text(inner)));
+    }
+
+    blocks
 }
 
 #[cfg(test)]
@@ -280,4 +347,29 @@ mod tests {
         let sections = extract_sections_from_html(html);
         assert!(sections.len() >= 2);
     }
+
+    #[test]
+    fn test_nota_blocks_are_section_digests() {
+        let html = concat!(
+            r#"<h2 id="results">Results</h2>"#,
+            r#"<div class="nota-block nota-example" id="ex"><div class="nota-block-heading"><span class="nota-kind">Example</span><span class="nota-number">1</span></div><div class="nota-block-body"><p>Setup.</p>"#,
+            r#"<div class="nota-block nota-proof" id="pf"><div class="nota-block-heading"><span class="nota-kind">Proof</span></div><div class="nota-block-body"><p>Done.</p></div></div>"#,
+            r#"</div></div><p>After.</p>"#,
+        );
+        let digests = section_digests_from_html("note", "Note", html);
+        let anchors: Vec<_> = digests
+            .iter()
+            .filter_map(|d| d.anchor_id.as_deref())
+            .collect();
+        assert_eq!(anchors, vec!["results", "ex", "pf"]);
+
+        let example = &digests[1];
+        assert_eq!(example.heading, "Example 1");
+        assert!(example.section_id.starts_with("ex-"));
+        assert_eq!(digests[2].heading, "Proof");
+        assert_eq!(
+            example.hash,
+            compute_section_hash("Example 1 Setup. Proof Done.")
+        );
+    }
 }
@@ -63,6 +63,16 @@ pub fn normalize_slug(slug: &str) -> String {
     slugify(slug)
 }
 
+/// Output path segment for a (possibly nested) tag: each `/`-separated
+/// level is slugified, so `Rust/Async IO` becomes `rust/async-io`
+pub fn tag_slug(tag: &str) -> String {
+    tag.split('/')
+        .map(slugify)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,4 +135,11 @@ mod tests {
         assert_eq!(normalize_slug("Already-Good"), "already-good");
         assert_eq!(normalize_slug("Needs_Fixing"), "needs-fixing");
     }
+
+    #[test]
+    fn test_tag_slug() {
+        assert_eq!(tag_slug("Rust/Async IO"), "rust/async-io");
+        assert_eq!(tag_slug("c++"), "c");
+        assert_eq!(tag_slug("/lead//trail/"), "lead/trail");
+    }
 }
@@ -8,5 +8,6 @@ pub mod templates;
 
 pub use templates::{
     ApiTemplate, Author, BacklinkEntry, CommentRender, DirectoryNode, FileNode, IndexTemplate,
-    NotFoundTemplate, NoteEntry, Paper, PostTemplate,
+    MentionEntry, NotFoundTemplate, NoteEntry, Paper, PostTemplate, RedirectTemplate, TagEntry,
+    TagIndexTemplate, TagTemplate,
 };
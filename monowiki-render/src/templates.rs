@@ -12,6 +12,17 @@ pub struct NoteEntry {
     pub note_type: String,
 }
 
+/// A tag with its listing page and the number of notes under it
+#[derive(Debug, Clone)]
+pub struct TagEntry {
+    pub name: String,
+    pub url: String,
+    /// Notes under this tag, including nested tags; 0 if it has no page
+    pub count: usize,
+    /// Nesting level (`rust/async` is 1)
+    pub depth: usize,
+}
+
 /// A file entry in the directory tree
 #[derive(Debug, Clone)]
 pub struct FileNode {
@@ -179,6 +190,16 @@ pub struct BacklinkEntry {
     pub title: String,
 }
 
+/// A plain-text mention of the page's title in another note
+#[derive(Debug, Clone)]
+pub struct MentionEntry {
+    pub url: String,
+    pub title: String,
+    /// Section heading (empty for top matter)
+    pub section: String,
+    pub snippet: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct CommentRender {
     pub id: String,
@@ -210,7 +231,7 @@ pub struct PostTemplate {
     pub description: String,
     pub date: Option<String>,
     pub updated: Option<String>,
-    pub tags: Vec<String>,
+    pub tags: Vec<TagEntry>,
 
     // Content
     pub content: String,
@@ -234,6 +255,9 @@ pub struct PostTemplate {
     // Backlinks
     pub backlinks: Vec<BacklinkEntry>,
 
+    // Unlinked mentions (empty unless enable_mentions is set)
+    pub mentions: Vec<MentionEntry>,
+
     // Site base URL and current slug (for frontend scripts)
     pub base_url: String,
     pub slug: String,
@@ -246,6 +270,12 @@ pub struct PostTemplate {
 
     // Pre-computed flag for whether any unanchored comments exist
     pub has_unanchored_comments: bool,
+
+    // Whether the page loads MathJax (math not rendered to MathML at build time)
+    pub mathjax: bool,
+
+    // MathJax `tex.macros` as a JSON object
+    pub mathjax_macros: String,
 }
 
 /// API documentation page template
@@ -361,3 +391,70 @@ pub struct NotFoundTemplate {
     // Site base URL (for frontend scripts)
     pub base_url: String,
 }
+
+/// Stub left at an alias's or retired URL's path, pointing at the note
+#[derive(Template)]
+#[template(path = "redirect.html")]
+pub struct RedirectTemplate {
+    pub site_title: String,
+    /// Title of the note redirected to
+    pub title: String,
+    pub target_url: String,
+    /// Absolute URL of the note, for search engines
+    pub canonical_url: String,
+}
+
+/// Tag index page template
+#[derive(Template)]
+#[template(path = "tags.html")]
+pub struct TagIndexTemplate {
+    // Every tag, sorted so nested tags follow their parent
+    pub tags: Vec<TagEntry>,
+
+    // Site metadata
+    pub site_title: String,
+    pub site_author: String,
+    pub year: i32,
+
+    // Navigation
+    pub nav_home: String,
+    pub nav_about: String,
+    pub nav_github: String,
+    pub has_about: bool,
+    pub has_github: bool,
+
+    // Path adjustments
+    pub css_path: String,
+
+    // Site base URL (for frontend scripts)
+    pub base_url: String,
+}
+
+/// Per-tag listing page template
+#[derive(Template)]
+#[template(path = "tag.html")]
+pub struct TagTemplate {
+    pub tag: String,
+    // Notes under this tag (or a nested tag), newest first
+    pub items: Vec<NoteEntry>,
+    // Direct children of this tag
+    pub subtags: Vec<TagEntry>,
+
+    // Site metadata
+    pub site_title: String,
+    pub site_author: String,
+    pub year: i32,
+
+    // Navigation
+    pub nav_home: String,
+    pub nav_about: String,
+    pub nav_github: String,
+    pub has_about: bool,
+    pub has_github: bool,
+
+    // Path adjustments
+    pub css_path: String,
+
+    // Site base URL (for frontend scripts)
+    pub base_url: String,
+}